@@ -0,0 +1,245 @@
+use std::convert::TryInto;
+
+use libc::{NT_PRSTATUS, NT_PRFPREG, user_fpsimd_struct};
+
+use crate::ptrace;
+use super::{RegisterDesc, RegisterFile, WatchKind, WatchSlot, register};
+
+pub type Regs = libc::user_regs_struct;
+
+// Watchpoint registers in 'struct user_hwdebug_state': dbg_info and padding,
+// followed by (addr, ctrl, padding) of each register
+const NT_ARM_HW_WATCH: i32 = 0x403;
+const HWDEBUG_HEADER_SIZE: usize = 8;
+const HWDEBUG_REG_SIZE: usize = 16;
+const HWDEBUG_MAX_REGS: usize = 16;
+
+// si_code of SIGTRAP caused by hardware breakpoints and watchpoints
+const TRAP_HWBKPT: i32 = 4;
+
+// Defines RegisterDesc of 'x@index' in the 'regs' array
+macro_rules! xregister {
+    ($name: expr, $index: expr) => {
+        RegisterDesc {
+            name: $name,
+            get: |regs| regs.regs[$index],
+            set: |regs, value| regs.regs[$index] = value,
+        }
+    };
+}
+
+// General purpose registers in the order of 'info regs'
+const GENERAL_REGISTERS: &[RegisterDesc<Regs>] = &[
+    xregister!("x0", 0),   xregister!("x1", 1),   xregister!("x2", 2),   xregister!("x3", 3),
+    xregister!("x4", 4),   xregister!("x5", 5),   xregister!("x6", 6),   xregister!("x7", 7),
+    xregister!("x8", 8),   xregister!("x9", 9),   xregister!("x10", 10), xregister!("x11", 11),
+    xregister!("x12", 12), xregister!("x13", 13), xregister!("x14", 14), xregister!("x15", 15),
+    xregister!("x16", 16), xregister!("x17", 17), xregister!("x18", 18), xregister!("x19", 19),
+    xregister!("x20", 20), xregister!("x21", 21), xregister!("x22", 22), xregister!("x23", 23),
+    xregister!("x24", 24), xregister!("x25", 25), xregister!("x26", 26), xregister!("x27", 27),
+    xregister!("x28", 28), xregister!("x29", 29), xregister!("x30", 30),
+    register!(sp), register!(pc), register!(pstate),
+];
+
+impl RegisterFile for Regs {
+    const PC: &'static str = "pc";
+    const SP: &'static str = "sp";
+    const SYSCALL_NR: &'static str = "x8";
+    const SYSCALL_ARGS: [&'static str; 6] = ["x0", "x1", "x2", "x3", "x4", "x5"];
+    const SYSCALL_RET: &'static str = "x0";
+    const BREAKPOINT_INSN: &'static [u8] = &[0x00, 0x00, 0x20, 0xd4]; // brk #0
+    const BREAKPOINT_PC_OFFSET: u64 = 0;
+    const LINK_REGISTER: Option<&'static str> = Some("x30");
+    const ELF_MACHINE: u16 = 183; // EM_AARCH64
+    const MAX_INSN_SIZE: usize = 4;
+    const WATCH_BEFORE_ACCESS: bool = true;
+    const WATCH_READ_ONLY: bool = true;
+
+    // procedure call standard names of x29 and x30
+    const ALIASES: &'static [(&'static str, &'static str)] = &[("fp", "x29"), ("lr", "x30")];
+
+    fn registers() -> &'static [RegisterDesc<Self>] { GENERAL_REGISTERS }
+
+    // PTRACE_GETREGS doesn't exist on AArch64
+    fn read(target: i32) -> Result<Self, ()> {
+        ptrace::getregset_as(target, NT_PRSTATUS)
+    }
+
+    fn write(&self, target: i32) -> Result<(), ()> {
+        ptrace::setregset(target, NT_PRSTATUS, self).map(|_| ())
+    }
+
+    fn vector_registers(target: i32) -> Result<Vec<(String, String)>, ()> {
+        let fpregs: user_fpsimd_struct = ptrace::getregset_as(target, NT_PRFPREG)?;
+        let mut list = Vec::new();
+        for (i, vreg) in fpregs.vregs.iter().enumerate() {
+            list.push((format!("v{}", i), format!("{:#034x}", vreg)));
+        }
+        list.push(("fpsr".to_string(), format!("{:#x}", fpregs.fpsr)));
+        list.push(("fpcr".to_string(), format!("{:#x}", fpregs.fpcr)));
+        Ok(list)
+    }
+
+    fn call_length(code: &[u8]) -> Option<usize> {
+        let insn = u32::from_le_bytes([*code.first()?, *code.get(1)?, *code.get(2)?, *code.get(3)?]);
+        let is_bl = insn & 0xfc00_0000 == 0x9400_0000;
+        let is_blr = insn & 0xffff_fc1f == 0xd63f_0000;
+        match is_bl || is_blr {
+            true => Some(4),
+            false => None,
+        }
+    }
+
+    // Byte address select covers up to 8 bytes within a doubleword
+    fn watch_chunk(addr: u64, len: usize) -> usize {
+        std::cmp::min(len, 8 - (addr % 8) as usize)
+    }
+
+    fn watch_slots(target: i32) -> Result<usize, ()> {
+        let state = read_hwdebug_state(target)?;
+        Ok(std::cmp::min(state[0] as usize, HWDEBUG_MAX_REGS))
+    }
+
+    fn set_watch_slots(target: i32, slots: &[WatchSlot]) -> Result<(), ()> {
+        let count = Self::watch_slots(target)?;
+        if slots.len() > count {
+            return Err(());
+        }
+
+        // registers beyond the hardware ones are rejected, write only those
+        let mut state = vec![0u8; HWDEBUG_HEADER_SIZE + count * HWDEBUG_REG_SIZE];
+        for (i, slot) in slots.iter().enumerate() {
+            let offset = HWDEBUG_HEADER_SIZE + i * HWDEBUG_REG_SIZE;
+            let (addr, ctrl) = encode_slot(slot);
+            state[offset..offset + 8].copy_from_slice(&addr.to_le_bytes());
+            state[offset + 8..offset + 12].copy_from_slice(&ctrl.to_le_bytes());
+        }
+        ptrace::setregset_bytes(target, NT_ARM_HW_WATCH, &state).map(|_| ())
+    }
+
+    // The trap reports the accessed address, which is matched against the doublewords watched
+    fn watch_hit(target: i32) -> Result<Option<usize>, ()> {
+        let info = ptrace::getsiginfo(target)?;
+        if info.si_code != TRAP_HWBKPT {
+            return Ok(None);
+        }
+        let addr = unsafe { info.si_addr() } as u64;
+        Ok(hit_slot(&read_hwdebug_state(target)?, addr))
+    }
+
+    fn decode(name: &str, value: u64) -> Option<String> {
+        match name {
+            "pstate" => Some(decode_pstate(value)),
+            _ => None,
+        }
+    }
+}
+
+// Doubleword address and control of the watchpoint register for @slot,
+// byte address select picks the bytes watched in the doubleword
+fn encode_slot(slot: &WatchSlot) -> (u64, u32) {
+    let bas = ((1u32 << slot.len) - 1) << (slot.addr % 8);
+    let lsc = match slot.kind {
+        WatchKind::Read => 0b01,
+        WatchKind::Write => 0b10,
+        WatchKind::Access => 0b11,
+    };
+    // enabled at EL0
+    (slot.addr & !7, bas << 5 | lsc << 3 | 0b10 << 1 | 1)
+}
+
+// Enabled watchpoint register watching the doubleword @addr is in, None if no one does
+// since the trap came from something else
+fn hit_slot(state: &[u8], addr: u64) -> Option<usize> {
+    state.get(HWDEBUG_HEADER_SIZE..)?
+        .chunks_exact(HWDEBUG_REG_SIZE)
+        .position(|reg| reg[8] & 1 != 0 && u64::from_le_bytes(reg[..8].try_into().unwrap()) == addr & !7)
+}
+
+// Raw 'struct user_hwdebug_state' of the watchpoint registers
+fn read_hwdebug_state(target: i32) -> Result<Vec<u8>, ()> {
+    let mut state = vec![0u8; HWDEBUG_HEADER_SIZE + HWDEBUG_MAX_REGS * HWDEBUG_REG_SIZE];
+    let size = ptrace::getregset(target, NT_ARM_HW_WATCH, &mut state)?;
+    state.truncate(size);
+    Ok(state)
+}
+
+// e.g) '[ N Z EL0 ]'
+fn decode_pstate(pstate: u64) -> String {
+    const FLAGS: &[(u64, &str)] = &[
+        (31, "N"), (30, "Z"), (29, "C"), (28, "V"),
+        (9, "D"), (8, "A"), (7, "I"), (6, "F"),
+    ];
+    let mut set: Vec<String> = FLAGS.iter()
+        .filter(|(bit, _)| pstate & (1 << bit) != 0)
+        .map(|(_, name)| name.to_string())
+        .collect();
+    set.push(format!("EL{}", (pstate >> 2) & 0b11));
+    format!("[ {} ]", set.join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn call_length_decodes_calls() {
+        assert_eq!(Regs::call_length(&0x9400_0010u32.to_le_bytes()), Some(4)); // bl
+        assert_eq!(Regs::call_length(&0x97ff_fffeu32.to_le_bytes()), Some(4)); // bl backwards
+        assert_eq!(Regs::call_length(&0xd63f_0020u32.to_le_bytes()), Some(4)); // blr x1
+    }
+
+    #[test]
+    fn call_length_ignores_others() {
+        assert_eq!(Regs::call_length(&[]), None);
+        assert_eq!(Regs::call_length(&[0x94, 0x00]), None);
+        assert_eq!(Regs::call_length(&0x1400_0010u32.to_le_bytes()), None); // b
+        assert_eq!(Regs::call_length(&0xd61f_0020u32.to_le_bytes()), None); // br x1
+        assert_eq!(Regs::call_length(&0xd65f_03c0u32.to_le_bytes()), None); // ret
+    }
+
+    #[test]
+    fn watch_chunk_stays_in_doubleword() {
+        assert_eq!(Regs::watch_chunk(0x1000, 8), 8);
+        assert_eq!(Regs::watch_chunk(0x1000, 16), 8);
+        assert_eq!(Regs::watch_chunk(0x1000, 3), 3);
+        assert_eq!(Regs::watch_chunk(0x1003, 8), 5);
+        assert_eq!(Regs::watch_chunk(0x1007, 4), 1);
+    }
+
+    #[test]
+    fn slot_selects_bytes_in_doubleword() {
+        let slot = WatchSlot { addr: 0x1003, len: 2, kind: WatchKind::Write };
+        assert_eq!(encode_slot(&slot), (0x1000, 0b0001_1000 << 5 | 0b10 << 3 | 0b10 << 1 | 1));
+        let slot = WatchSlot { addr: 0x2000, len: 8, kind: WatchKind::Read };
+        assert_eq!(encode_slot(&slot), (0x2000, 0xff << 5 | 0b01 << 3 | 0b10 << 1 | 1));
+    }
+
+    #[test]
+    fn hit_is_matched_by_doubleword() {
+        let mut state = vec![0u8; HWDEBUG_HEADER_SIZE + 3 * HWDEBUG_REG_SIZE];
+        let slots = [
+            WatchSlot { addr: 0x1000, len: 4, kind: WatchKind::Write },
+            WatchSlot { addr: 0x2004, len: 4, kind: WatchKind::Write },
+        ];
+        for (i, slot) in slots.iter().enumerate() {
+            let offset = HWDEBUG_HEADER_SIZE + i * HWDEBUG_REG_SIZE;
+            let (addr, ctrl) = encode_slot(slot);
+            state[offset..offset + 8].copy_from_slice(&addr.to_le_bytes());
+            state[offset + 8..offset + 12].copy_from_slice(&ctrl.to_le_bytes());
+        }
+        assert_eq!(hit_slot(&state, 0x1002), Some(0));
+        assert_eq!(hit_slot(&state, 0x2006), Some(1));
+
+        // no register watches it, or the one there is disabled
+        assert_eq!(hit_slot(&state, 0x3000), None);
+        assert_eq!(hit_slot(&state, 0), None);
+        assert_eq!(hit_slot(&[], 0x1000), None);
+    }
+
+    #[test]
+    fn pstate_names_flags_and_level() {
+        assert_eq!(decode_pstate(0x6000_0000), "[ Z C EL0 ]");
+        assert_eq!(decode_pstate(0x3c5), "[ D A I F EL1 ]");
+    }
+}
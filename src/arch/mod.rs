@@ -0,0 +1,190 @@
+// Architecture dependent parts of rsdb, selected at compile time.
+// Everything else reaches registers through 'RegisterFile' with the 'Regs' of the build target.
+
+#[cfg(target_arch = "x86_64")]
+mod x86_64;
+#[cfg(target_arch = "x86_64")]
+pub use self::x86_64::*;
+
+#[cfg(target_arch = "aarch64")]
+mod aarch64;
+#[cfg(target_arch = "aarch64")]
+pub use self::aarch64::*;
+
+pub struct RegisterDesc<R> {
+    pub name: &'static str,
+    pub get: fn(&R) -> u64,
+    pub set: fn(&mut R, u64),
+}
+
+// Defines RegisterDesc of a field with the same name
+macro_rules! register {
+    ($name: ident) => {
+        RegisterDesc {
+            name: stringify!($name),
+            get: |regs| regs.$name,
+            set: |regs, value| regs.$name = value,
+        }
+    };
+}
+pub(crate) use register;
+
+// Accesses a watchpoint stops at
+#[derive(Clone, Copy, PartialEq)]
+pub enum WatchKind {
+    Write,
+    Read,
+    Access,
+}
+
+// Memory range watched by one debug register
+#[derive(Clone, Copy)]
+pub struct WatchSlot {
+    pub addr: u64,
+    pub len: usize,
+    pub kind: WatchKind,
+}
+
+pub trait RegisterFile: Sized + 'static {
+    // Names of program counter and stack pointer
+    const PC: &'static str;
+    const SP: &'static str;
+
+    // Register holding the system call number at syscall-entry stop
+    const SYSCALL_NR: &'static str;
+
+    // Registers holding the system call arguments in order and the return value
+    const SYSCALL_ARGS: [&'static str; 6];
+    const SYSCALL_RET: &'static str;
+
+    // Trap instruction patched over the original code
+    const BREAKPOINT_INSN: &'static [u8];
+
+    // How far pc has advanced past the breakpoint address when the trap is reported
+    const BREAKPOINT_PC_OFFSET: u64;
+
+    // Register the return address is in at the function entry, None if it's on top of the stack
+    const LINK_REGISTER: Option<&'static str>;
+
+    // e_machine of the ELF files for the architecture, core files are written with it
+    const ELF_MACHINE: u16;
+
+    // Longest instruction in bytes
+    const MAX_INSN_SIZE: usize;
+
+    // Watchpoints trigger before the access, the instruction has to be stepped
+    // with the watchpoints removed to see the new value
+    const WATCH_BEFORE_ACCESS: bool;
+
+    // Debug registers can watch reads only, otherwise 'rwatch' uses access watchpoints
+    const WATCH_READ_ONLY: bool;
+
+    // Alternative register names as (alias, name), 'pc' and 'sp' are always available
+    const ALIASES: &'static [(&'static str, &'static str)] = &[];
+
+    // Registers shown by 'info regs' in order
+    fn registers() -> &'static [RegisterDesc<Self>];
+
+    // Registers shown only by 'info all-registers'
+    fn extra_registers() -> &'static [RegisterDesc<Self>] { &[] }
+
+    fn read(target: i32) -> Result<Self, ()>;
+    fn write(&self, target: i32) -> Result<(), ()>;
+
+    // Floating point and vector registers as (name, value) to display
+    fn vector_registers(target: i32) -> Result<Vec<(String, String)>, ()>;
+
+    // Length of the instruction if @code starts with a call, 'nexti' steps over it
+    fn call_length(code: &[u8]) -> Option<usize>;
+
+    // Bytes one debug register can watch from @addr, at most @len
+    fn watch_chunk(addr: u64, len: usize) -> usize;
+
+    // Number of debug registers available for watchpoints
+    fn watch_slots(target: i32) -> Result<usize, ()>;
+
+    // Programs the debug registers of @target with @slots, the rest are disabled
+    fn set_watch_slots(target: i32, slots: &[WatchSlot]) -> Result<(), ()>;
+
+    // Index of the debug register which caused the last SIGTRAP, None for other traps
+    fn watch_hit(target: i32) -> Result<Option<usize>, ()>;
+
+    // Human readable decoding of the register value such as flags
+    fn decode(_name: &str, _value: u64) -> Option<String> { None }
+
+    // Finds register by name or by the alias
+    fn find(name: &str) -> Option<&'static RegisterDesc<Self>> {
+        let name = match name {
+            "pc" => Self::PC,
+            "sp" => Self::SP,
+            name => Self::ALIASES.iter()
+                .find(|(alias, _)| *alias == name)
+                .map_or(name, |(_, name)| *name),
+        };
+        Self::registers().iter()
+            .chain(Self::extra_registers().iter())
+            .find(|desc| desc.name == name)
+    }
+
+    fn get(&self, name: &str) -> Option<u64> {
+        Self::find(name).map(|desc| (desc.get)(self))
+    }
+
+    fn set(&mut self, name: &str, value: u64) -> Result<(), ()> {
+        let desc = Self::find(name).ok_or(())?;
+        (desc.set)(self, value);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn zeroed() -> Regs {
+        unsafe { std::mem::zeroed() }
+    }
+
+    #[test]
+    fn find_takes_pc_sp_and_aliases() {
+        assert_eq!(Regs::find("pc").map(|desc| desc.name), Some(Regs::PC));
+        assert_eq!(Regs::find("sp").map(|desc| desc.name), Some(Regs::SP));
+        for (alias, name) in Regs::ALIASES {
+            assert_eq!(Regs::find(alias).map(|desc| desc.name), Some(*name));
+        }
+        assert!(Regs::find("fp").is_some());
+        assert!(Regs::find("nosuchreg").is_none());
+    }
+
+    #[test]
+    fn every_register_is_found_by_name() {
+        for desc in Regs::registers().iter().chain(Regs::extra_registers()) {
+            assert_eq!(Regs::find(desc.name).map(|found| found.name), Some(desc.name));
+        }
+    }
+
+    #[test]
+    fn set_and_get_share_the_field() {
+        let mut regs = zeroed();
+        regs.set("pc", 0x401126).unwrap();
+        assert_eq!(regs.get(Regs::PC), Some(0x401126));
+        regs.set(Regs::SP, 0x7ffe0000).unwrap();
+        assert_eq!(regs.get("sp"), Some(0x7ffe0000));
+        regs.set("fp", 0x1234).unwrap();
+        assert_eq!(regs.get(Regs::ALIASES[0].1), Some(0x1234));
+        assert!(regs.set("nosuchreg", 1).is_err());
+        assert_eq!(regs.get("nosuchreg"), None);
+    }
+
+    #[test]
+    fn registers_are_distinct() {
+        let mut regs = zeroed();
+        let list = Regs::registers();
+        for (i, desc) in list.iter().enumerate() {
+            (desc.set)(&mut regs, i as u64 + 1);
+        }
+        for (i, desc) in list.iter().enumerate() {
+            assert_eq!((desc.get)(&regs), i as u64 + 1, "{}", desc.name);
+        }
+    }
+}
@@ -0,0 +1,328 @@
+use libc::{NT_PRSTATUS, user_fpregs_struct};
+
+use crate::ptrace;
+use super::{RegisterDesc, RegisterFile, WatchKind, WatchSlot, register};
+
+pub type Regs = libc::user_regs_struct;
+
+// NT_X86_XSTATE, xsave area layout of AVX state
+const NT_X86_XSTATE: i32 = 0x202;
+const XSTATE_SIZE: usize = 4096;
+const XSTATE_BV_OFFSET: usize = 512;
+const XSTATE_AVX: u64 = 1 << 2;
+const YMM_HI128_OFFSET: usize = 576;
+
+// DR0-DR3 hold the addresses, DR6 is the status and DR7 is the control
+const WATCH_SLOTS: usize = 4;
+const DR_STATUS: usize = 6;
+const DR_CONTROL: usize = 7;
+
+// General purpose and segment registers in the order of 'info regs'
+const GENERAL_REGISTERS: &[RegisterDesc<Regs>] = &[
+    register!(rax), register!(rbx), register!(rcx), register!(rdx),
+    register!(rsi), register!(rdi), register!(rbp), register!(rsp),
+    register!(r8),  register!(r9),  register!(r10), register!(r11),
+    register!(r12), register!(r13), register!(r14), register!(r15),
+    register!(rip), register!(eflags),
+    register!(cs), register!(ss), register!(ds), register!(es), register!(fs), register!(gs),
+    register!(fs_base), register!(gs_base),
+];
+
+// Syscall number the kernel keeps, only for 'info all-registers'
+const EXTRA_REGISTERS: &[RegisterDesc<Regs>] = &[
+    register!(orig_rax),
+];
+
+impl RegisterFile for Regs {
+    const PC: &'static str = "rip";
+    const SP: &'static str = "rsp";
+    const SYSCALL_NR: &'static str = "orig_rax";
+    const SYSCALL_ARGS: [&'static str; 6] = ["rdi", "rsi", "rdx", "r10", "r8", "r9"];
+    const SYSCALL_RET: &'static str = "rax";
+    const BREAKPOINT_INSN: &'static [u8] = &[0xcc]; // int3
+    const BREAKPOINT_PC_OFFSET: u64 = 1;
+    const LINK_REGISTER: Option<&'static str> = None;
+    const ELF_MACHINE: u16 = 62; // EM_X86_64
+    const MAX_INSN_SIZE: usize = 15;
+    const WATCH_BEFORE_ACCESS: bool = false;
+    const WATCH_READ_ONLY: bool = false;
+    const ALIASES: &'static [(&'static str, &'static str)] = &[("fp", "rbp")];
+
+    fn registers() -> &'static [RegisterDesc<Self>] { GENERAL_REGISTERS }
+
+    fn extra_registers() -> &'static [RegisterDesc<Self>] { EXTRA_REGISTERS }
+
+    // same regset path as AArch64 rather than PTRACE_GETREGS
+    fn read(target: i32) -> Result<Self, ()> {
+        ptrace::getregset_as(target, NT_PRSTATUS)
+    }
+
+    fn write(&self, target: i32) -> Result<(), ()> {
+        ptrace::setregset(target, NT_PRSTATUS, self).map(|_| ())
+    }
+
+    fn vector_registers(target: i32) -> Result<Vec<(String, String)>, ()> {
+        let fpregs = ptrace::getfpregs(target)?;
+        let mut list = Vec::new();
+        for i in 0..8 {
+            let raw = st_raw(&fpregs, i);
+            let hex: String = raw.iter().rev().map(|b| format!("{:02x}", b)).collect();
+            list.push((format!("st{}", i), format!("{:>20} (raw 0x{})", f80_to_f64(raw), hex)));
+        }
+        list.push(("fctrl".to_string(), format!("{:#x}", fpregs.cwd)));
+        list.push(("fstat".to_string(), format!("{:#x}", fpregs.swd)));
+        list.push(("ftag".to_string(), format!("{:#x}", fpregs.ftw)));
+        list.push(("fop".to_string(), format!("{:#x}", fpregs.fop)));
+        list.push(("fioff".to_string(), format!("{:#x}", fpregs.rip)));
+        list.push(("fooff".to_string(), format!("{:#x}", fpregs.rdp)));
+        list.push(("mxcsr".to_string(), format!("{:#x}", fpregs.mxcsr)));
+        for i in 0..16 {
+            list.push((format!("xmm{}", i), format!("{:#034x}", xmm(&fpregs, i))));
+        }
+
+        // AVX registers are available only through the xsave area
+        let mut xstate = vec![0u8; XSTATE_SIZE];
+        if let Ok(size) = ptrace::getregset(target, NT_X86_XSTATE, &mut xstate) {
+            xstate.truncate(size);
+            for i in 0..16 {
+                if let Some(hi) = ymm_hi(&xstate, i) {
+                    list.push((format!("ymm{}", i), format!("0x{:032x}{:032x}", hi, xmm(&fpregs, i))));
+                }
+            }
+        }
+        Ok(list)
+    }
+
+    fn call_length(code: &[u8]) -> Option<usize> {
+        // legacy prefixes and REX
+        let mut i = code.iter()
+            .position(|b| !matches!(b, 0x26 | 0x2e | 0x36 | 0x3e | 0x64 | 0x65 |
+                                       0x66 | 0x67 | 0xf2 | 0xf3 | 0x40..=0x4f))?;
+        match code[i] {
+            // call rel32
+            0xe8 => Some(i + 5),
+
+            // call r/m64 (/2) and call m16:64 (/3)
+            0xff => {
+                i += 1;
+                let modrm = *code.get(i)?;
+                if !matches!((modrm >> 3) & 0b111, 2 | 3) {
+                    return None;
+                }
+                let (mode, rm) = (modrm >> 6, modrm & 0b111);
+                i += 1;
+                if mode != 0b11 && rm == 0b100 {
+                    // SIB, base 0b101 without displacement means disp32
+                    let sib = *code.get(i)?;
+                    i += 1;
+                    if mode == 0b00 && sib & 0b111 == 0b101 {
+                        i += 4;
+                    }
+                }
+                match mode {
+                    0b00 if rm == 0b101 => i += 4, // rip-relative
+                    0b01 => i += 1,
+                    0b10 => i += 4,
+                    _ => (),
+                }
+                Some(i)
+            },
+            _ => None,
+        }
+    }
+
+    // 1, 2, 4 or 8 bytes aligned to the length
+    fn watch_chunk(addr: u64, len: usize) -> usize {
+        [8, 4, 2, 1].iter()
+            .copied()
+            .find(|size| *size <= len && addr.is_multiple_of(*size as u64))
+            .unwrap_or(1)
+    }
+
+    fn watch_slots(_target: i32) -> Result<usize, ()> {
+        Ok(WATCH_SLOTS)
+    }
+
+    fn set_watch_slots(target: i32, slots: &[WatchSlot]) -> Result<(), ()> {
+        if slots.len() > WATCH_SLOTS {
+            return Err(());
+        }
+
+        // disabled while the addresses change, the kernel validates every update
+        ptrace::pokeuser(target, debugreg_offset(DR_CONTROL), 0)?;
+        for (i, slot) in slots.iter().enumerate() {
+            ptrace::pokeuser(target, debugreg_offset(i), slot.addr)?;
+        }
+        let dr7 = dr7(slots);
+        if dr7 != 0 {
+            ptrace::pokeuser(target, debugreg_offset(DR_CONTROL), dr7)?;
+        }
+        Ok(())
+    }
+
+    fn watch_hit(target: i32) -> Result<Option<usize>, ()> {
+        let dr6 = ptrace::peekuser(target, debugreg_offset(DR_STATUS))?;
+        let hit = (0..WATCH_SLOTS).find(|i| dr6 & (1 << i) != 0);
+
+        // the status stays until cleared, a later int3 must not look like a hit
+        if hit.is_some() {
+            ptrace::pokeuser(target, debugreg_offset(DR_STATUS), 0)?;
+        }
+        Ok(hit)
+    }
+
+    fn decode(name: &str, value: u64) -> Option<String> {
+        match name {
+            "eflags" => Some(decode_eflags(value)),
+            _ => None,
+        }
+    }
+}
+
+// DR7 enabling DR0-DR3 for @slots in order: local enable bit, then R/W and LEN of each
+fn dr7(slots: &[WatchSlot]) -> u64 {
+    let mut dr7 = 0;
+    for (i, slot) in slots.iter().enumerate() {
+        let rw = match slot.kind {
+            WatchKind::Write => 0b01,
+            WatchKind::Read | WatchKind::Access => 0b11,
+        };
+        let len = match slot.len {
+            1 => 0b00,
+            2 => 0b01,
+            8 => 0b10,
+            _ => 0b11,
+        };
+        dr7 |= 1 << (i * 2) | (rw | len << 2) << (16 + i * 4);
+    }
+    dr7
+}
+
+// Offset of DR@index in 'struct user' for PTRACE_PEEKUSER and PTRACE_POKEUSER
+fn debugreg_offset(index: usize) -> u64 {
+    (std::mem::offset_of!(libc::user, u_debugreg) + index * 8) as u64
+}
+
+// e.g) '[ CF ZF IF ]'
+fn decode_eflags(eflags: u64) -> String {
+    const FLAGS: &[(u64, &str)] = &[
+        (0, "CF"), (2, "PF"), (4, "AF"), (6, "ZF"), (7, "SF"),
+        (8, "TF"), (9, "IF"), (10, "DF"), (11, "OF"),
+    ];
+    let set: Vec<&str> = FLAGS.iter()
+        .filter(|(bit, _)| eflags & (1 << bit) != 0)
+        .map(|(_, name)| *name)
+        .collect();
+    format!("[ {} ]", set.join(" "))
+}
+
+// 80-bit extended precision to f64, precision loss is fine for displaying
+fn f80_to_f64(raw: &[u8]) -> f64 {
+    let mantissa = u64::from_le_bytes([raw[0], raw[1], raw[2], raw[3], raw[4], raw[5], raw[6], raw[7]]);
+    let sign_exp = u16::from_le_bytes([raw[8], raw[9]]);
+    let sign = if sign_exp & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exp = (sign_exp & 0x7fff) as i32;
+
+    match exp {
+        0 if mantissa == 0 => sign * 0.0,
+        0x7fff if mantissa << 1 == 0 => sign * f64::INFINITY,
+        0x7fff => f64::NAN,
+        _ => sign * (mantissa as f64 / (1u64 << 63) as f64) * 2f64.powi(exp - 16383),
+    }
+}
+
+fn fpregs_bytes(fpregs: &user_fpregs_struct) -> &[u8] {
+    unsafe {
+        std::slice::from_raw_parts(
+            fpregs as *const _ as *const u8,
+            std::mem::size_of::<user_fpregs_struct>())
+    }
+}
+
+// Raw 10 bytes of st(@index), each slot in the fxsave area is 16 bytes wide
+fn st_raw(fpregs: &user_fpregs_struct, index: usize) -> &[u8] {
+    let offset = 32 + index * 16;
+    &fpregs_bytes(fpregs)[offset..offset + 10]
+}
+
+fn xmm(fpregs: &user_fpregs_struct, index: usize) -> u128 {
+    let offset = 160 + index * 16;
+    let mut raw = [0u8; 16];
+    raw.copy_from_slice(&fpregs_bytes(fpregs)[offset..offset + 16]);
+    u128::from_le_bytes(raw)
+}
+
+// Upper 128 bits of ymm(@index), None if the xsave area has no AVX state
+fn ymm_hi(xstate: &[u8], index: usize) -> Option<u128> {
+    let mut bv = [0u8; 8];
+    bv.copy_from_slice(xstate.get(XSTATE_BV_OFFSET..XSTATE_BV_OFFSET + 8)?);
+    if u64::from_le_bytes(bv) & XSTATE_AVX == 0 {
+        // AVX state in its initial configuration, which is all zero
+        return Some(0);
+    }
+
+    let offset = YMM_HI128_OFFSET + index * 16;
+    let mut raw = [0u8; 16];
+    raw.copy_from_slice(xstate.get(offset..offset + 16)?);
+    Some(u128::from_le_bytes(raw))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn call_length_decodes_calls() {
+        assert_eq!(Regs::call_length(&[0xe8, 0x10, 0x00, 0x00, 0x00]), Some(5));
+        assert_eq!(Regs::call_length(&[0xff, 0xd0]), Some(2)); // call rax
+        assert_eq!(Regs::call_length(&[0x41, 0xff, 0xd3]), Some(3)); // call r11
+        assert_eq!(Regs::call_length(&[0xff, 0x15, 0, 0, 0, 0]), Some(6)); // call [rip+disp32]
+        assert_eq!(Regs::call_length(&[0xff, 0x14, 0x24]), Some(3)); // call [rsp]
+        assert_eq!(Regs::call_length(&[0xff, 0x54, 0x24, 0x08]), Some(4)); // call [rsp+8]
+        assert_eq!(Regs::call_length(&[0xff, 0x94, 0x24, 0, 1, 0, 0]), Some(7)); // call [rsp+disp32]
+        assert_eq!(Regs::call_length(&[0xff, 0x14, 0x25, 0, 0x10, 0, 0]), Some(7)); // call [disp32]
+        assert_eq!(Regs::call_length(&[0xff, 0x50, 0x10]), Some(3)); // call [rax+0x10]
+        assert_eq!(Regs::call_length(&[0xff, 0x1c, 0x24]), Some(3)); // call far [rsp]
+    }
+
+    #[test]
+    fn call_length_ignores_others() {
+        assert_eq!(Regs::call_length(&[]), None);
+        assert_eq!(Regs::call_length(&[0x90]), None);
+        assert_eq!(Regs::call_length(&[0xff, 0xe0]), None); // jmp rax
+        assert_eq!(Regs::call_length(&[0xff, 0x30]), None); // push [rax]
+        assert_eq!(Regs::call_length(&[0xe9, 0, 0, 0, 0]), None);
+        assert_eq!(Regs::call_length(&[0xff]), None);
+    }
+
+    #[test]
+    fn watch_chunk_is_aligned() {
+        assert_eq!(Regs::watch_chunk(0x1000, 8), 8);
+        assert_eq!(Regs::watch_chunk(0x1000, 16), 8);
+        assert_eq!(Regs::watch_chunk(0x1000, 7), 4);
+        assert_eq!(Regs::watch_chunk(0x1004, 8), 4);
+        assert_eq!(Regs::watch_chunk(0x1002, 8), 2);
+        assert_eq!(Regs::watch_chunk(0x1001, 8), 1);
+        assert_eq!(Regs::watch_chunk(0x1000, 3), 2);
+    }
+
+    #[test]
+    fn dr7_encodes_slots() {
+        assert_eq!(dr7(&[]), 0);
+        let slots = [
+            WatchSlot { addr: 0x1000, len: 4, kind: WatchKind::Write },
+            WatchSlot { addr: 0x2000, len: 8, kind: WatchKind::Access },
+            WatchSlot { addr: 0x3001, len: 1, kind: WatchKind::Read },
+            WatchSlot { addr: 0x4002, len: 2, kind: WatchKind::Write },
+        ];
+        let expected = 1 | 1 << 2 | 1 << 4 | 1 << 6 |
+            0b1101 << 16 | 0b1011 << 20 | 0b0011 << 24 | 0b0101 << 28;
+        assert_eq!(dr7(&slots), expected);
+    }
+
+    #[test]
+    fn eflags_names_set_bits() {
+        assert_eq!(decode_eflags(0x246), "[ PF ZF IF ]");
+        assert_eq!(decode_eflags(0), "[  ]");
+    }
+}
@@ -0,0 +1,283 @@
+use crate::ptrace;
+use crate::arch::{Regs, RegisterFile};
+use crate::expr::Expr;
+
+const BREAKPOINT_INSN: &[u8] = Regs::BREAKPOINT_INSN;
+
+pub struct Breakpoint {
+    pub id: usize,
+    pub addr: u64,
+    pub enabled: bool,
+    pub hits: usize,
+
+    // symbol expression the address came from, resolved again on relocation
+    pub location: Option<String>,
+
+    // internal breakpoint deleted at the next stop, e.g) return address of 'nexti'
+    pub temporary: bool,
+
+    // stands in for a temporary breakpoint at the same address until the next stop,
+    // stopping even if disabled, the condition is false or the hit is ignored
+    pub stop_once: bool,
+
+    // stops only if the expression is non-zero, 'break ... if' and 'condition'
+    pub condition: Option<Expr>,
+
+    // hits to go without stopping, 'ignore'
+    pub ignore_count: usize,
+
+    // original bytes under the trap instruction, Some(..) only while inserted
+    orig: Option<Vec<u8>>,
+}
+
+impl Breakpoint {
+    pub fn inserted(&self) -> bool { self.orig.is_some() }
+
+    pub fn insert(&mut self, target: i32) -> Result<(), ()> {
+        if self.inserted() { return Ok(()) }
+
+        let word = ptrace::peektext(target, self.addr)?;
+        let mut bytes = word.to_le_bytes();
+        let orig = bytes[..BREAKPOINT_INSN.len()].to_vec();
+        bytes[..BREAKPOINT_INSN.len()].copy_from_slice(BREAKPOINT_INSN);
+        ptrace::poketext(target, self.addr, u64::from_le_bytes(bytes))?;
+
+        self.orig = Some(orig);
+        Ok(())
+    }
+
+    pub fn remove(&mut self, target: i32) -> Result<(), ()> {
+        self.restore(target)?;
+        self.orig = None;
+        Ok(())
+    }
+
+    // Writes back the original bytes without changing the state
+    fn restore(&self, target: i32) -> Result<(), ()> {
+        let orig = match &self.orig {
+            Some(orig) => orig,
+            None => return Ok(()),
+        };
+
+        let word = ptrace::peektext(target, self.addr)?;
+        let mut bytes = word.to_le_bytes();
+        bytes[..orig.len()].copy_from_slice(orig);
+        ptrace::poketext(target, self.addr, u64::from_le_bytes(bytes))?;
+        Ok(())
+    }
+}
+
+pub struct BreakpointTable {
+    next_id: usize,
+    list: Vec<Breakpoint>,
+
+    // whether a temporary breakpoint is hit since the last 'add_temporary',
+    // the command which set it reports the stop
+    pub temporary_hit: bool,
+}
+
+impl BreakpointTable {
+    pub fn new() -> Self {
+        BreakpointTable { next_id: 1, list: Vec::new(), temporary_hit: false }
+    }
+
+    // Number for a new breakpoint or watchpoint
+    pub fn new_id(&mut self) -> usize {
+        self.next_id += 1;
+        self.next_id - 1
+    }
+
+    pub fn add(&mut self, addr: u64, location: Option<String>) -> Result<usize, ()> {
+        if self.find_by_addr(addr).is_some() {
+            return Err(());
+        }
+        let id = self.new_id();
+        self.list.push(Breakpoint {
+            id, addr, enabled: true, hits: 0, location, temporary: false, stop_once: false,
+            condition: None, ignore_count: 0, orig: None
+        });
+        Ok(id)
+    }
+
+    // Stops the process at @addr once. The breakpoint there which may not stop, disabled
+    // or conditional, is made to stop instead. Fails if it stops the process anyway.
+    pub fn add_temporary(&mut self, addr: u64) -> Result<(), ()> {
+        self.temporary_hit = false;
+        if let Some(bp) = self.find_by_addr_mut(addr) {
+            if bp.enabled && bp.condition.is_none() && bp.ignore_count == 0 {
+                return Err(());
+            }
+            bp.stop_once = true;
+            return Ok(());
+        }
+        self.list.push(Breakpoint {
+            id: 0, addr, enabled: true, hits: 0, location: None, temporary: true, stop_once: false,
+            condition: None, ignore_count: 0, orig: None
+        });
+        Ok(())
+    }
+
+    pub fn delete_temporaries(&mut self, target: i32) {
+        for bp in self.list.iter_mut().filter(|bp| bp.temporary || bp.stop_once) {
+            // enabled user breakpoints stay inserted
+            let keep = bp.enabled && !bp.temporary;
+            bp.stop_once = false;
+            if target != -1 && !keep {
+                bp.remove(target).unwrap_or(());
+            }
+        }
+        self.list.retain(|bp| !bp.temporary);
+    }
+
+    // Removes the breakpoint from the table, restoring original code if @target is valid
+    pub fn delete(&mut self, id: usize, target: i32) -> Result<Breakpoint, ()> {
+        let index = self.list.iter().position(|bp| bp.id == id).ok_or(())?;
+        if target != -1 {
+            self.list[index].remove(target)?;
+        }
+        Ok(self.list.remove(index))
+    }
+
+    pub fn get_mut(&mut self, id: usize) -> Option<&mut Breakpoint> {
+        self.list.iter_mut().find(|bp| bp.id == id)
+    }
+
+    pub fn find_by_addr(&self, addr: u64) -> Option<&Breakpoint> {
+        self.list.iter().find(|bp| bp.addr == addr)
+    }
+
+    pub fn find_by_addr_mut(&mut self, addr: u64) -> Option<&mut Breakpoint> {
+        self.list.iter_mut().find(|bp| bp.addr == addr)
+    }
+
+    pub fn ids(&self) -> Vec<usize> {
+        self.list.iter().filter(|bp| !bp.temporary).map(|bp| bp.id).collect()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Breakpoint> {
+        self.list.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> std::slice::IterMut<'_, Breakpoint> {
+        self.list.iter_mut()
+    }
+
+    // Replaces trap instructions in @buf read from @addr with the original bytes
+    pub fn unpatch(&self, addr: u64, buf: &mut [u8]) {
+        let end = addr + buf.len() as u64;
+        for bp in self.list.iter() {
+            let orig = match &bp.orig {
+                Some(orig) => orig,
+                None => continue,
+            };
+            for (i, byte) in orig.iter().enumerate() {
+                let cur = bp.addr + i as u64;
+                if cur >= addr && cur < end {
+                    buf[(cur - addr) as usize] = *byte;
+                }
+            }
+        }
+    }
+
+    // Makes the target's memory reflect the table: enabled breakpoints are
+    // inserted, disabled ones are removed unless they stop once.
+    pub fn sync(&mut self, target: i32) -> Result<(), ()> {
+        for bp in self.list.iter_mut() {
+            if bp.enabled || bp.stop_once {
+                bp.insert(target)?;
+            } else {
+                bp.remove(target)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn remove_all(&mut self, target: i32) -> Result<(), ()> {
+        for bp in self.list.iter_mut() {
+            bp.remove(target)?;
+        }
+        Ok(())
+    }
+
+    // Removes breakpoints from @target forked from the process they are inserted in,
+    // the table stays as it is for the process.
+    pub fn remove_from_fork(&self, target: i32) -> Result<(), ()> {
+        for bp in self.list.iter() {
+            bp.restore(target)?;
+        }
+        Ok(())
+    }
+
+    // The process is gone, nothing is inserted anymore
+    pub fn reset(&mut self) {
+        for bp in self.list.iter_mut() {
+            bp.orig = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn temporary_is_planted_at_free_address() {
+        let mut table = BreakpointTable::new();
+        assert!(table.add_temporary(0x1000).is_ok());
+        assert!(table.find_by_addr(0x1000).is_some_and(|bp| bp.temporary));
+        assert!(table.ids().is_empty());
+        table.delete_temporaries(-1);
+        assert!(table.find_by_addr(0x1000).is_none());
+    }
+
+    #[test]
+    fn enabled_breakpoint_stops_by_itself() {
+        let mut table = BreakpointTable::new();
+        table.add(0x1000, None).unwrap();
+        assert!(table.add_temporary(0x1000).is_err());
+        assert!(!table.find_by_addr(0x1000).unwrap().stop_once);
+    }
+
+    #[test]
+    fn disabled_or_conditional_breakpoint_stops_once() {
+        let mut table = BreakpointTable::new();
+        let disabled = table.add(0x1000, None).unwrap();
+        table.get_mut(disabled).unwrap().enabled = false;
+        let ignored = table.add(0x2000, None).unwrap();
+        table.get_mut(ignored).unwrap().ignore_count = 3;
+
+        assert!(table.add_temporary(0x1000).is_ok());
+        assert!(table.add_temporary(0x2000).is_ok());
+        assert!(table.iter().all(|bp| bp.stop_once && !bp.temporary));
+
+        table.delete_temporaries(-1);
+        assert_eq!(table.ids(), vec![disabled, ignored]);
+        assert!(table.iter().all(|bp| !bp.stop_once));
+    }
+
+    #[test]
+    fn temporary_hit_is_reset_by_a_new_temporary() {
+        let mut table = BreakpointTable::new();
+        table.temporary_hit = true;
+        table.add_temporary(0x1000).unwrap();
+        assert!(!table.temporary_hit);
+    }
+
+    #[test]
+    fn unpatch_restores_original_bytes() {
+        let mut table = BreakpointTable::new();
+        let id = table.add(0x1002, None).unwrap();
+        let orig: Vec<u8> = (0..BREAKPOINT_INSN.len() as u8).map(|i| 0x90 + i).collect();
+        table.get_mut(id).unwrap().orig = Some(orig.clone());
+
+        let mut buf = vec![0u8; 16];
+        buf[2..2 + BREAKPOINT_INSN.len()].copy_from_slice(BREAKPOINT_INSN);
+        table.unpatch(0x1000, &mut buf);
+        assert_eq!(&buf[2..2 + orig.len()], orig.as_slice());
+
+        // only the part inside the buffer
+        let mut tail = vec![0u8; 1];
+        table.unpatch(0x1002, &mut tail);
+        assert_eq!(tail[0], orig[0]);
+    }
+}
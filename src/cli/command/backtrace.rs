@@ -0,0 +1,66 @@
+use super::*;
+
+// Frames shown if the count is not given, the walk stops there
+const MAX_FRAMES: usize = 64;
+
+fn word_at(buf: &[u8], offset: usize) -> u64 {
+    let mut raw = [0u8; 8];
+    raw.copy_from_slice(&buf[offset..offset + 8]);
+    u64::from_le_bytes(raw)
+}
+
+// Return address of the function pc is at the entry of, the frame is not set up yet
+fn entry_return_address(sess: &session::Session, regs: &Regs, pc: u64) -> Option<u64> {
+    if sess.symbols.bounds(pc).map(|(start, _)| start) != Some(pc) {
+        return None;
+    }
+    match Regs::LINK_REGISTER {
+        Some(link) => regs.get(link),
+        None => {
+            let sp = regs.get("sp")?;
+            let bytes = sess.target().read_memory(sp, 8).ok()?;
+            Some(word_at(&bytes, 0))
+        },
+    }
+}
+
+// Walks the frame pointer chain, each frame record is (caller's frame pointer, return address)
+// on both x86_64 and AArch64. Frames of the code built without frame pointers are missed.
+pub fn backtrace(sess: &session::Session, count: Option<usize>) -> MainLoopAction {
+    let target = sess.target();
+    let regs = match target.getregs() {
+        Ok(regs) => regs,
+        Err(_) => {
+            println!("{}", "Failed to read registers".red());
+            return MainLoopAction::None;
+        },
+    };
+    let pc = regs.get("pc").unwrap_or_default();
+    let mut frames = vec![pc];
+    frames.extend(entry_return_address(sess, &regs, pc));
+
+    let limit = count.unwrap_or(MAX_FRAMES);
+    let mut fp = regs.get("fp").unwrap_or_default();
+    while frames.len() < limit && fp != 0 && fp.is_multiple_of(8) {
+        let record = match target.read_memory(fp, 16) {
+            Ok(record) => record,
+            Err(_) => break,
+        };
+        let (next, ret) = (word_at(&record, 0), word_at(&record, 8));
+
+        // garbage past the outermost frame doesn't point to code
+        if !target.maps().find(ret).is_some_and(|region| region.exec) {
+            break;
+        }
+        frames.push(ret);
+        if next <= fp {
+            break;
+        }
+        fp = next;
+    }
+
+    for (i, addr) in frames.iter().take(limit).enumerate() {
+        println!("#{:<3} {}", i, sess.format_addr(*addr));
+    }
+    MainLoopAction::None
+}
@@ -0,0 +1,200 @@
+use super::*;
+use crate::watchpoint::Watchpoint;
+use crate::expr::Expr;
+
+// @location is kept for the breakpoints set by symbol to follow relocation
+pub fn set(sess: &mut session::Session, addr: u64, location: Option<String>,
+           condition: Option<Expr>) -> MainLoopAction {
+    let id = match sess.breakpoints.add(addr, location) {
+        Ok(id) => id,
+        Err(_) => {
+            println!("{}{:#x}", "Breakpoint already exists at ".red(), addr);
+            return MainLoopAction::None;
+        },
+    };
+
+    // insert right away if there is a process, otherwise on 'run' or 'continue'
+    if sess.valid() {
+        let target = sess.get_target();
+        let inserted = sess.breakpoints
+            .get_mut(id)
+            .map(|bp| bp.insert(target).is_ok())
+            .unwrap_or(false);
+        if !inserted {
+            sess.breakpoints.delete(id, -1).unwrap_or_else(|_| unreachable!());
+            println!("{}{:#x}", "Cannot insert breakpoint at ".red(), addr);
+            return MainLoopAction::None;
+        }
+    }
+    if let Some(bp) = sess.breakpoints.get_mut(id) {
+        bp.condition = condition;
+    }
+    println!("Breakpoint {} at {}", id, sess.format_addr(addr));
+    MainLoopAction::None
+}
+
+// Stops at breakpoint @id only if @condition is non-zero, None makes it unconditional
+pub fn condition(sess: &mut session::Session, id: usize, condition: Option<Expr>) -> MainLoopAction {
+    let bp = match sess.breakpoints.get_mut(id).filter(|bp| !bp.temporary) {
+        Some(bp) => bp,
+        None => {
+            println!("{}{}", "No breakpoint number ".red(), id);
+            return MainLoopAction::None;
+        },
+    };
+    if condition.is_none() {
+        println!("Breakpoint {} now unconditional.", id);
+    }
+    bp.condition = condition;
+    MainLoopAction::None
+}
+
+// Passes over breakpoint @id the next @count hits
+pub fn ignore(sess: &mut session::Session, id: usize, count: usize) -> MainLoopAction {
+    let bp = match sess.breakpoints.get_mut(id).filter(|bp| !bp.temporary) {
+        Some(bp) => bp,
+        None => {
+            println!("{}{}", "No breakpoint number ".red(), id);
+            return MainLoopAction::None;
+        },
+    };
+    bp.ignore_count = count;
+    match count {
+        0 => println!("Will stop next time breakpoint {} is reached.", id),
+        1 => println!("Will ignore next crossing of breakpoint {}.", id),
+        _ => println!("Will ignore next {} crossings of breakpoint {}.", count, id),
+    }
+    MainLoopAction::None
+}
+
+// e.g) 'Hardware read watchpoint 2: counter'
+fn watch_title(wp: &Watchpoint) -> String {
+    let kind = match wp.kind {
+        WatchKind::Write if wp.software => "Watchpoint",
+        WatchKind::Write => "Hardware watchpoint",
+        WatchKind::Read => "Hardware read watchpoint",
+        WatchKind::Access => "Hardware access (read/write) watchpoint",
+    };
+    let what = wp.location.clone().unwrap_or_else(|| format!("{:#x}", wp.addr));
+    format!("{} {}: {}", kind, wp.id, what)
+}
+
+// Bytes of the longer values shown
+const MAX_WATCH_BYTES: usize = 32;
+
+// Little endian integer up to 8 bytes, bytes in hex for the longer ones
+fn format_watch_value(value: &Option<Vec<u8>>) -> String {
+    match value {
+        None => "<unreadable>".to_string(),
+        Some(bytes) if bytes.len() <= 8 => {
+            let mut raw = [0u8; 8];
+            raw[..bytes.len()].copy_from_slice(bytes);
+            let value = u64::from_le_bytes(raw);
+            format!("{} ({:#x})", value, value)
+        },
+        Some(bytes) => {
+            let list: Vec<String> = bytes.iter()
+                .take(MAX_WATCH_BYTES)
+                .map(|b| format!("{:#04x}", b))
+                .collect();
+            let more = if bytes.len() > MAX_WATCH_BYTES { "..." } else { "" };
+            format!("{{{}{}}}", list.join(", "), more)
+        },
+    }
+}
+
+// Stops when @len bytes at @addr are accessed as @kind says, with the debug registers
+// or by single-stepping if they are all in use.
+pub fn watch(sess: &mut session::Session, addr: u64, len: usize, kind: WatchKind,
+             location: Option<String>) -> MainLoopAction {
+    let id = sess.breakpoints.new_id();
+    sess.watchpoints.add(id, addr, len, kind, location);
+
+    // programmed right away if there is a process, otherwise on 'run'
+    if sess.valid() {
+        let tids = sess.proc.threads.tids();
+        if sess.watchpoints.sync(&sess.proc, &tids).is_err() {
+            sess.watchpoints.delete(id).unwrap_or_else(|_| unreachable!());
+            sess.watchpoints.sync(&sess.proc, &tids).unwrap_or_default();
+            println!("{}", "Hardware watchpoint resources exhausted, reads can't be watched in software".red());
+            return MainLoopAction::None;
+        }
+    }
+    if let Some(wp) = sess.watchpoints.iter().find(|wp| wp.id == id) {
+        println!("{}", watch_title(wp));
+    }
+    MainLoopAction::None
+}
+
+// Tells the user which watchpoint the current thread has hit and how the value changed
+pub fn report_watch(sess: &session::Session, hit: &Hit) {
+    let wp = match sess.watchpoints.iter().find(|wp| wp.id == hit.id) {
+        Some(wp) => wp,
+        None => return,
+    };
+    println!("\n{}\n", watch_title(wp));
+    match wp.kind {
+        WatchKind::Write => {
+            println!("Old value = {}", format_watch_value(&hit.old));
+            println!("New value = {}", format_watch_value(&hit.new));
+        },
+        WatchKind::Access if hit.old != hit.new => {
+            println!("Old value = {}", format_watch_value(&hit.old));
+            println!("New value = {}", format_watch_value(&hit.new));
+        },
+        _ => println!("Value = {}", format_watch_value(&hit.new)),
+    }
+}
+
+// Deletes breakpoint or watchpoint @id, or every one of them if None
+pub fn delete(sess: &mut session::Session, id: Option<usize>) -> MainLoopAction {
+    let ids = match id {
+        Some(id) => vec![id],
+        None => [sess.breakpoints.ids(), sess.watchpoints.ids()].concat(),
+    };
+    for id in ids {
+        if let Ok(wp) = sess.watchpoints.delete(id) {
+            println!("Deleted watchpoint {} at {:#x}", wp.id, wp.addr);
+            continue;
+        }
+        match sess.breakpoints.delete(id, sess.get_target()) {
+            Ok(bp) => println!("Deleted breakpoint {} at {:#x}", bp.id, bp.addr),
+            Err(_) => println!("{}{}", "No breakpoint number ".red(), id),
+        }
+    }
+
+    // the debug registers are cleared as well
+    if sess.valid() {
+        let tids = sess.proc.threads.tids();
+        sess.watchpoints.sync(&sess.proc, &tids).unwrap_or_default();
+    }
+    MainLoopAction::None
+}
+
+// Enables or disables breakpoint or watchpoint @id, or every one of them if None
+pub fn enable(sess: &mut session::Session, id: Option<usize>, enabled: bool) -> MainLoopAction {
+    let ids = match id {
+        Some(id) => vec![id],
+        None => [sess.breakpoints.ids(), sess.watchpoints.ids()].concat(),
+    };
+    for id in ids {
+        if let Some(wp) = sess.watchpoints.get_mut(id) {
+            wp.enabled = enabled;
+            continue;
+        }
+        match sess.breakpoints.get_mut(id) {
+            Some(bp) => bp.enabled = enabled,
+            None => println!("{}{}", "No breakpoint number ".red(), id),
+        }
+    }
+
+    // disabled breakpoints are removed from memory right away
+    if sess.valid() && sess.breakpoints.sync(sess.get_target()).is_err() {
+        println!("{}", "Failed to update breakpoints".red());
+    }
+    let tids = sess.proc.threads.tids();
+    if sess.valid() && sess.watchpoints.sync(&sess.proc, &tids).is_err() {
+        println!("{}", "Failed to update watchpoints".red());
+    }
+    MainLoopAction::None
+}
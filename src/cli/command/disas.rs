@@ -0,0 +1,98 @@
+use super::*;
+use crate::disas::Instruction;
+
+// Number of instructions if neither count nor the function boundary is known
+const DEFAULT_COUNT: usize = 10;
+
+// Instructions shown before and after pc whenever the process stops
+const CONTEXT_BEFORE: usize = 3;
+const CONTEXT_AFTER: usize = 5;
+
+// Reads code at @addr with the original bytes under the breakpoints,
+// trimmed to the end of the page if the rest is not readable.
+fn read_code(sess: &session::Session, addr: u64, len: usize) -> Option<Vec<u8>> {
+    let mut code = match sess.target().read_memory(addr, len) {
+        Ok(code) => code,
+        Err(_) => {
+            let page_end = (addr | 0xfff) + 1;
+            sess.target().read_memory(addr, (page_end - addr) as usize).ok()?
+        },
+    };
+    sess.breakpoints.unpatch(addr, &mut code);
+    Some(code)
+}
+
+fn print_instructions(sess: &session::Session, insns: &[Instruction]) {
+    let pc = sess.target().getreg("pc").ok();
+    for insn in insns {
+        let bp_mark = match sess.breakpoints.find_by_addr(insn.addr) {
+            Some(bp) if bp.enabled && !bp.temporary => "*".red(),
+            _ => " ".normal(),
+        };
+        let target = insn.target
+            .and_then(|target| sess.symbols.symbolize(target))
+            .map(|sym| format!(" <{}>", sym))
+            .unwrap_or_default();
+        let text = format!("{:7} {}", insn.mnemonic, insn.operands);
+        let line = format!("{}:\t{}{}", sess.format_addr(insn.addr), text.trim_end(), target);
+        match Some(insn.addr) == pc {
+            true => println!("=>{} {}", bp_mark, line.bright_green()),
+            false => println!("  {} {}", bp_mark, line),
+        }
+    }
+}
+
+// Disassembles @count instructions from @addr, or the whole function containing
+// @addr if @count is not given.
+pub fn disassemble(sess: &mut session::Session, addr: u64, count: Option<usize>) -> MainLoopAction {
+    let (start, len, count) = match (count, sess.symbols.bounds(addr)) {
+        (Some(count), _) => (addr, count * Regs::MAX_INSN_SIZE, count),
+        (None, Some((start, size))) => (start, size as usize, size as usize),
+        (None, None) => (addr, DEFAULT_COUNT * Regs::MAX_INSN_SIZE, DEFAULT_COUNT),
+    };
+
+    let insns = read_code(sess, start, len)
+        .and_then(|code| crate::disas::disassemble(&code, start, count).ok());
+    match insns {
+        Some(insns) if !insns.is_empty() => print_instructions(sess, &insns),
+        _ => println!("{}{:#x}", "Cannot disassemble at address ".red(), start),
+    }
+    MainLoopAction::None
+}
+
+// Instructions ending right at @pc, decoded from the function start if it's close,
+// otherwise from the farthest address that decodes into @pc exactly.
+fn instructions_before(sess: &session::Session, pc: u64) -> Vec<Instruction> {
+    let window = (CONTEXT_BEFORE * Regs::MAX_INSN_SIZE) as u64;
+    let candidates: Vec<u64> = match sess.symbols.bounds(pc) {
+        Some((start, _)) if pc - start <= window * 4 => vec![start],
+        _ => (pc.saturating_sub(window)..pc).collect(),
+    };
+
+    for from in candidates {
+        let insns = read_code(sess, from, (pc - from) as usize)
+            .and_then(|code| crate::disas::disassemble(&code, from, code.len()).ok())
+            .unwrap_or_default();
+        let decoded: usize = insns.iter().map(|insn| insn.size).sum();
+        if !insns.is_empty() && from + decoded as u64 == pc {
+            let skip = insns.len().saturating_sub(CONTEXT_BEFORE);
+            return insns.into_iter().skip(skip).collect();
+        }
+    }
+    Vec::new()
+}
+
+// A few instructions around pc, shown whenever the process stops
+pub fn context(sess: &session::Session) {
+    let pc = match sess.target().getreg("pc") {
+        Ok(pc) => pc,
+        Err(_) => return,
+    };
+
+    let mut insns = instructions_before(sess, pc);
+    let after = read_code(sess, pc, CONTEXT_AFTER * Regs::MAX_INSN_SIZE)
+        .and_then(|code| crate::disas::disassemble(&code, pc, CONTEXT_AFTER).ok())
+        .unwrap_or_default();
+    insns.extend(after);
+    print_instructions(sess, &insns);
+}
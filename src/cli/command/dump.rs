@@ -0,0 +1,217 @@
+use std::fs;
+use std::io::{Read, Write};
+use std::os::unix::fs::FileExt;
+use std::path::Path;
+
+use super::*;
+use crate::coredump;
+use crate::process::{procfs, maps::MemoryRegion};
+
+// Bytes read from '/proc/{PID}/mem' and written to the file at once
+const CHUNK_SIZE: usize = 1 << 20;
+
+// Lists the dumped regions in the directory of 'dump all'
+const MANIFEST: &str = "manifest.txt";
+
+// Copies [@start, @end) of the memory to @path with the breakpoints unpatched.
+// Returns the bytes copied, which are fewer if it runs into unreadable memory.
+fn copy_to_file(sess: &session::Session, mem: &fs::File, start: u64, end: u64, path: &Path) -> Result<u64, ()> {
+    let mut file = fs::File::create(path).map_err(|errstr| {
+        println!("{}'{}': {}", "Cannot create ".red(), path.display(), errstr);
+    })?;
+    let mut buf = vec![0u8; CHUNK_SIZE];
+    let mut addr = start;
+    while addr < end {
+        let len = (end - addr).min(CHUNK_SIZE as u64) as usize;
+        let read = mem.read_at(&mut buf[..len], addr).unwrap_or(0);
+        if read == 0 {
+            break;
+        }
+        sess.breakpoints.unpatch(addr, &mut buf[..read]);
+        file.write_all(&buf[..read]).map_err(|errstr| {
+            println!("{}'{}': {}", "Cannot write ".red(), path.display(), errstr);
+        })?;
+        addr += read as u64;
+    }
+    Ok(addr - start)
+}
+
+// e.g) '7ffff7dd1000-7ffff7df9000-libc.so.6.bin', 'anon' for anonymous mappings.
+// The spaces of the name are replaced so that it's a word of the manifest.
+fn region_file_name(region: &MemoryRegion) -> String {
+    let name = match region.name().trim_matches(|c| c == '[' || c == ']') {
+        "" => "anon",
+        name => name,
+    };
+    format!("{:x}-{:x}-{}.bin", region.start, region.end, name.replace(char::is_whitespace, "_"))
+}
+
+// Line of the manifest, the path is the last as it may contain spaces
+fn manifest_line(region: &MemoryRegion, end: u64, file: &str) -> String {
+    let path = if region.path.is_empty() { "-" } else { &region.path };
+    format!("{:#x} {:#x} {} {:#x} {} {}\n", region.start, end, region.perms(), region.offset, file, path)
+}
+
+// Dumps and tells how much was written, @end is where it was supposed to stop
+fn dump_range(sess: &session::Session, mem: &fs::File, start: u64, end: u64, path: &Path) -> Result<u64, ()> {
+    let size = copy_to_file(sess, mem, start, end, path)?;
+    if size == 0 {
+        let _ = fs::remove_file(path);
+        println!("{}{:#x}", "Cannot access memory at address ".red(), start);
+        return Err(());
+    }
+    println!("Dumped {:#x}-{:#x} ({} bytes) to '{}'", start, start + size, size, path.display());
+    if start + size < end {
+        println!("{}{:#x}", "Stopped at unreadable memory at ".yellow(), start + size);
+    }
+    Ok(size)
+}
+
+// 'dump memory FILE START END'
+pub fn memory(sess: &mut session::Session, path: &str, start: u64, end: u64) -> MainLoopAction {
+    if let Ok(mem) = procfs::open_proc_mem(sess.proc.target) {
+        let _ = dump_range(sess, &mem, start, end, Path::new(path));
+    }
+    MainLoopAction::None
+}
+
+// Dumps the readable regions matching @filter the same way as 'vmmap', to the current directory
+pub fn region(sess: &mut session::Session, filter: &str) -> MainLoopAction {
+    sess.proc.update_maps();
+    let addr = match filter.starts_with(|c: char| c.is_ascii_digit() || "$(*".contains(c)) {
+        true => match sess.eval(filter) {
+            Ok(addr) => Some(addr),
+            Err(_) => return MainLoopAction::None,
+        },
+        false => None,
+    };
+    let regions: Vec<&MemoryRegion> = sess.proc.maps().iter()
+        .filter(|region| region.read)
+        .filter(|region| match addr {
+            Some(addr) => region.contains(addr),
+            None => region.matches(filter),
+        })
+        .collect();
+    if regions.is_empty() {
+        println!("{}'{}'", "No readable memory region matches ".red(), filter);
+        return MainLoopAction::None;
+    }
+    let mem = match procfs::open_proc_mem(sess.proc.target) {
+        Ok(mem) => mem,
+        Err(_) => return MainLoopAction::None,
+    };
+    for region in regions {
+        let path = region_file_name(region);
+        if dump_range(sess, &mem, region.start, region.end, Path::new(&path)).is_err() {
+            break;
+        }
+    }
+    MainLoopAction::None
+}
+
+// Dumps every readable region into @dir, with the manifest listing
+// the address range, permissions, file offset, dumped file and backing path of each region
+pub fn all(sess: &mut session::Session, dir: &str) -> MainLoopAction {
+    let dir = Path::new(dir);
+    if let Err(errstr) = fs::create_dir_all(dir) {
+        println!("{}'{}': {}", "Cannot create directory ".red(), dir.display(), errstr);
+        return MainLoopAction::None;
+    }
+    sess.proc.update_maps();
+    let mem = match procfs::open_proc_mem(sess.proc.target) {
+        Ok(mem) => mem,
+        Err(_) => return MainLoopAction::None,
+    };
+
+    let mut manifest = String::from("# start end perms offset file path\n");
+    let (mut files, mut total) = (0, 0);
+    for region in sess.proc.maps().iter().filter(|region| region.read) {
+        let name = region_file_name(region);
+        let size = match copy_to_file(sess, &mem, region.start, region.end, &dir.join(&name)) {
+            Ok(size) => size,
+            Err(_) => return MainLoopAction::None,
+        };
+
+        // [vvar] and the like can't be read, listed without the file
+        let (name, end) = match size {
+            0 => {
+                let _ = fs::remove_file(dir.join(&name));
+                ("-".to_string(), region.end)
+            },
+            size => (name, region.start + size),
+        };
+        manifest += &manifest_line(region, end, &name);
+        if size > 0 {
+            files += 1;
+            total += size;
+        }
+    }
+    if let Err(errstr) = fs::write(dir.join(MANIFEST), manifest) {
+        println!("{}'{}': {}", "Cannot write ".red(), dir.join(MANIFEST).display(), errstr);
+        return MainLoopAction::None;
+    }
+    println!("Dumped {} regions ({} bytes) to '{}', see '{}'", files, total, dir.display(), MANIFEST);
+    MainLoopAction::None
+}
+
+// Writes the content of @path at @addr. The breakpoints are taken out during the write
+// and put back, so that they keep the restored bytes as the original ones.
+pub fn restore(sess: &mut session::Session, path: &str, addr: u64) -> MainLoopAction {
+    let mut data = Vec::new();
+    if let Err(errstr) = fs::File::open(path).and_then(|mut file| file.read_to_end(&mut data)) {
+        println!("{}'{}': {}", "Cannot read ".red(), path, errstr);
+        return MainLoopAction::None;
+    }
+    let target = sess.get_target();
+    if sess.breakpoints.remove_all(target).is_err() {
+        println!("{}", "Failed to remove breakpoints".red());
+        return MainLoopAction::None;
+    }
+    match sess.proc.write_memory(addr, &data) {
+        Ok(_) => println!("Restored {} bytes from '{}' to {:#x}-{:#x}", data.len(), path, addr, addr + data.len() as u64),
+        Err(_) => println!("{}{:#x}", "Cannot access memory at address ".red(), addr),
+    }
+    if sess.breakpoints.sync(target).is_err() {
+        println!("{}", "Failed to insert breakpoints".red());
+    }
+    MainLoopAction::None
+}
+
+// Writes the core file of the process, 'core.{PID}' if @path is None
+pub fn gcore(sess: &mut session::Session, path: Option<&str>) -> MainLoopAction {
+    let path = path.map_or_else(|| format!("core.{}", sess.proc.target), str::to_string);
+    sess.proc.update_maps();
+    if let Ok(size) = coredump::writer::write(&sess.proc, &sess.breakpoints, Path::new(&path)) {
+        println!("Saved corefile '{}' ({} bytes)", path, size);
+    }
+    MainLoopAction::None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn region(path: &str) -> MemoryRegion {
+        MemoryRegion {
+            start: 0x7f0000, end: 0x7f2000, read: true, write: false, exec: true, shared: false,
+            offset: 0x1000, dev: "08:01".to_string(), inode: 1, path: path.to_string(),
+        }
+    }
+
+    #[test]
+    fn file_names_are_single_words() {
+        assert_eq!(region_file_name(&region("/usr/lib/libc.so.6")), "7f0000-7f2000-libc.so.6.bin");
+        assert_eq!(region_file_name(&region("[heap]")), "7f0000-7f2000-heap.bin");
+        assert_eq!(region_file_name(&region("")), "7f0000-7f2000-anon.bin");
+        assert_eq!(region_file_name(&region("/tmp/my game (deleted)")), "7f0000-7f2000-my_game_(deleted).bin");
+    }
+
+    #[test]
+    fn manifest_path_is_the_rest_of_the_line() {
+        let region = region("/tmp/my  game");
+        let line = manifest_line(&region, 0x7f1000, &region_file_name(&region));
+        let fields: Vec<&str> = line.trim_end().splitn(6, ' ').collect();
+        assert_eq!(fields, vec!["0x7f0000", "0x7f1000", "r-xp", "0x1000", "7f0000-7f2000-my__game.bin", "/tmp/my  game"]);
+        assert!(manifest_line(&self::region(""), 0x7f2000, "f.bin").ends_with(" f.bin -\n"));
+    }
+}
@@ -0,0 +1,93 @@
+use super::*;
+use crate::process::{Proc, procfs};
+use crate::session::FollowForkMode;
+
+// Leaves @proc which is not debugged anymore, detached with 'set detach-on-fork on'
+// or kept stopped as the inferior @num otherwise.
+fn leave(session: &mut session::Session, num: usize, proc: Proc) {
+    if session.detach_on_fork {
+        for tid in proc.threads.tids() {
+            ptrace::detach(tid).unwrap_or(-1);
+        }
+        println!("[Detaching after fork from process {}]", proc.target);
+    } else {
+        println!("[Inferior {} (process {}) kept stopped]", num, proc.target);
+        session.hold(num, proc);
+    }
+}
+
+// The thread @tid of the current process has forked, debugging goes on with the
+// process @follow says. @early has new processes whose first stop has come already.
+// Returns the thread to resume.
+pub fn fork(session: &mut session::Session, tid: i32, follow: FollowForkMode,
+            early: &mut Vec<i32>) -> i32 {
+    let child = match ptrace::geteventmsg(tid) {
+        Ok(child) => child as i32,
+        Err(_) => return tid,
+    };
+    match early.iter().position(|pid| *pid == child) {
+        Some(index) => { early.remove(index); },
+        None => { waitpid(child).unwrap_or(WaitStatus::StillAlive); },
+    }
+    let mut proc = Proc::new();
+    proc.set(child).unwrap_or(-1);
+
+    match follow {
+        FollowForkMode::Parent => {
+            // the child inherited our trap instructions
+            session.breakpoints.remove_from_fork(child).unwrap_or(());
+            let num = session.new_inferior_num();
+            leave(session, num, proc);
+            tid
+        },
+        FollowForkMode::Child => {
+            println!("[Attaching after process {} fork to child process {}]", session.proc.target, child);
+            stop_threads(session, tid);
+            session.breakpoints.remove_from_fork(tid).unwrap_or(());
+            let parent = std::mem::replace(&mut session.proc, proc);
+            let num = session.inferior;
+            session.inferior = session.new_inferior_num();
+            leave(session, num, parent);
+            child
+        },
+    }
+}
+
+// The thread @tid of the current process has executed a new program
+pub fn exec(session: &mut session::Session, tid: i32) {
+    // the thread which called execve took over the thread id of the leader,
+    // its syscall-exit stop is still to come
+    let call = ptrace::geteventmsg(tid).ok()
+        .and_then(|former| session.proc.threads.get_mut(former as i32))
+        .and_then(|thread| thread.syscall.take());
+    session.exec_reload();
+    if let Some(thread) = session.proc.threads.current_mut() {
+        thread.syscall = call;
+    }
+    println!("process {} is executing new program: {}", session.proc.target, session.get_exe().display());
+    if session.breakpoints.sync(session.get_target()).is_err() {
+        println!("{}", "Failed to insert breakpoints".red());
+    }
+}
+
+// Whether @tid is a thread of the current process rather than a new process
+pub fn is_own_thread(session: &session::Session, tid: i32) -> bool {
+    procfs::get_tgid(tid) == Some(session.proc.target)
+}
+
+pub fn current(sess: &session::Session) -> MainLoopAction {
+    println!("[Current inferior is {} (process {})]", sess.inferior, sess.proc.target);
+    MainLoopAction::None
+}
+
+pub fn select(sess: &mut session::Session, num: usize) -> MainLoopAction {
+    if num == sess.inferior {
+        return current(sess);
+    }
+    if sess.select_inferior(num).is_err() {
+        println!("{}{}", "Invalid inferior number: ".red(), num);
+        return MainLoopAction::None;
+    }
+    print_pc(sess);
+    MainLoopAction::None
+}
@@ -1,7 +1,39 @@
 use super::*;
 
-pub fn regs(proc: &mut process::Proc) -> MainLoopAction {
-    proc.dump_regs();
+pub fn regs(sess: &mut session::Session) -> MainLoopAction {
+    match sess.target().getregs() {
+        Ok(regs) => ptrace::dumpregs(&regs, |value| sess.symbols.symbolize(value)),
+        Err(_) => println!("{}", "Failed to read registers".red()),
+    }
+    MainLoopAction::None
+}
+
+pub fn all_registers(sess: &mut session::Session) -> MainLoopAction {
+    let regs = match sess.target().getregs() {
+        Ok(regs) => regs,
+        Err(_) => {
+            println!("{}", "Failed to read registers".red());
+            return MainLoopAction::None;
+        },
+    };
+    ptrace::dumpregs(&regs, |value| sess.symbols.symbolize(value));
+    for desc in Regs::extra_registers() {
+        let value = (desc.get)(&regs);
+        println!("  {:6} {:16x} {:20}", desc.name, value, value);
+    }
+
+    if sess.invalid() {
+        println!("{}", "Floating point registers are not read from core files".yellow());
+        return MainLoopAction::None;
+    }
+    match Regs::vector_registers(sess.get_target()) {
+        Ok(list) => {
+            for (name, value) in list {
+                println!("  {:6} {}", name, value);
+            }
+        },
+        Err(_) => println!("{}", "Failed to read floating point registers".red()),
+    }
     MainLoopAction::None
 }
 
@@ -9,4 +41,107 @@ pub fn proc(sess: &mut session::Session) -> MainLoopAction {
     sess.proc.update();
     sess.proc.dump();
     MainLoopAction::None
-}
\ No newline at end of file
+}
+
+pub fn breakpoints(sess: &session::Session) -> MainLoopAction {
+    let catchpoint = syscall::describe(sess);
+    if sess.breakpoints.ids().is_empty() && sess.watchpoints.ids().is_empty() && catchpoint.is_none() {
+        println!("No breakpoints");
+        return MainLoopAction::None;
+    }
+    println!("{:<4} {:<4} {:<6} {:<18} What", "Num", "Enb", "Hits", "Address");
+    for bp in sess.breakpoints.iter().filter(|bp| !bp.temporary) {
+        println!("{:<4} {:<4} {:<6} {:<#18x} {}",
+                 bp.id, if bp.enabled { "y" } else { "n" }, bp.hits, bp.addr,
+                 sess.symbols.symbolize(bp.addr).unwrap_or_default());
+        if let Some(cond) = &bp.condition {
+            println!("        stop only if {}", cond.text());
+        }
+        if bp.ignore_count > 0 {
+            println!("        will ignore next {} hits", bp.ignore_count);
+        }
+    }
+    for wp in sess.watchpoints.iter() {
+        let what = wp.location.clone().unwrap_or_else(|| format!("{:#x}", wp.addr));
+        println!("{:<4} {:<4} {:<6} {:<#18x} {} {} ({} bytes)",
+                 wp.id, if wp.enabled { "y" } else { "n" }, wp.hits, wp.addr,
+                 wp.type_name(), what, wp.len);
+    }
+    if let Some(what) = catchpoint {
+        println!("{:<4} {:<4} {:<6} {:<18} {}", "-", "y", "", "<catchpoint>", what);
+    }
+    MainLoopAction::None
+}
+
+pub fn watchpoints(sess: &session::Session) -> MainLoopAction {
+    if sess.watchpoints.ids().is_empty() {
+        println!("No watchpoints");
+        return MainLoopAction::None;
+    }
+    println!("{:<4} {:<4} {:<6} {:<18} {:<9} {:<15} What", "Num", "Enb", "Hits", "Address", "Mode", "Type");
+    for wp in sess.watchpoints.iter() {
+        let what = wp.location.clone().unwrap_or_else(|| format!("{:#x}", wp.addr));
+        println!("{:<4} {:<4} {:<6} {:<#18x} {:<9} {:<15} {} ({} bytes)",
+                 wp.id, if wp.enabled { "y" } else { "n" }, wp.hits, wp.addr,
+                 if wp.software { "software" } else { "hardware" }, wp.type_name(), what, wp.len);
+    }
+    MainLoopAction::None
+}
+
+// Prints disposition of @sig, or of every signal if None
+pub fn signals(sess: &session::Session, sig: Option<Signal>) -> MainLoopAction {
+    let yes_no = |b: bool| if b { "Yes" } else { "No" };
+    println!("{:<10} {:<5} {:<6} {:<5} Description", "Signal", "Stop", "Print", "Pass");
+    let list: Vec<Signal> = match sig {
+        Some(sig) => vec![sig],
+        None => Signal::iterator().collect(),
+    };
+    for sig in list {
+        let disposition = sess.signals.get(sig);
+        println!("{:<10} {:<5} {:<6} {:<5} {}",
+                 sig.as_str(), yes_no(disposition.stop), yes_no(disposition.print),
+                 yes_no(disposition.pass), get_strsig(sig as i32));
+    }
+    MainLoopAction::None
+}
+
+pub fn threads(sess: &session::Session) -> MainLoopAction {
+    println!("  {:<4} {:<30} Frame", "Id", "Target Id");
+
+    // threads of the core file are numbered in the order of the notes
+    if let Some(core) = &sess.core {
+        for (i, tid) in core.tids().into_iter().enumerate() {
+            let mark = if tid == core.tid() { "*" } else { " " };
+            let frame = core.thread_regs(tid)
+                .ok()
+                .and_then(|regs| regs.get("pc"))
+                .map(|pc| sess.format_addr(pc))
+                .unwrap_or_default();
+            println!("{} {:<4} {:<30} {}", mark, i + 1, format!("Thread {}", tid), frame);
+        }
+        return MainLoopAction::None;
+    }
+    for thread in sess.proc.threads.iter() {
+        let mark = if thread.tid == sess.proc.tid() { "*" } else { " " };
+        let frame = Regs::read(thread.tid)
+            .ok()
+            .and_then(|regs| regs.get("pc"))
+            .map(|pc| sess.format_addr(pc))
+            .unwrap_or_default();
+        println!("{} {:<4} {:<30} {}", mark, thread.num, thread::describe(sess, thread.tid), frame);
+    }
+    MainLoopAction::None
+}
+
+pub fn inferiors(sess: &session::Session) -> MainLoopAction {
+    println!("  {:<4} {:<16} Executable", "Num", "Process");
+    if sess.valid() {
+        let process = format!("process {}", sess.proc.target);
+        println!("* {:<4} {:<16} {}", sess.inferior, process, sess.get_exe().display());
+    }
+    for inferior in sess.inferiors.iter() {
+        let process = format!("process {}", inferior.proc.target);
+        println!("  {:<4} {:<16} {}", inferior.num, process, inferior.proc.get_exe().display());
+    }
+    MainLoopAction::None
+}
@@ -0,0 +1,124 @@
+use super::*;
+
+// Upper bound of a string read by 'x/s'
+const MAX_STRING_LEN: usize = 4096;
+
+// Display format of 'x' command, '/<count><fmt><size>'
+pub struct Format {
+    pub count: usize,
+    pub fmt: char,
+    pub size: usize,
+}
+
+impl Format {
+    // Parses the part after 'x', e.g) "/4xg", empty string gives the default format
+    pub fn parse(spec: &str) -> Result<Self, ()> {
+        let spec = match spec {
+            "" => return Ok(Format { count: 1, fmt: 'x', size: 4 }),
+            spec => spec.strip_prefix('/').ok_or(())?,
+        };
+
+        let digits = spec.chars().take_while(|c| c.is_ascii_digit()).count();
+        let count = match digits {
+            0 => 1,
+            _ => spec[..digits].parse::<usize>().map_err(|_| ())?,
+        };
+
+        let mut fmt = None;
+        let mut size = None;
+        for c in spec[digits..].chars() {
+            match c {
+                'x' | 'd' | 'u' | 'c' | 's' | 'i' if fmt.is_none() => fmt = Some(c),
+                'b' if size.is_none() => size = Some(1),
+                'h' if size.is_none() => size = Some(2),
+                'w' if size.is_none() => size = Some(4),
+                'g' if size.is_none() => size = Some(8),
+                _ => return Err(()),
+            }
+        }
+
+        let fmt = fmt.unwrap_or('x');
+        let size = match fmt {
+            'c' | 's' | 'i' => 1,
+            _ => size.unwrap_or(4),
+        };
+        Ok(Format { count, fmt, size })
+    }
+}
+
+pub fn format_unit(fmt: char, size: usize, bytes: &[u8]) -> String {
+    let mut raw = [0u8; 8];
+    raw[..size].copy_from_slice(bytes);
+    let value = u64::from_le_bytes(raw);
+
+    match fmt {
+        'd' => {
+            // sign-extend from @size bytes
+            let shift = 64 - size * 8;
+            format!("{}", ((value << shift) as i64) >> shift)
+        },
+        'u' => format!("{}", value),
+        'c' => format!("{:<4}'{}'", value, (value as u8 as char).escape_default()),
+        _ => format!("{:#0width$x}", value, width = size * 2 + 2),
+    }
+}
+
+fn examine_strings(target: &dyn Target, count: usize, mut addr: u64) {
+    for _ in 0..count {
+        let mut string = Vec::new();
+        let mut terminated = false;
+        while !terminated && string.len() < MAX_STRING_LEN {
+            let chunk = match target.read_memory(addr + string.len() as u64, 64) {
+                Ok(chunk) => chunk,
+                Err(_) => break,
+            };
+            match chunk.iter().position(|b| *b == 0) {
+                Some(nul) => {
+                    string.extend_from_slice(&chunk[..nul]);
+                    terminated = true;
+                },
+                None => string.extend_from_slice(&chunk),
+            }
+        }
+
+        if string.is_empty() && !terminated {
+            println!("{}{:#x}", "Cannot access memory at address ".red(), addr);
+            return;
+        }
+        println!("{:#x}: \"{}\"", addr, String::from_utf8_lossy(&string).escape_default());
+        addr += string.len() as u64 + 1;
+    }
+}
+
+pub fn examine(sess: &mut session::Session, format: &Format, addr: u64) -> MainLoopAction {
+    match format.fmt {
+        's' => {
+            examine_strings(sess.target(), format.count, addr);
+            return MainLoopAction::None;
+        },
+        'i' => return disas::disassemble(sess, addr, Some(format.count)),
+        _ => (),
+    }
+
+    let bytes = match sess.target().read_memory(addr, format.count * format.size) {
+        Ok(bytes) => bytes,
+        Err(_) => {
+            println!("{}{:#x}", "Cannot access memory at address ".red(), addr);
+            return MainLoopAction::None;
+        },
+    };
+
+    let per_line = match (format.fmt, format.size) {
+        (_, 8) => 2,
+        ('c', _) | (_, 1) | (_, 2) => 8,
+        _ => 4,
+    };
+    for (line, units) in bytes.chunks(per_line * format.size).enumerate() {
+        let line_addr = addr + (line * per_line * format.size) as u64;
+        let values: Vec<String> = units.chunks(format.size)
+            .map(|unit| format_unit(format.fmt, format.size, unit))
+            .collect();
+        println!("{:#x}:\t{}", line_addr, values.join("\t"));
+    }
+    MainLoopAction::None
+}
@@ -1,9 +1,32 @@
-use nix::sys::wait::WaitStatus;
+use std::convert::TryFrom;
+use std::path::Path;
+
+use nix::sys::wait::{WaitStatus, WaitPidFlag};
 use nix::sys::signal::Signal;
+use nix::unistd::Pid;
+
+use colored::*;
 
 use crate::{traits::*, process, ptrace, session};
+use crate::arch::{Regs, RegisterFile, WatchKind};
+use crate::coredump::reader::CoreFile;
+use crate::watchpoint::Hit;
 
 pub mod info;
+pub mod backtrace;
+pub mod breakpoints;
+pub mod memory;
+pub mod print;
+pub mod scan;
+pub mod search;
+pub mod set;
+pub mod disas;
+pub mod dump;
+pub mod show;
+pub mod thread;
+pub mod inferior;
+pub mod syscall;
+pub mod track;
 
 pub enum MainLoopAction {
     None,
@@ -27,102 +50,859 @@ fn get_strsig(signum: i32) -> &'static str {
  */
 
 pub fn attach(session: &mut session::Session, newtarget: i32) -> MainLoopAction {
-    match ptrace::attach_wait(newtarget) {
-        Ok(_) => {
-            println!("Successfully attached to pid: {}", newtarget);
-            session.set_target(newtarget).unwrap_or(-1);
-            session.set_type(session::Type::Attach);
+    if ptrace::attach_wait(newtarget).is_ok() {
+        println!("Successfully attached to pid: {}", newtarget);
+        session.set_target(newtarget).unwrap_or(-1);
+        session.set_type(session::Type::Attach);
+
+        // set elf with '/proc/{PID}/exe'
+        session.reload_elf();
+    }
+    MainLoopAction::None
+}
+
+// Loads the core file at @path in place of a process, drops the one loaded if None
+pub fn core(session: &mut session::Session, path: Option<&str>) -> MainLoopAction {
+    let path = match path {
+        Some(path) => path,
+        None => {
+            if session.core.take().is_some() {
+                println!("No core file now.");
+            }
+            return MainLoopAction::None;
         },
-        Err(_) => (),
+    };
+    let core = match CoreFile::open(Path::new(path)) {
+        Ok(core) => core,
+        Err(_) => return MainLoopAction::None,
+    };
+    println!("Core was generated by `{}'", core.psargs);
+    let signal = core.threads.first().map_or(0, |thread| thread.signal);
+    match Signal::try_from(signal) {
+        Ok(sig) => println!("Program terminated with signal {}, {}", sig, get_strsig(signal)),
+        Err(_) => println!("Program stopped without a signal"),
+    }
+    println!("[Current thread is 1 (Thread {}) of process {}]", core.tid(), core.pid());
+    if core.get_exe().as_os_str().is_empty() && session.elf.is_none() {
+        println!("{}", "The executable is unknown, no files are listed in the core file. Give it with --file".red());
     }
+    session.load_core(core);
+    disas::context(session);
     MainLoopAction::None
 }
 
 pub fn detach(sess: &mut session::Session) -> MainLoopAction {
-    if ptrace::detach(sess.get_target()).is_ok() {
+    // leave no trap instruction behind in the process
+    if sess.breakpoints.remove_all(sess.get_target()).is_err() {
+        println!("{}", "Failed to remove breakpoints".red());
+    }
+    let detached = sess.proc.threads.tids()
+        .into_iter()
+        .all(|tid| ptrace::detach(tid).is_ok());
+    if detached {
         sess.release();
     }
     MainLoopAction::None
 }
 
-pub fn cont(proc: &mut process::Proc) -> MainLoopAction {
-    ptrace::cont(proc.target).unwrap_or(-1);
+// Waits for the thread @tid, or for any thread if -1
+fn waitpid(tid: i32) -> nix::Result<WaitStatus> {
+    nix::sys::wait::waitpid(Pid::from_raw(tid), Some(WaitPidFlag::__WALL))
+}
+
+// Signal number to deliver to @tid on resume, consuming its pending signal
+fn take_pending_signal(session: &mut session::Session, tid: i32) -> i32 {
+    session.proc.threads.get_mut(tid)
+        .and_then(|thread| thread.pending_signal.take())
+        .map_or(0, |sig| sig as i32)
+}
+
+// Resumes @tid with @signal, a single instruction for the software watchpoints,
+// otherwise stopping at system calls if they are caught or traced
+fn resume_thread(session: &mut session::Session, tid: i32, signal: i32) {
+    if session.watchpoints.software() {
+        if let Some(thread) = session.proc.threads.get_mut(tid) {
+            thread.syscall = None;
+        }
+        ptrace::singlestep(tid, signal).unwrap_or(-1);
+        return;
+    }
+    if session.syscalls.active() {
+        ptrace::syscall(tid, signal).unwrap_or(-1);
+        return;
+    }
+    if let Some(thread) = session.proc.threads.get_mut(tid) {
+        thread.syscall = None;
+    }
+    ptrace::cont(tid, signal).unwrap_or(-1);
+}
+
+// Registers a thread reported by the clone event or by its first stop, waits for
+// the first stop unless @stopped is set. Returns false if it's already known.
+fn add_thread(session: &mut session::Session, tid: i32, stopped: bool) -> bool {
+    if session.proc.threads.contains(tid) {
+        return false;
+    }
+    if !stopped {
+        waitpid(tid).unwrap_or(WaitStatus::StillAlive);
+    }
+    let num = session.proc.threads.add(tid);
+    println!("[New Thread {} ({})]", num, tid);
+    sync_watchpoints(session, &[tid]);
+    true
+}
+
+fn remove_thread(session: &mut session::Session, tid: i32) {
+    if let Some(thread) = session.proc.threads.remove(tid) {
+        println!("[Thread {} ({}) exited]", thread.num, tid);
+    }
+}
+
+// Programs the watchpoints into the debug registers of @tids, they are per thread
+fn sync_watchpoints(session: &mut session::Session, tids: &[i32]) {
+    if session.watchpoints.ids().is_empty() {
+        return;
+    }
+    match session.watchpoints.sync(&session.proc, tids) {
+        Ok(moved) => {
+            for id in moved {
+                println!("[Watchpoint {} is checked by single-stepping, debug registers are all in use]", id);
+            }
+        },
+        Err(_) => println!("{}", "Failed to set hardware watchpoints".red()),
+    }
+}
+
+// Whether the SIGTRAP of @tid is just a step for the software watchpoints, which go on
+// stepping unless a watched value has changed or a breakpoint is hit.
+fn software_watch_step(session: &mut session::Session, tid: i32) -> bool {
+    if !session.watchpoints.software() {
+        return false;
+    }
+    let pending = session.proc.threads.get(tid).is_none_or(|thread| thread.watch_hit.is_some());
+    let at_breakpoint = Regs::read(tid).ok()
+        .and_then(|regs| regs.get("pc"))
+        .map(|pc| pc.wrapping_sub(Regs::BREAKPOINT_PC_OFFSET))
+        .is_some_and(|addr| session.breakpoints.find_by_addr(addr).is_some_and(|bp| bp.inserted()));
+    if pending || at_breakpoint {
+        return false;
+    }
+    !check_software_watch(session, tid)
+}
+
+// Records the hit on @tid if a value watched in software has changed
+fn check_software_watch(session: &mut session::Session, tid: i32) -> bool {
+    let hit = match session.watchpoints.check_software(&session.proc) {
+        Some(hit) => hit,
+        None => return false,
+    };
+    if let Some(thread) = session.proc.threads.get_mut(tid) {
+        thread.watch_hit = Some(hit);
+    }
+    true
+}
+
+// Records the hit on the thread if the SIGTRAP of @tid came from a watchpoint. Returns None
+// for the other traps, otherwise whether the hit is worth a stop: 'watch' stops only if the
+// value has changed and 'rwatch' emulated with an access watchpoint only if it hasn't.
+fn check_watch_trap(session: &mut session::Session, tid: i32) -> Option<bool> {
+    if session.watchpoints.ids().is_empty() {
+        return None;
+    }
+    let index = Regs::watch_hit(tid).ok()??;
+    if Regs::WATCH_BEFORE_ACCESS {
+        // let the access happen with the watchpoints out of the way
+        Regs::set_watch_slots(tid, &[]).ok()?;
+        ptrace::singlestep(tid, 0).ok()?;
+        waitpid(tid).ok()?;
+        sync_watchpoints(session, &[tid]);
+    }
+
+    let wp = session.watchpoints.find_by_slot(index)?;
+    let new = session.proc.read_memory(wp.addr, wp.len).ok();
+    let old = std::mem::replace(&mut wp.value, new.clone());
+    let stop = match wp.kind {
+        WatchKind::Write => old != new,
+        WatchKind::Read => Regs::WATCH_READ_ONLY || old == new,
+        WatchKind::Access => true,
+    };
+    if stop {
+        wp.hits += 1;
+        let id = wp.id;
+        if let Some(thread) = session.proc.threads.get_mut(tid) {
+            thread.watch_hit = Some(Hit { id, old, new });
+        }
+    }
+    Some(stop)
+}
+
+// Makes @tid the current thread, telling the user if it's another one
+fn switch_thread(session: &mut session::Session, tid: i32) {
+    if session.proc.tid() == tid || session.proc.threads.select(tid).is_err() {
+        return;
+    }
+    if let Some(thread) = session.proc.threads.get(tid) {
+        println!("[Switching to thread {} ({})]", thread.num, tid);
+    }
+}
+
+// Rewinds pc of @tid if it trapped on one of our breakpoints, so that
+// the breakpoint is hit again when the thread is resumed.
+fn rewind_breakpoint(session: &mut session::Session, tid: i32) -> Result<(), ()> {
+    let mut regs = Regs::read(tid)?;
+    let addr = regs.get("pc").ok_or(())?.wrapping_sub(Regs::BREAKPOINT_PC_OFFSET);
+    if session.breakpoints.find_by_addr(addr).is_some_and(|bp| bp.inserted()) {
+        regs.set("pc", addr)?;
+        regs.write(tid)?;
+        if let Some(thread) = session.proc.threads.get_mut(tid) {
+            thread.pending_hit = true;
+        }
+    }
+    Ok(())
+}
+
+// Stops every thread but @except which has stopped already (all-stop). Events reported
+// meanwhile are kept: signals become pending and breakpoint hits will happen again.
+fn stop_threads(session: &mut session::Session, except: i32) {
+    let pid = session.proc.target;
+    for tid in session.proc.threads.tids().into_iter().filter(|tid| *tid != except) {
+        unsafe { libc::syscall(libc::SYS_tgkill, pid, tid, libc::SIGSTOP); }
+        loop {
+            match waitpid(tid) {
+                Ok(WaitStatus::Stopped(_, Signal::SIGSTOP)) => break,
+                // watchpoint hits are kept on the thread, reported on the next resume
+                Ok(WaitStatus::Stopped(_, Signal::SIGTRAP)) => {
+                    if check_watch_trap(session, tid).is_none() {
+                        rewind_breakpoint(session, tid).unwrap_or(());
+                    }
+                },
+                Ok(WaitStatus::Stopped(_, signum)) => {
+                    let pass = session.signals.get(signum).pass;
+                    if let Some(thread) = session.proc.threads.get_mut(tid).filter(|_| pass) {
+                        thread.pending_signal = Some(signum);
+                    }
+                },
+                Ok(WaitStatus::PtraceEvent(_, _, libc::PTRACE_EVENT_CLONE)) => {
+                    if let Ok(new) = ptrace::geteventmsg(tid) {
+                        add_thread(session, new as i32, false);
+                    }
+                },
+                // traced but not reported, only one stop is reported at a time
+                Ok(WaitStatus::PtraceSyscall(_)) => {
+                    syscall::stop(session, tid);
+                },
+                // not the thread which reported the stop, stay with this process
+                Ok(WaitStatus::PtraceEvent(_, _, libc::PTRACE_EVENT_FORK)) |
+                Ok(WaitStatus::PtraceEvent(_, _, libc::PTRACE_EVENT_VFORK)) => {
+                    inferior::fork(session, tid, session::FollowForkMode::Parent, &mut Vec::new());
+                },
+                _ => {
+                    remove_thread(session, tid);
+                    break;
+                },
+            }
+            // SIGSTOP is still queued, the thread stops again before running any code
+            resume_thread(session, tid, 0);
+        }
+    }
+}
+
+// Resumes every thread with its pending signal
+fn resume_threads(session: &mut session::Session) {
+    for tid in session.proc.threads.tids() {
+        let signal = take_pending_signal(session, tid);
+        if let Some(thread) = session.proc.threads.get_mut(tid) {
+            thread.pending_hit = false;
+        }
+        resume_thread(session, tid, signal);
+    }
+}
+
+// Executes a single instruction, the original one if there is a breakpoint at pc,
+// returns the wait status if the process didn't come back with SIGTRAP.
+// The pending signal is delivered only if @deliver is set.
+fn step_instruction(session: &mut session::Session, deliver: bool) -> Option<WaitStatus> {
+    let target = session.get_target();
+    let pc = session.proc.getreg("pc").ok()?;
+    if let Some(bp) = session.breakpoints.find_by_addr_mut(pc) {
+        bp.remove(target).ok()?;
+    }
+    let signal = if deliver { take_pending_signal(session, target) } else { 0 };
+
+    // no syscall stop while stepping, the call is left untracked
+    if let Some(thread) = session.proc.threads.current_mut() {
+        thread.syscall = None;
+    }
+    sync_watchpoints(session, &[target]);
+    ptrace::singlestep(target, signal).ok()?;
+
+    // other threads stay stopped while stepping
+    loop {
+        match waitpid(target) {
+            Ok(WaitStatus::Stopped(_, Signal::SIGTRAP)) => {
+                // put back the breakpoint we've just stepped over
+                if session.breakpoints.sync(target).is_err() {
+                    println!("{}", "Failed to insert breakpoints".red());
+                }
+                if check_watch_trap(session, target) == Some(true) || check_software_watch(session, target) {
+                    return Some(WaitStatus::Stopped(Pid::from_raw(target), Signal::SIGTRAP));
+                }
+                return None;
+            },
+            Ok(WaitStatus::PtraceEvent(_, _, libc::PTRACE_EVENT_CLONE)) => {
+                if let Ok(new) = ptrace::geteventmsg(target) {
+                    add_thread(session, new as i32, false);
+                }
+                ptrace::singlestep(target, 0).ok()?;
+            },
+            Ok(WaitStatus::PtraceEvent(_, _, libc::PTRACE_EVENT_FORK)) |
+            Ok(WaitStatus::PtraceEvent(_, _, libc::PTRACE_EVENT_VFORK)) => {
+                let follow = session.follow_fork_mode;
+                match inferior::fork(session, target, follow, &mut Vec::new()) {
+                    // the child is stopped right after fork
+                    tid if tid != target => return None,
+                    _ => ptrace::singlestep(target, 0).ok()?,
+                };
+            },
+            // the signal came before the instruction is executed, try again
+            Ok(WaitStatus::Stopped(_, signum)) if !session.signals.get(signum).stop => {
+                let pass = session.signals.get(signum).pass;
+                if let Some(thread) = session.proc.threads.current_mut().filter(|_| pass) {
+                    thread.pending_signal = Some(signum);
+                }
+                ptrace::singlestep(target, 0).ok()?;
+            },
+            // stopped at the first instruction of the new program
+            Ok(WaitStatus::PtraceEvent(_, _, libc::PTRACE_EVENT_EXEC)) => {
+                inferior::exec(session, target);
+                return None;
+            },
+            Ok(status) => return Some(status),
+            Err(err) => {
+                println!("waitpid failed: {:?}", err);
+                return None;
+            },
+        }
+    }
+}
+
+// Rewinds pc to the breakpoint address if the process trapped on one of ours,
+// returns (id, address, temporary) of the breakpoint.
+fn check_breakpoint_hit(session: &mut session::Session) -> Option<(usize, u64, bool)> {
+    let pc = session.proc.getreg("pc").ok()?;
+    let addr = pc.wrapping_sub(Regs::BREAKPOINT_PC_OFFSET);
+    let bp = session.breakpoints
+        .find_by_addr_mut(addr)
+        .filter(|bp| bp.inserted())?;
+    let temporary = bp.temporary || bp.stop_once;
+    if !temporary {
+        bp.hits += 1;
+    }
+    let id = bp.id;
+    session.breakpoints.temporary_hit = temporary;
+    session.proc.setreg("pc", addr).ok()?;
+    Some((id, addr, temporary))
+}
+
+// Whether the breakpoint trap of @tid is passed over silently since the condition is false
+// or the hit is ignored, pc is rewound to run the original instruction on the next resume.
+fn skip_breakpoint(session: &mut session::Session, tid: i32) -> bool {
+    if session.proc.threads.get(tid).is_none_or(|thread| thread.watch_hit.is_some()) {
+        return false;
+    }
+    let mut regs = match Regs::read(tid) {
+        Ok(regs) => regs,
+        Err(_) => return false,
+    };
+    let addr = match regs.get("pc") {
+        Some(pc) => pc.wrapping_sub(Regs::BREAKPOINT_PC_OFFSET),
+        None => return false,
+    };
+    let bp = match session.breakpoints.find_by_addr(addr).filter(|bp| bp.inserted() && !bp.temporary && !bp.stop_once) {
+        Some(bp) => bp,
+        None => return false,
+    };
+    let id = bp.id;
+    match bp.condition.as_ref().map(|cond| cond.eval(session, tid)) {
+        // stops to let the user fix the condition
+        Some(Err(_)) => {
+            println!("{}{}", "Error in testing condition of breakpoint ".red(), id);
+            return false;
+        },
+        Some(Ok(0)) => (),
+        _ => match session.breakpoints.get_mut(id) {
+            Some(bp) if bp.ignore_count > 0 => {
+                bp.ignore_count -= 1;
+                bp.hits += 1;
+            },
+            _ => return false,
+        },
+    }
+    regs.set("pc", addr).is_ok() && regs.write(tid).is_ok()
+}
+
+// Length of the call instruction at @pc, None if it's not a call
+fn call_length_at(session: &session::Session, pc: u64) -> Option<usize> {
+    let mut code = session.proc.read_memory(pc, Regs::MAX_INSN_SIZE).ok()?;
+    session.breakpoints.unpatch(pc, &mut code);
+    Regs::call_length(&code)
+}
+
+fn print_pc(session: &mut session::Session) {
+    // the process may have mapped or unmapped memory while running
+    session.proc.update_maps();
+    session.proc.getreg("pc")
+        .map(|pc| { println!("Stopped at: pc={}", session.format_addr(pc)); })
+        .unwrap_or_default();
+    disas::context(session);
+}
+
+// Shows where the program stopped after it ran, with the tracked memory read again
+fn report_stop(session: &mut session::Session) {
+    let tid = session.proc.tid();
+    set_siginfo(session, tid);
+    print_pc(session);
+    track::report(session);
+}
+
+// Keeps the signal information of @tid as '$_siginfo.si_signo', '.si_code' and '.si_addr',
+// '$_siginfo' itself is the signal number
+fn set_siginfo(session: &mut session::Session, tid: i32) {
+    let info = match ptrace::getsiginfo(tid) {
+        Ok(info) => info,
+        Err(_) => return,
+    };
+    // the address is only given by the faults
+    let addr = match info.si_signo {
+        libc::SIGSEGV | libc::SIGBUS | libc::SIGILL | libc::SIGFPE | libc::SIGTRAP => Some(unsafe { info.si_addr() } as u64),
+        _ => None,
+    };
+    session.variables.insert("_siginfo".to_string(), info.si_signo as u64);
+    session.variables.insert("_siginfo.si_signo".to_string(), info.si_signo as u64);
+    session.variables.insert("_siginfo.si_code".to_string(), info.si_code as i64 as u64);
+    match addr {
+        Some(addr) => session.variables.insert("_siginfo.si_addr".to_string(), addr),
+        None => session.variables.remove("_siginfo.si_addr"),
+    };
+}
 
-    // catching signal from the process
-    match nix::sys::wait::waitpid(proc.get_pid(), None) {
+fn report_status(session: &mut session::Session, status: nix::Result<WaitStatus>) {
+    match status {
+        Ok(WaitStatus::Exited(tid, _)) if tid.as_raw() != session.proc.target => {
+            remove_thread(session, tid.as_raw());
+        },
         Ok(WaitStatus::Exited(_, exit_status)) => {
             println!("\nProgram terminated with status: {}", exit_status);
-            proc.release();
+            session.variables.insert("_exitcode".to_string(), exit_status as u64);
+            session.release();
         },
         Ok(WaitStatus::Stopped(_, signum)) => {
-            let sigstr = get_strsig(signum as i32);
-            match signum {
-                Signal::SIGTERM => {
-                    ptrace::sigkill(proc.target).unwrap();
-
-                    println!("\nProgram terminated with signal {}, {}", signum, sigstr);
-                    proc.release();
+            let watch_hit = match signum {
+                Signal::SIGTRAP => session.proc.threads.current_mut().and_then(|thread| thread.watch_hit.take()),
+                _ => None,
+            };
+            if let Some(hit) = watch_hit {
+                breakpoints::report_watch(session, &hit);
+                report_stop(session);
+                return;
+            }
+            let hit = match signum {
+                Signal::SIGTRAP => check_breakpoint_hit(session),
+                _ => None,
+            };
+            match hit {
+                // the command which set it reports the stop by itself
+                Some((_, _, true)) => return,
+                Some((id, addr, false)) => {
+                    println!("\nBreakpoint {}, at {}", id, session.format_addr(addr));
                 },
-                _ => {
-                    println!("\nProgram Stopped with signal {}, {}", signum, sigstr);
-                    proc.getreg("pc")
-                        .map(|pc| { println!("Stopped at: pc={:#x}", pc); })
-                        .unwrap_or_default();
+                None => {
+                    // delivered on resume unless 'handle SIG nopass'
+                    let pass = session.signals.get(signum).pass;
+                    if let Some(thread) = session.proc.threads.current_mut().filter(|_| pass) {
+                        thread.pending_signal = Some(signum);
+                    }
+                    let sigstr = get_strsig(signum as i32);
+                    println!("\nProgram received signal {}, {}", signum, sigstr);
                 },
             }
+            report_stop(session);
+        },
+        Ok(WaitStatus::PtraceSyscall(_)) => {
+            syscall::report(session);
+            report_stop(session);
         },
         Ok(WaitStatus::Signaled(_, signum, _)) => {
             let sigstr = get_strsig(signum as i32);
-            match signum {
-                Signal::SIGKILL => {
-                    println!("\nProgram received {}, {}, terminating...", signum, sigstr);
-                    proc.release(); 
-                },
-                _ => println!("Signaled {}", signum),
-            }
+            println!("\nProgram terminated with signal {}, {}", signum, sigstr);
+            session.release();
         },
         Ok(status) => println!("\nProgram received status: {:?}", status),
         Err(err) => println!("waitpid failed: {:?}", err),
     }
+}
+
+pub fn cont(session: &mut session::Session) -> MainLoopAction {
+    // passing over the breakpoints whose condition is false until a stop is reported
+    loop {
+        // a watchpoint hit while other thread was reporting its stop comes first
+        let pending = session.proc.threads.iter()
+            .find(|thread| thread.watch_hit.is_some())
+            .map(|thread| thread.tid);
+        if let Some(tid) = pending {
+            switch_thread(session, tid);
+            report_status(session, Ok(WaitStatus::Stopped(Pid::from_raw(tid), Signal::SIGTRAP)));
+            return MainLoopAction::None;
+        }
+
+        // execute the original instruction under the breakpoints threads are stopped at,
+        // except for the hits not reported yet
+        let current = session.proc.tid();
+        for tid in session.proc.threads.tids() {
+            let pending_hit = session.proc.threads.get(tid).is_some_and(|thread| thread.pending_hit);
+            let at_breakpoint = Regs::read(tid).ok()
+                .and_then(|regs| regs.get("pc"))
+                .map(|pc| session.breakpoints.find_by_addr(pc).is_some_and(|bp| bp.enabled || bp.stop_once))
+                .unwrap_or(false);
+            if !at_breakpoint || pending_hit {
+                continue;
+            }
+            session.proc.threads.select(tid).unwrap_or(());
+            if let Some(status) = step_instruction(session, false) {
+                report_status(session, Ok(status));
+                return MainLoopAction::None;
+            }
+        }
+        session.proc.threads.select(current).unwrap_or(());
+
+        let target = session.get_target();
+        if session.breakpoints.sync(target).is_err() {
+            println!("{}", "Failed to insert breakpoints".red());
+        }
+        let tids = session.proc.threads.tids();
+        sync_watchpoints(session, &tids);
+        resume_threads(session);
+
+        // catching signal from any thread, thread creation/exit, fork, exec, system calls and
+        // 'handle SIG nostop' are handled here resuming the thread silently.
+        let mut early = Vec::new();
+        let status = loop {
+            let status = waitpid(-1);
+            match status {
+                Ok(WaitStatus::PtraceEvent(pid, _, libc::PTRACE_EVENT_FORK)) |
+                Ok(WaitStatus::PtraceEvent(pid, _, libc::PTRACE_EVENT_VFORK)) => {
+                    let follow = session.follow_fork_mode;
+                    let tid = inferior::fork(session, pid.as_raw(), follow, &mut early);
+                    resume_thread(session, tid, 0);
+                },
+                Ok(WaitStatus::PtraceEvent(pid, _, libc::PTRACE_EVENT_EXEC)) => {
+                    inferior::exec(session, pid.as_raw());
+                    resume_thread(session, pid.as_raw(), 0);
+                },
+                Ok(WaitStatus::PtraceSyscall(pid)) => {
+                    if syscall::stop(session, pid.as_raw()) {
+                        break status;
+                    }
+                    resume_thread(session, pid.as_raw(), 0);
+                },
+                // the first stop of a new process can come before the fork event
+                Ok(WaitStatus::Stopped(pid, Signal::SIGSTOP))
+                    if !session.proc.threads.contains(pid.as_raw()) && !inferior::is_own_thread(session, pid.as_raw()) => {
+                    early.push(pid.as_raw());
+                },
+                Ok(WaitStatus::PtraceEvent(pid, _, libc::PTRACE_EVENT_CLONE)) => {
+                    if let Ok(new) = ptrace::geteventmsg(pid.as_raw()) {
+                        if add_thread(session, new as i32, false) {
+                            resume_thread(session, new as i32, 0);
+                        }
+                    }
+                    resume_thread(session, pid.as_raw(), 0);
+                },
+                // the first stop of a new thread can come before the clone event
+                Ok(WaitStatus::Stopped(pid, Signal::SIGSTOP)) if !session.proc.threads.contains(pid.as_raw()) => {
+                    add_thread(session, pid.as_raw(), true);
+                    resume_thread(session, pid.as_raw(), 0);
+                },
+                Ok(WaitStatus::Exited(pid, _)) if pid.as_raw() != session.proc.target => {
+                    remove_thread(session, pid.as_raw());
+                },
+                // watched memory is accessed but not in the way the watchpoint stops at
+                Ok(WaitStatus::Stopped(pid, Signal::SIGTRAP)) if check_watch_trap(session, pid.as_raw()) == Some(false) => {
+                    resume_thread(session, pid.as_raw(), 0);
+                },
+                Ok(WaitStatus::Stopped(pid, Signal::SIGTRAP)) if software_watch_step(session, pid.as_raw()) => {
+                    resume_thread(session, pid.as_raw(), 0);
+                },
+                Ok(WaitStatus::Stopped(pid, signum)) if signum != Signal::SIGTRAP => {
+                    let disposition = session.signals.get(signum);
+                    if disposition.stop {
+                        break status;
+                    }
+                    if disposition.print {
+                        println!("\nProgram received signal {}, {}", signum, get_strsig(signum as i32));
+                    }
+                    let signal = if disposition.pass { signum as i32 } else { 0 };
+                    resume_thread(session, pid.as_raw(), signal);
+                },
+                _ => break status,
+            }
+        };
+
+        // all-stop, the thread which stopped becomes the current one
+        if let Ok(WaitStatus::Stopped(pid, _)) | Ok(WaitStatus::PtraceSyscall(pid)) = status {
+            stop_threads(session, pid.as_raw());
+            if matches!(status, Ok(WaitStatus::Stopped(_, Signal::SIGTRAP))) && skip_breakpoint(session, pid.as_raw()) {
+                continue;
+            }
+            switch_thread(session, pid.as_raw());
+        }
+        report_status(session, status);
+        session.breakpoints.delete_temporaries(session.get_target());
+        return MainLoopAction::None;
+    }
+}
+
+// Continues with @signal instead of the pending one, None to suppress it
+pub fn signal(session: &mut session::Session, signal: Option<Signal>) -> MainLoopAction {
+    if let Some(thread) = session.proc.threads.current_mut() {
+        thread.pending_signal = signal;
+    }
+    cont(session)
+}
+
+pub fn handle(session: &mut session::Session, sig: Signal, keywords: &[String]) -> MainLoopAction {
+    for keyword in keywords {
+        if session.signals.handle(sig, keyword).is_err() {
+            println!("{}'{}'", "Invalid keyword: ".red(), keyword);
+            return MainLoopAction::None;
+        }
+    }
+    info::signals(session, Some(sig))
+}
+
+pub fn stepi(session: &mut session::Session, count: usize) -> MainLoopAction {
+    for _ in 0..count {
+        if let Some(status) = step_instruction(session, true) {
+            report_status(session, Ok(status));
+            return MainLoopAction::None;
+        }
+    }
+    report_stop(session);
     MainLoopAction::None
 }
 
-pub fn run(session: &mut session::Session) -> MainLoopAction {
-    match process::spawn_file(session.path.as_ref().unwrap()) {
-        -1 => MainLoopAction::None,
-        child_pid => {
-            // Wait parent until it's ready
-            nix::sys::wait::wait().unwrap();
-            session.set_target(child_pid).unwrap_or(-1);
-            session.set_type(session::Type::Spawn);
-
-            // Continuing execution of the child
-            super::command::cont(session.mut_proc())
+// Same as stepi but runs until the call returns if the instruction is a call
+pub fn nexti(session: &mut session::Session, count: usize) -> MainLoopAction {
+    for _ in 0..count {
+        let (pc, sp) = match (session.proc.getreg("pc"), session.proc.getreg("sp")) {
+            (Ok(pc), Ok(sp)) => (pc, sp),
+            _ => return MainLoopAction::None,
+        };
+        let return_addr = match call_length_at(session, pc) {
+            Some(len) => pc + len as u64,
+            None => {
+                if let Some(status) = step_instruction(session, true) {
+                    report_status(session, Ok(status));
+                    return MainLoopAction::None;
+                }
+                continue;
+            },
+        };
+
+        loop {
+            // enabled user breakpoint at the return address reports the stop by itself
+            if session.breakpoints.add_temporary(return_addr).is_err() {
+                cont(session);
+                return MainLoopAction::None;
+            }
+            cont(session);
+            if session.invalid() {
+                return MainLoopAction::None;
+            }
+
+            // returned to the call site, unless it's a recursive call hitting the same place
+            match (session.proc.getreg("pc"), session.proc.getreg("sp")) {
+                (Ok(cur_pc), Ok(cur_sp)) if cur_pc == return_addr && cur_sp < sp => continue,
+                (Ok(cur_pc), _) if cur_pc == return_addr => break,
+                _ => return MainLoopAction::None,
+            }
+        }
+    }
+    report_stop(session);
+    MainLoopAction::None
+}
+
+// Spawns the program, it's stopped at the first instruction of the loader on success
+fn spawn(session: &mut session::Session) -> Result<(), ()> {
+    let child_pid = match process::spawn_file(session.path.as_ref().unwrap(), &session.launch) {
+        -1 => return Err(()),
+        child_pid => child_pid,
+    };
+
+    // the child stops at exec, otherwise it couldn't run the program and is gone
+    match nix::sys::wait::waitpid(Pid::from_raw(child_pid), None) {
+        Ok(WaitStatus::Stopped(_, Signal::SIGTRAP)) => (),
+        Ok(WaitStatus::Exited(_, code)) => {
+            println!("{}{}", "The program couldn't be started, exited with status: ".red(), code);
+            return Err(());
+        },
+        status => {
+            println!("{}{:?}", "The program couldn't be started: ".red(), status);
+            return Err(());
         },
     }
+    session.set_target(child_pid).unwrap_or(-1);
+    session.set_type(session::Type::Spawn);
+    Ok(())
+}
+
+pub fn run(session: &mut session::Session) -> MainLoopAction {
+    if spawn(session).is_ok() {
+        // Continuing execution of the child
+        super::command::cont(session);
+    }
+    MainLoopAction::None
+}
+
+// Stops at the very first instruction, in the dynamic loader for dynamically linked program
+pub fn starti(session: &mut session::Session) -> MainLoopAction {
+    if spawn(session).is_ok() {
+        report_stop(session);
+    }
+    MainLoopAction::None
+}
+
+// Stops at 'main', or at the entry point if there is no such symbol
+pub fn start(session: &mut session::Session) -> MainLoopAction {
+    if spawn(session).is_err() {
+        return MainLoopAction::None;
+    }
+    let addr = match session.symbols.lookup("main").or_else(|| session.entry_point()) {
+        Some(addr) => addr,
+        None => return cont(session),
+    };
+
+    // enabled user breakpoint at the same address reports the stop by itself
+    if session.breakpoints.add_temporary(addr).is_err() {
+        return cont(session);
+    }
+    cont(session);
+    if session.valid() && session.breakpoints.temporary_hit {
+        println!("\nTemporary breakpoint, at {}", session.format_addr(addr));
+        report_stop(session);
+    }
+    MainLoopAction::None
 }
 
-pub fn vmmap(proc: &mut process::Proc) -> MainLoopAction {
-    proc.update();
-    proc.dump_maps();
+// Memory regions colored by what they hold like pwndbg, only the ones matching @filter if given:
+// an address expression, permissions such as 'rwx' or a part of the path
+pub fn vmmap(session: &mut session::Session, filter: Option<&str>) -> MainLoopAction {
+    if session.valid() {
+        session.proc.update_maps();
+    }
+    let addr = match filter.filter(|f| f.starts_with(|c: char| c.is_ascii_digit() || "$(*".contains(c))) {
+        Some(expr) => match session.eval(expr) {
+            Ok(addr) => Some(addr),
+            Err(_) => return MainLoopAction::None,
+        },
+        None => None,
+    };
+    let regions: Vec<&process::maps::MemoryRegion> = session.target().maps().iter()
+        .filter(|region| match (addr, filter) {
+            (Some(addr), _) => region.contains(addr),
+            (None, Some(pattern)) => region.matches(pattern),
+            (None, None) => true,
+        })
+        .collect();
+    if regions.is_empty() {
+        println!("{}'{}'", "No memory region matches ".red(), filter.unwrap_or_default());
+        return MainLoopAction::None;
+    }
+
+    println!("LEGEND: {} | {} | {} | {} | {} | RODATA",
+             "STACK".yellow(), "HEAP".blue(), "CODE".red(), "DATA".magenta(), "RWX".underline());
+    println!("{:<18} {:<18} {:<4} {:>10} {:>10} Path", "Start", "End", "Perm", "Size", "Offset");
+    for region in regions {
+        let line = format!("{:#018x} {:#018x} {:<4} {:>#10x} {:>#10x} {}",
+                           region.start, region.end, region.perms(), region.size(), region.offset, region.path);
+        let colored = match region.path.as_str() {
+            path if path.starts_with("[stack") => line.yellow(),
+            "[heap]" => line.blue(),
+            _ if region.exec => line.red(),
+            _ if region.write => line.magenta(),
+            _ => line.normal(),
+        };
+        match region.write && region.exec {
+            true => println!("{}", colored.underline()),
+            false => println!("{}", colored),
+        }
+    }
     MainLoopAction::None
 }
 
-pub fn kill(proc: &mut process::Proc) -> MainLoopAction {
-    if ptrace::sigkill(proc.target).is_ok() {
+pub fn kill(session: &mut session::Session) -> MainLoopAction {
+    if session.proc.kill().is_ok() {
         println!("Process killed successfully");
-        proc.release();
+        session.release();
     }
     MainLoopAction::None
 }
 
-pub fn quit(proc: &mut process::Proc) -> MainLoopAction {
-    if proc.valid() {
-        println!("terminating the process({})...", proc.target);
-        if ptrace::sigkill(proc.target).is_ok() {
+pub fn quit(session: &mut session::Session) -> MainLoopAction {
+    for inferior in session.inferiors.drain(..) {
+        inferior.proc.kill().unwrap_or(());
+    }
+    if session.valid() {
+        println!("terminating the process({})...", session.proc.target);
+        if session.proc.kill().is_ok() {
             println!("Process killed successfully");
-            proc.release();
+            session.release();
         }
     }
     MainLoopAction::Break
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Child stopped by @signal it raised under ptrace
+    fn stopped_child(signal: Signal) -> Pid {
+        match unsafe { nix::unistd::fork() }.unwrap() {
+            nix::unistd::ForkResult::Child => {
+                let _ = nix::sys::ptrace::traceme();
+                let _ = nix::sys::signal::raise(signal);
+                unsafe { libc::_exit(0) }
+            },
+            nix::unistd::ForkResult::Parent { child } => {
+                let status = nix::sys::wait::waitpid(child, None);
+                assert!(matches!(status, Ok(WaitStatus::Stopped(_, sig)) if sig == signal));
+                child
+            },
+        }
+    }
+
+    fn kill_child(child: Pid) {
+        let _ = nix::sys::signal::kill(child, Signal::SIGKILL);
+        let _ = nix::sys::wait::waitpid(child, None);
+    }
+
+    #[test]
+    fn stop_sets_siginfo() {
+        let mut session = session::Session::new();
+        let child = stopped_child(Signal::SIGTRAP);
+        set_siginfo(&mut session, child.as_raw());
+        kill_child(child);
+        assert_eq!(session.eval("$_siginfo"), Ok(libc::SIGTRAP as u64));
+        assert_eq!(session.eval("$_siginfo.si_signo"), Ok(libc::SIGTRAP as u64));
+        assert_eq!(session.eval("$_siginfo.si_code == -6"), Ok(1));
+        assert!(session.variable("_siginfo.si_addr").is_some());
+
+        // no address for the signals other than the faults
+        let child = stopped_child(Signal::SIGUSR1);
+        set_siginfo(&mut session, child.as_raw());
+        kill_child(child);
+        assert_eq!(session.eval("$_siginfo"), Ok(libc::SIGUSR1 as u64));
+        assert!(session.variable("_siginfo.si_addr").is_none());
+    }
+}
@@ -0,0 +1,47 @@
+use super::*;
+
+// Output format of 'print', '/<fmt>'
+pub enum Format {
+    // decimal, hex, octal and binary at once
+    All,
+    Hex,
+    Decimal,
+    Unsigned,
+    Octal,
+    Binary,
+    Char,
+    Address,
+}
+
+impl Format {
+    // Parses the part after 'print', e.g) "/x", empty string gives every radix
+    pub fn parse(spec: &str) -> Result<Self, ()> {
+        match spec {
+            "" => Ok(Format::All),
+            "/x" => Ok(Format::Hex),
+            "/d" => Ok(Format::Decimal),
+            "/u" => Ok(Format::Unsigned),
+            "/o" => Ok(Format::Octal),
+            "/t" => Ok(Format::Binary),
+            "/c" => Ok(Format::Char),
+            "/a" => Ok(Format::Address),
+            _ => Err(()),
+        }
+    }
+}
+
+pub fn print(sess: &mut session::Session, format: Format, value: u64) -> MainLoopAction {
+    let text = match format {
+        Format::All => format!("{} ({:#x}, {:#o}, {:#b})", value as i64, value, value, value),
+        Format::Hex => format!("{:#x}", value),
+        Format::Decimal => format!("{}", value as i64),
+        Format::Unsigned => format!("{}", value),
+        Format::Octal => format!("{:#o}", value),
+        Format::Binary => format!("{:#b}", value),
+        // the lowest byte as a character, e.g) 65 'A'
+        Format::Char => format!("{} '{}'", value as u8 as i8, (value as u8 as char).escape_default()),
+        Format::Address => sess.format_addr(value),
+    };
+    println!("{}", text);
+    MainLoopAction::None
+}
@@ -0,0 +1,103 @@
+use super::*;
+use crate::process::procfs;
+use crate::scan::{Expected, Filter, Scan, Value, ValueType};
+
+// Candidates listed after a scan, the rest are only counted
+const MAX_SHOWN: usize = 20;
+
+fn format_value(value: &Value) -> String {
+    match value {
+        Value::Int(value) => value.to_string(),
+        Value::Float(value) => track::format_float(*value),
+    }
+}
+
+// Integers are expressions, floats are literals matched to the digits given,
+// e.g) '1.5' matches 1.45 to 1.55
+pub fn parse_expected(sess: &session::Session, ty: ValueType, text: &str) -> Result<Expected, ()> {
+    if !ty.is_float() {
+        let value = sess.eval(text)?;
+        let value = ty.value_of(value).ok_or_else(|| {
+            println!("{}'{}' for {}", "Value out of range: ".red(), text, ty.name());
+        })?;
+        return Ok(Expected { value, tolerance: 0.0 });
+    }
+    let value = text.parse::<f64>().map_err(|_| {
+        println!("{}'{}'", "Invalid floating point number: ".red(), text);
+    })?;
+    let digits = text.split_once('.')
+        .map(|(_, fraction)| fraction.chars().take_while(char::is_ascii_digit).count())
+        .unwrap_or(0);
+    Ok(Expected { value: Value::Float(value), tolerance: 0.5 * 10f64.powi(-(digits as i32)) })
+}
+
+// Prints the number of the candidates, and them if not too many
+fn show(sess: &session::Session, scan: &Scan) {
+    match scan.len() {
+        0 => println!("No matches, start over with 'scan TYPE VALUE'"),
+        1 => println!("1 match"),
+        n => println!("{} matches", n),
+    }
+    if scan.len() <= MAX_SHOWN {
+        for (addr, value) in scan.iter() {
+            println!("  {} = {}", sess.format_addr(addr), format_value(&value));
+        }
+    }
+}
+
+// Scans the writable memory for @expected as @ty, replacing the last scan
+pub fn start(sess: &mut session::Session, ty: ValueType, expected: &Expected) -> MainLoopAction {
+    sess.proc.update_maps();
+    let mem = match procfs::open_proc_mem(sess.proc.target) {
+        Ok(mem) => mem,
+        Err(_) => return MainLoopAction::None,
+    };
+    let regions: Vec<_> = sess.proc.maps().iter()
+        .filter(|region| region.read && region.write)
+        .collect();
+    let scan = Scan::first(&mem, &sess.breakpoints, &regions, ty, expected);
+    show(sess, &scan);
+    sess.scan = Some(scan);
+    MainLoopAction::None
+}
+
+// Narrows down the candidates of the last scan with @filter
+pub fn next(sess: &mut session::Session, filter: &Filter) -> MainLoopAction {
+    let mut scan = match sess.scan.take() {
+        Some(scan) => scan,
+        None => {
+            println!("{}", "No scan in progress, start with 'scan TYPE VALUE'".red());
+            return MainLoopAction::None;
+        },
+    };
+    if let Ok(mem) = procfs::open_proc_mem(sess.proc.target) {
+        scan.next(&mem, &sess.breakpoints, filter);
+        show(sess, &scan);
+    }
+    sess.scan = Some(scan);
+    MainLoopAction::None
+}
+
+// Candidates with the value at the last scan
+pub fn list(sess: &session::Session) -> MainLoopAction {
+    let scan = match &sess.scan {
+        Some(scan) => scan,
+        None => {
+            println!("No scan in progress");
+            return MainLoopAction::None;
+        },
+    };
+    println!("Scan of {} values, round {}: {} candidates", scan.ty.name(), scan.rounds, scan.len());
+    for (addr, value) in scan.iter().take(MAX_SHOWN) {
+        println!("  {} = {}", sess.format_addr(addr), format_value(&value));
+    }
+    if scan.len() > MAX_SHOWN {
+        println!("  ... and {} more", scan.len() - MAX_SHOWN);
+    }
+    MainLoopAction::None
+}
+
+pub fn reset(sess: &mut session::Session) -> MainLoopAction {
+    sess.scan = None;
+    MainLoopAction::None
+}
@@ -0,0 +1,271 @@
+use std::os::unix::fs::FileExt;
+
+use super::*;
+use crate::cli;
+use crate::process::{procfs, maps::MemoryRegion};
+
+// Bytes read from '/proc/{PID}/mem' at once
+const CHUNK_SIZE: usize = 1 << 20;
+
+// Matches printed, the rest are only counted
+const MAX_RESULTS: usize = 256;
+
+// What to search for and where, 'search [-b | -s | -w | -1 | -2 | -4 | -8] [-r REGION] [-p PERMS] PATTERN'
+pub struct Query {
+    // None for wildcard bytes
+    pattern: Vec<Option<u8>>,
+
+    // mapping name or address expression, every readable region if None
+    region: Option<String>,
+
+    // permissions the regions have at least, e.g) 'rw'
+    perms: Option<String>,
+
+    // shown after the matches of string patterns
+    text: Option<String>,
+}
+
+// '488b??24' or '48 8b ?? 24', '??' matches any byte
+fn parse_bytes(words: &[String]) -> Result<Vec<Option<u8>>, ()> {
+    let hex: String = words.concat();
+    if hex.is_empty() || !hex.len().is_multiple_of(2) {
+        return Err(());
+    }
+    (0..hex.len()).step_by(2)
+        .map(|i| match &hex[i..i + 2] {
+            "??" => Ok(None),
+            byte => u8::from_str_radix(byte, 16).map(Some).map_err(|_| ()),
+        })
+        .collect()
+}
+
+impl Query {
+    // @args are the words after 'search' and @line is the same as typed,
+    // the pattern is the rest after the options
+    pub fn parse(sess: &session::Session, args: &[String], line: &str) -> Result<Self, ()> {
+        let mut kind = "-s".to_string();
+        let mut region = None;
+        let mut perms = None;
+        let mut i = 0;
+        while i < args.len() {
+            match args[i].as_str() {
+                "-b" | "-s" | "-w" | "-1" | "-2" | "-4" | "-8" => kind = args[i].clone(),
+                "-r" => {
+                    region = Some(args.get(i + 1).ok_or(())?.clone());
+                    i += 1;
+                },
+                "-p" => {
+                    perms = Some(args.get(i + 1).ok_or(())?.clone());
+                    i += 1;
+                },
+                _ => break,
+            }
+            i += 1;
+        }
+        let words = &args[i..];
+        if words.is_empty() {
+            return Err(());
+        }
+
+        // taken as typed, quotes keep the leading and trailing spaces of the strings
+        let raw = cli::rest_of_line(line, i);
+        let string = match raw.strip_prefix('"').and_then(|s| s.strip_suffix('"')) {
+            Some(quoted) => quoted.to_string(),
+            None => raw.to_string(),
+        };
+        let (pattern, text) = match kind.as_str() {
+            "-b" => (parse_bytes(words)?, None),
+            "-s" => (string.bytes().map(Some).collect(), Some(string)),
+            "-w" => (string.encode_utf16().flat_map(u16::to_le_bytes).map(Some).collect(), Some(string)),
+            width => {
+                let size = width[1..].parse::<usize>().map_err(|_| ())?;
+                let value = sess.eval(&string)?;
+
+                // either signed or unsigned, the bits cut off are all 0 or all 1 with the sign
+                let shift = 64 - size as u32 * 8;
+                let fits = size >= 8 || value >> (64 - shift) == 0 || ((value << shift) as i64 >> shift) as u64 == value;
+                if !fits {
+                    println!("{}'{}' for {} bytes", "Value out of range: ".red(), string, size);
+                    return Err(());
+                }
+                (value.to_le_bytes()[..size].iter().copied().map(Some).collect(), None)
+            },
+        };
+        if pattern.is_empty() {
+            return Err(());
+        }
+        Ok(Query { pattern, region, perms, text })
+    }
+}
+
+// Offsets @pattern matches at in @buf
+fn find_all(buf: &[u8], pattern: &[Option<u8>]) -> Vec<usize> {
+    if buf.len() < pattern.len() {
+        return Vec::new();
+    }
+    (0..=buf.len() - pattern.len())
+        .filter(|i| {
+            pattern.iter()
+                .zip(&buf[*i..])
+                .all(|(p, b)| p.is_none_or(|p| p == *b))
+        })
+        .collect()
+}
+
+// Regions to search in, the ones containing the address if @query.region evaluates to one
+fn regions<'a>(sess: &'a session::Session, query: &Query) -> Result<Vec<&'a MemoryRegion>, ()> {
+    let addr = match &query.region {
+        Some(expr) if expr.starts_with(|c: char| c.is_ascii_digit() || "$(*".contains(c)) => Some(sess.eval(expr)?),
+        _ => None,
+    };
+    Ok(sess.proc.maps().iter()
+        .filter(|region| region.read)
+        .filter(|region| match (addr, &query.region) {
+            (Some(addr), _) => region.contains(addr),
+            (None, Some(name)) => region.path.contains(name.as_str()),
+            (None, None) => true,
+        })
+        .filter(|region| query.perms.as_ref().is_none_or(|perms| region.matches(perms)))
+        .collect())
+}
+
+// Searches the memory through '/proc/{PID}/mem', reading in chunks that overlap
+// by the pattern length so that no match is cut in between
+pub fn search(sess: &mut session::Session, query: &Query) -> MainLoopAction {
+    sess.proc.update_maps();
+    let regions = match regions(sess, query) {
+        Ok(regions) => regions,
+        Err(_) => return MainLoopAction::None,
+    };
+    if regions.is_empty() {
+        println!("{}", "No readable memory region to search".red());
+        return MainLoopAction::None;
+    }
+    let mem = match procfs::open_proc_mem(sess.proc.target) {
+        Ok(mem) => mem,
+        Err(_) => return MainLoopAction::None,
+    };
+
+    let len = query.pattern.len();
+    let mut found = 0;
+    for region in regions {
+        let mut addr = region.start;
+        while addr < region.end {
+            let size = (region.end - addr).min(CHUNK_SIZE as u64) as usize;
+            let mut buf = vec![0u8; size];
+
+            // [vvar] and the like can't be read, skipped
+            let read = mem.read_at(&mut buf, addr).unwrap_or(0);
+            if read == 0 {
+                break;
+            }
+            buf.truncate(read);
+            sess.breakpoints.unpatch(addr, &mut buf);
+
+            for offset in find_all(&buf, &query.pattern) {
+                let hit = addr + offset as u64;
+                found += 1;
+                if found <= MAX_RESULTS {
+                    let text = query.text.as_ref().map(|s| format!(" \"{}\"", s.escape_default())).unwrap_or_default();
+                    println!("{} {} {}{}", sess.format_addr(hit), region.perms(), region.name(), text);
+                }
+            }
+
+            // done with the region, or go back to catch a match across the chunks
+            if addr + read as u64 >= region.end || read < len {
+                break;
+            }
+            addr += (read - (len - 1)) as u64;
+        }
+    }
+
+    match found {
+        0 => println!("Pattern not found"),
+        n if n > MAX_RESULTS => println!("{} matches, the first {} are shown", n, MAX_RESULTS),
+        1 => println!("1 match"),
+        n => println!("{} matches", n),
+    }
+    MainLoopAction::None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    fn query(line: &str) -> Result<Query, ()> {
+        Query::parse(&session::Session::new(), &words(line), line)
+    }
+
+    #[test]
+    fn bytes_with_wildcards() {
+        assert_eq!(parse_bytes(&words("488b??24")), Ok(vec![Some(0x48), Some(0x8b), None, Some(0x24)]));
+        assert_eq!(parse_bytes(&words("48 8b ?? 24")), Ok(vec![Some(0x48), Some(0x8b), None, Some(0x24)]));
+        assert_eq!(parse_bytes(&words("FF")), Ok(vec![Some(0xff)]));
+    }
+
+    #[test]
+    fn invalid_bytes() {
+        assert!(parse_bytes(&[]).is_err());
+        assert!(parse_bytes(&words("488")).is_err());
+        assert!(parse_bytes(&words("4g")).is_err());
+        assert!(parse_bytes(&words("?1")).is_err());
+    }
+
+    #[test]
+    fn find_all_matches() {
+        let buf = b"abcabcab";
+        let pattern: Vec<Option<u8>> = b"abc".iter().copied().map(Some).collect();
+        assert_eq!(find_all(buf, &pattern), vec![0, 3]);
+        assert_eq!(find_all(buf, &[Some(b'b'), None]), vec![1, 4]);
+        assert_eq!(find_all(buf, &[None, Some(b'a')]), vec![2, 5]);
+        assert_eq!(find_all(b"aaa", &[Some(b'a'), Some(b'a')]), vec![0, 1]);
+        assert!(find_all(b"ab", &pattern).is_empty());
+        assert!(find_all(b"", &pattern).is_empty());
+    }
+
+    #[test]
+    fn strings_keep_their_spaces() {
+        let query = query("-r heap \"  two  spaces \"").unwrap();
+        assert_eq!(query.text.as_deref(), Some("  two  spaces "));
+        assert_eq!(query.pattern.len(), 14);
+        assert_eq!(query.region.as_deref(), Some("heap"));
+
+        let query = self::query("hello   world").unwrap();
+        assert_eq!(query.text.as_deref(), Some("hello   world"));
+    }
+
+    #[test]
+    fn pattern_kinds() {
+        let wide = query("-w ab").unwrap();
+        assert_eq!(wide.pattern, vec![Some(b'a'), Some(0), Some(b'b'), Some(0)]);
+        let value = query("-p rw -4 0x1234").unwrap();
+        assert_eq!(value.pattern, vec![Some(0x34), Some(0x12), Some(0), Some(0)]);
+        assert_eq!(value.perms.as_deref(), Some("rw"));
+        assert!(value.text.is_none());
+        let bytes = query("-b 90 ?? c3").unwrap();
+        assert_eq!(bytes.pattern, vec![Some(0x90), None, Some(0xc3)]);
+    }
+
+    #[test]
+    fn values_fit_the_width() {
+        assert_eq!(query("-1 255").unwrap().pattern, vec![Some(0xff)]);
+        assert_eq!(query("-1 -(1)").unwrap().pattern, vec![Some(0xff)]);
+        assert_eq!(query("-2 -32768").unwrap().pattern, vec![Some(0), Some(0x80)]);
+        assert_eq!(query("-8 ~0").unwrap().pattern, vec![Some(0xff); 8]);
+        assert!(query("-1 300").is_err());
+        assert!(query("-1 -129").is_err());
+        assert!(query("-2 0x12345").is_err());
+        assert!(query("-4 1 << 32").is_err());
+    }
+
+    #[test]
+    fn missing_pattern() {
+        assert!(query("-s").is_err());
+        assert!(query("-r").is_err());
+        assert!(query("\"\"").is_err());
+    }
+}
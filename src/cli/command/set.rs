@@ -0,0 +1,98 @@
+use super::*;
+use std::path::PathBuf;
+
+pub fn mem(sess: &mut session::Session, addr: u64, value: u64, size: usize) -> MainLoopAction {
+    // the value must fit in @size bytes, either as unsigned or as signed
+    if size < 8 {
+        let bits = size * 8;
+        let fits_unsigned = value >> bits == 0;
+        let fits_signed = ((value as i64) >> (bits - 1)) == 0 || ((value as i64) >> (bits - 1)) == -1;
+        if !fits_unsigned && !fits_signed {
+            println!("{}{:#x}", "Value too large for the size: ".red(), value);
+            return MainLoopAction::None;
+        }
+    }
+
+    if sess.proc.write_memory(addr, &value.to_le_bytes()[..size]).is_err() {
+        println!("{}{:#x}", "Cannot access memory at address ".red(), addr);
+    }
+    MainLoopAction::None
+}
+
+pub fn reg(sess: &mut session::Session, name: &str, value: u64) -> MainLoopAction {
+    if Regs::find(name).is_none() {
+        println!("{}'{}'", "Invalid register: ".red(), name);
+        return MainLoopAction::None;
+    }
+    match sess.proc.setreg(name, value) {
+        Ok(_) => println!("{} = {:#x}", name, value),
+        Err(_) => println!("{}'{}'", "Failed to write register: ".red(), name),
+    }
+    MainLoopAction::None
+}
+
+// Convenience variable '$@name', a register is written if @name is one
+pub fn variable(sess: &mut session::Session, name: &str, value: u64) -> MainLoopAction {
+    if Regs::find(name).is_some() && sess.invalid() {
+        println!("{}", "No process has been attached".red());
+        return MainLoopAction::None;
+    }
+    if Regs::find(name).is_some() {
+        return reg(sess, name, value);
+    }
+    match name {
+        "base" | "libc" => println!("{}${}", "Cannot set the load address: ".red(), name),
+        _ => {
+            sess.variables.insert(name.to_string(), value);
+        },
+    }
+    MainLoopAction::None
+}
+
+// Arguments and redirections of the next 'run'
+pub fn args(sess: &mut session::Session, line: &str) -> MainLoopAction {
+    if sess.launch.parse_args(line).is_err() {
+        println!("{}", "Invalid arguments".red());
+    }
+    MainLoopAction::None
+}
+
+pub fn env(sess: &mut session::Session, name: &str, value: &str) -> MainLoopAction {
+    sess.launch.env.insert(name.to_string(), Some(value.to_string()));
+    MainLoopAction::None
+}
+
+// Removes @name from the environment of the program, or every variable if None
+pub fn unset_env(sess: &mut session::Session, name: Option<&str>) -> MainLoopAction {
+    match name {
+        Some(name) => {
+            sess.launch.env.insert(name.to_string(), None);
+        },
+        None => {
+            sess.launch.env.clear();
+            sess.launch.clear_env = true;
+        },
+    }
+    MainLoopAction::None
+}
+
+// Working directory of the program, rsdb's one if None
+pub fn cwd(sess: &mut session::Session, dir: Option<&str>) -> MainLoopAction {
+    sess.launch.cwd = dir.map(PathBuf::from);
+    MainLoopAction::None
+}
+
+pub fn disable_randomization(sess: &mut session::Session, disable: bool) -> MainLoopAction {
+    sess.launch.disable_randomization = disable;
+    MainLoopAction::None
+}
+
+pub fn follow_fork_mode(sess: &mut session::Session, mode: session::FollowForkMode) -> MainLoopAction {
+    sess.follow_fork_mode = mode;
+    MainLoopAction::None
+}
+
+pub fn detach_on_fork(sess: &mut session::Session, detach: bool) -> MainLoopAction {
+    sess.detach_on_fork = detach;
+    MainLoopAction::None
+}
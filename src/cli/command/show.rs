@@ -0,0 +1,68 @@
+use super::*;
+
+pub fn args(sess: &session::Session) -> MainLoopAction {
+    println!("Arguments of the program: '{}'", sess.launch.args_string());
+    MainLoopAction::None
+}
+
+// Environment the program will be started with
+pub fn env(sess: &session::Session) -> MainLoopAction {
+    let mut vars: Vec<(String, String)> = match sess.launch.clear_env {
+        true => Vec::new(),
+        false => std::env::vars().collect(),
+    };
+    for (name, value) in &sess.launch.env {
+        vars.retain(|(var, _)| var != name);
+        if let Some(value) = value {
+            vars.push((name.clone(), value.clone()));
+        }
+    }
+    for (name, value) in vars {
+        println!("{}={}", name, value);
+    }
+    MainLoopAction::None
+}
+
+pub fn cwd(sess: &session::Session) -> MainLoopAction {
+    match &sess.launch.cwd {
+        Some(cwd) => println!("Working directory of the program: '{}'", cwd.display()),
+        None => println!("Working directory of the program is the one of rsdb"),
+    }
+    MainLoopAction::None
+}
+
+pub fn disable_randomization(sess: &session::Session) -> MainLoopAction {
+    let state = if sess.launch.disable_randomization { "on" } else { "off" };
+    println!("Disabling randomization of the address space is {}", state);
+    MainLoopAction::None
+}
+
+pub fn follow_fork_mode(sess: &session::Session) -> MainLoopAction {
+    let mode = match sess.follow_fork_mode {
+        session::FollowForkMode::Parent => "parent",
+        session::FollowForkMode::Child => "child",
+    };
+    println!("Debugger response to a program call of fork or vfork is \"{}\"", mode);
+    MainLoopAction::None
+}
+
+pub fn detach_on_fork(sess: &session::Session) -> MainLoopAction {
+    let state = if sess.detach_on_fork { "on" } else { "off" };
+    println!("Whether rsdb will detach the child of a fork is {}", state);
+    MainLoopAction::None
+}
+
+// Convenience variables with the load addresses of the process
+pub fn convenience(sess: &session::Session) -> MainLoopAction {
+    let mut list: Vec<(String, u64)> = ["base", "libc"].iter()
+        .filter_map(|name| sess.variable(name).map(|value| (name.to_string(), value)))
+        .collect();
+    list.extend(sess.variables.iter().map(|(name, value)| (name.clone(), *value)));
+    if list.is_empty() {
+        println!("No convenience variables, set one with 'set $name = EXPR'");
+    }
+    for (name, value) in list {
+        println!("${} = {} ({:#x})", name, value as i64, value);
+    }
+    MainLoopAction::None
+}
@@ -0,0 +1,123 @@
+use super::*;
+use crate::syscall::{self as sys, Call};
+
+// Prefix of the trace lines telling which thread made the call if there are many
+fn thread_prefix(session: &session::Session, tid: i32) -> String {
+    match session.proc.threads.iter().count() {
+        1 => String::new(),
+        _ => format!("[{}] ", tid),
+    }
+}
+
+// Handles syscall-entry or syscall-exit stop of @tid, prints the call with 'trace syscalls on'.
+// Returns true if the call is caught and the stop has to be reported to the user.
+pub fn stop(session: &mut session::Session, tid: i32) -> bool {
+    let regs = match Regs::read(tid) {
+        Ok(regs) => regs,
+        Err(_) => return false,
+    };
+    let entering = session.proc.threads.get(tid)
+        .and_then(|thread| thread.syscall.as_ref())
+        .is_none_or(|call| call.ret.is_some());
+
+    let nr = match entering {
+        true => {
+            let nr = regs.get(Regs::SYSCALL_NR).unwrap_or(0);
+            let args: Vec<u64> = Regs::SYSCALL_ARGS.iter()
+                .map(|name| regs.get(name).unwrap_or(0))
+                .collect();
+            let text = sys::format_call(&session.proc, nr, &args);
+
+            // never returns, printed right away
+            if session.syscalls.trace && matches!(sys::name(nr), Some("exit") | Some("exit_group")) {
+                println!("{}{} = ?", thread_prefix(session, tid), text);
+            }
+            if let Some(thread) = session.proc.threads.get_mut(tid) {
+                thread.syscall = Some(Call { nr, text, ret: None });
+            }
+            nr
+        },
+        false => {
+            let ret = regs.get(Regs::SYSCALL_RET).unwrap_or(0);
+            let prefix = thread_prefix(session, tid);
+            let call = match session.proc.threads.get_mut(tid).and_then(|thread| thread.syscall.as_mut()) {
+                Some(call) => call,
+                None => return false,
+            };
+            call.ret = Some(ret);
+            if session.syscalls.trace {
+                println!("{}{} = {}", prefix, call.text, sys::format_return(call.nr, ret));
+            }
+            call.nr
+        },
+    };
+    session.syscalls.catches(nr)
+}
+
+// Tells the user which system call the current thread has stopped at
+pub fn report(session: &session::Session) {
+    let call = match session.proc.threads.get(session.proc.tid()).and_then(|thread| thread.syscall.as_ref()) {
+        Some(call) => call,
+        None => return,
+    };
+    let name = sys::display_name(call.nr);
+    match call.ret {
+        None => println!("\nCatchpoint (call to syscall {}), {}", name, call.text),
+        Some(ret) => {
+            println!("\nCatchpoint (returned from syscall {}), {} = {}",
+                     name, call.text, sys::format_return(call.nr, ret));
+        },
+    }
+}
+
+// Stops at the system calls @names, every system call if empty
+pub fn catch(sess: &mut session::Session, names: &[String]) -> MainLoopAction {
+    let mut numbers = Vec::new();
+    for name in names {
+        match sys::parse(name) {
+            Some(nr) => numbers.push(nr),
+            None => {
+                println!("{}'{}'", "Unknown syscall name: ".red(), name);
+                return MainLoopAction::None;
+            },
+        }
+    }
+    sess.syscalls.add(&numbers);
+    match numbers.is_empty() {
+        true => println!("Catchpoint (any syscall)"),
+        false => {
+            let list: Vec<String> = numbers.iter().map(|nr| format!("'{}' [{}]", sys::display_name(*nr), nr)).collect();
+            println!("Catchpoint (syscall {})", list.join(" "));
+        },
+    }
+    MainLoopAction::None
+}
+
+pub fn delete(sess: &mut session::Session) -> MainLoopAction {
+    match sess.syscalls.caught() {
+        Some(_) => {
+            sess.syscalls.clear();
+            println!("Deleted syscall catchpoint");
+        },
+        None => println!("{}", "No syscall catchpoint".red()),
+    }
+    MainLoopAction::None
+}
+
+pub fn trace(sess: &mut session::Session, on: bool) -> MainLoopAction {
+    sess.syscalls.trace = on;
+    println!("Tracing system calls is {}", if on { "on" } else { "off" });
+    MainLoopAction::None
+}
+
+// Line of 'info breakpoints' describing the catchpoint, None if there is no catchpoint
+pub fn describe(sess: &session::Session) -> Option<String> {
+    let list = sess.syscalls.caught()?;
+    match list.is_empty() {
+        true => Some("syscall \"<any syscall>\"".to_string()),
+        false => {
+            let names: Vec<String> = list.iter().map(|nr| sys::display_name(*nr)).collect();
+            Some(format!("syscalls \"{}\"", names.join(", ")))
+        },
+    }
+}
@@ -0,0 +1,32 @@
+use super::*;
+
+// 'Thread 1234 "name"'
+pub fn describe(sess: &session::Session, tid: i32) -> String {
+    match process::procfs::get_thread_name(sess.proc.target, tid) {
+        Some(name) => format!("Thread {} \"{}\"", tid, name),
+        None => format!("Thread {}", tid),
+    }
+}
+
+pub fn current(sess: &session::Session) -> MainLoopAction {
+    let tid = sess.proc.tid();
+    if let Some(thread) = sess.proc.threads.get(tid) {
+        println!("[Current thread is {} ({})]", thread.num, describe(sess, tid));
+    }
+    MainLoopAction::None
+}
+
+// Switches to the thread numbered @num, registers are shown for it from now on
+pub fn select(sess: &mut session::Session, num: usize) -> MainLoopAction {
+    let tid = match sess.proc.threads.find_by_num(num) {
+        Some(thread) => thread.tid,
+        None => {
+            println!("{}{}", "Invalid thread number: ".red(), num);
+            return MainLoopAction::None;
+        },
+    };
+    sess.proc.threads.select(tid).unwrap_or(());
+    println!("[Switching to thread {} ({})]", num, describe(sess, tid));
+    print_pc(sess);
+    MainLoopAction::None
+}
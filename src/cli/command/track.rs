@@ -0,0 +1,118 @@
+use super::*;
+use crate::track::Tracked;
+
+// Scientific notation for the values too large or small to read, e.g) 6.95e-310
+pub fn format_float(value: f64) -> String {
+    match value.abs() {
+        abs if abs == 0.0 || (1e-4..1e16).contains(&abs) || !abs.is_finite() => format!("{}", value),
+        _ => format!("{:e}", value),
+    }
+}
+
+// Formats the value as the tracked memory says, bytes in hex for the ones over 8 bytes
+fn format_value(tracked: &Tracked, value: &Option<Vec<u8>>) -> String {
+    let bytes = match value {
+        Some(bytes) => bytes,
+        None => return "<unreadable>".to_string(),
+    };
+    match (tracked.fmt, bytes.len()) {
+        ('f', 4) => format_float(f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64),
+        ('f', 8) => {
+            let mut raw = [0u8; 8];
+            raw.copy_from_slice(bytes);
+            format_float(f64::from_le_bytes(raw))
+        },
+        (fmt, size) if size <= 8 => memory::format_unit(fmt, size, bytes).trim_end().to_string(),
+        _ => {
+            let list: Vec<String> = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+            list.join(" ")
+        },
+    }
+}
+
+// e.g) 'counter' or '0x555555558040'
+fn describe(tracked: &Tracked) -> String {
+    tracked.location.clone().unwrap_or_else(|| format!("{:#x}", tracked.addr))
+}
+
+// Tracks @size bytes at @addr shown as @fmt, one of x, d, u, c and f
+pub fn add(sess: &mut session::Session, addr: u64, size: usize, fmt: char,
+           location: Option<String>) -> MainLoopAction {
+    let id = sess.tracked.add(&sess.proc, addr, size, fmt, location);
+    if let Some(tracked) = sess.tracked.get(id) {
+        match tracked.last() {
+            Some(value) => println!("Tracking {}: {} = {}", id, describe(tracked), format_value(tracked, value)),
+            None => println!("Tracking {}: {}", id, describe(tracked)),
+        }
+    }
+    MainLoopAction::None
+}
+
+// Stops tracking @id, or everything if None
+pub fn delete(sess: &mut session::Session, id: Option<usize>) -> MainLoopAction {
+    let ids = match id {
+        Some(id) => vec![id],
+        None => sess.tracked.ids(),
+    };
+    for id in ids {
+        if sess.tracked.delete(id).is_err() {
+            println!("{}{}", "No tracked memory number ".red(), id);
+        }
+    }
+    MainLoopAction::None
+}
+
+pub fn list(sess: &session::Session) -> MainLoopAction {
+    if sess.tracked.ids().is_empty() {
+        println!("No memory is tracked");
+        return MainLoopAction::None;
+    }
+    println!("{:<4} {:<18} {:<6} {:<4} {:<24} Value", "Num", "Address", "Size", "Fmt", "What");
+    for tracked in sess.tracked.iter() {
+        let value = tracked.last().map(|value| format_value(tracked, value)).unwrap_or_default();
+        println!("{:<4} {:<#18x} {:<6} {:<4} {:<24} {}",
+                 tracked.id, tracked.addr, tracked.size, tracked.fmt, describe(tracked), value);
+    }
+    MainLoopAction::None
+}
+
+// Value of @id at each stop kept, the changes highlighted
+pub fn history(sess: &session::Session, id: usize) -> MainLoopAction {
+    let tracked = match sess.tracked.get(id) {
+        Some(tracked) => tracked,
+        None => {
+            println!("{}{}", "No tracked memory number ".red(), id);
+            return MainLoopAction::None;
+        },
+    };
+    println!("History of {}: {} ({} bytes)", id, describe(tracked), tracked.size);
+    println!("{:<8} Value", "Stop");
+    let mut prev: Option<&Option<Vec<u8>>> = None;
+    for (stop, value) in tracked.history.iter() {
+        let text = format_value(tracked, value);
+        match prev {
+            Some(prev) if prev != value => println!("{:<8} {}", stop, text.yellow()),
+            _ => println!("{:<8} {}", stop, text),
+        }
+        prev = Some(value);
+    }
+    MainLoopAction::None
+}
+
+// Reads the tracked memory at a stop, the changed values are highlighted with the old ones
+pub fn report(sess: &mut session::Session) {
+    if sess.tracked.ids().is_empty() {
+        return;
+    }
+    let old: Vec<Option<Option<Vec<u8>>>> = sess.tracked.iter().map(|tracked| tracked.last().cloned()).collect();
+    let changed = sess.tracked.update(&sess.proc);
+    for (tracked, old) in sess.tracked.iter().zip(old) {
+        let line = format!("{}: {} = {}", tracked.id, describe(tracked), format_value(tracked, tracked.last().unwrap_or(&None)));
+        match old {
+            Some(old) if changed.contains(&tracked.id) => {
+                println!("{} {}", line.yellow(), format!("(was {})", format_value(tracked, &old)).yellow());
+            },
+            _ => println!("{}", line),
+        }
+    }
+}
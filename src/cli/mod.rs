@@ -2,7 +2,10 @@ use std::iter::*;
 use regex::Regex;
 use colored::*;
 
-use crate::{session, process::*, traits::*};
+use crate::{session, signal, process::*, traits::*};
+use crate::arch::WatchKind;
+use crate::expr::Expr;
+use crate::scan::{Filter, ValueType};
 use command::MainLoopAction;
 
 pub mod command;
@@ -21,26 +24,179 @@ macro_rules! continue_if {
     };
 }
 
+// Keeps 'symbol[+-offset]' expression to follow relocation, None for the other expressions
+fn symbol_location(session: &session::Session, expr: &str) -> Option<String> {
+    session.symbols.resolve(expr).map(|_| expr.to_string())
+}
+
+// Text after the first @count words of @line as typed, with the spaces and quotes
+fn rest_of_line(line: &str, count: usize) -> &str {
+    let mut rest = line.trim();
+    for _ in 0..count {
+        rest = rest[rest.find(char::is_whitespace).unwrap_or(rest.len())..].trim_start();
+    }
+    rest
+}
+
+// Evaluates @text as a count, negative or absurdly large ones are rejected
+fn eval_count(session: &session::Session, text: &str) -> Result<usize, ()> {
+    let count = session.eval(text)?;
+    if count > u32::MAX as u64 {
+        println!("{}{}", "Invalid count: ".red(), count as i64);
+        return Err(());
+    }
+    Ok(count as usize)
+}
+
+// Evaluates optional repeat count of stepi and nexti
+fn parse_count(session: &session::Session, commands: &[String]) -> Result<usize, ()> {
+    match commands.get(1) {
+        Some(count) => eval_count(session, count),
+        None => Ok(1),
+    }
+}
+
+// Parses '/SIZE' of watch commands, b, h, w, g or the number of bytes
+fn parse_watch_size(s: &str) -> Result<Option<usize>, ()> {
+    match s {
+        "" => Ok(None),
+        "/b" => Ok(Some(1)),
+        "/h" => Ok(Some(2)),
+        "/w" => Ok(Some(4)),
+        "/g" => Ok(Some(8)),
+        size => match size.strip_prefix('/').map(|n| n.parse::<usize>()) {
+            Some(Ok(len)) if len > 0 => Ok(Some(len)),
+            _ => Err(()),
+        },
+    }
+}
+
+// Evaluates optional breakpoint number argument of delete, enable and disable, the rest of the line
+fn parse_bp_id(session: &session::Session, text: &str) -> Result<Option<usize>, ()> {
+    match text {
+        "" => Ok(None),
+        id => eval_count(session, id).map(Some),
+    }
+}
+
 fn rsdb_help() -> MainLoopAction {
     println!("{}", "rsdb: Linux Debugger written in Rust".bright_yellow());
     println!("  help | ? => Print help");
     println!("  attach [PID | Package name] => attach to the prcess");
     println!("    e.g) {} or {}", "attach 31337".bright_yellow(), "attach com.test.package".bright_yellow());
     println!("  detach => detach from the process");
-    println!("  run | r => run the process only if --file argument given");
+    println!("  core [FILE] => inspect the ELF core file as the stopped process, drop the core file if omitted");
+    println!("    registers, memory, vmmap, bt and disas read from it, nothing can be changed");
+    println!("  run | r [ARGS...] => run the process only if --file argument given");
+    println!("    ARGS are kept for the next runs, quoted as in a shell, '<', '>', '>>', '2>' redirect stdio");
+    println!("    e.g) {}", "run -v input.bin < in.txt > out.txt".bright_yellow());
+    println!("  start [ARGS...] => same as run but stops at main, or at the entry point if no main");
+    println!("  starti [ARGS...] => same as run but stops at the first instruction");
+    println!("  continue | c => continue execution of the process");
+    println!("  signal [SIG | 0] => continue with the signal SIG, without any signal if 0");
+    println!("  handle [SIG] [KEYWORDS...] => set how to handle the signal SIG");
+    println!("    KEYWORDS: stop, nostop, print, noprint, pass, nopass");
+    println!("    e.g) {}", "handle SIGUSR1 nostop noprint pass".bright_yellow());
+    println!("  inferior [NUM] => switch to inferior NUM, show the current inferior if omitted");
+    println!("  thread [NUM] => switch to thread NUM, show the current thread if omitted");
+    println!("  stepi | si [N] => execute N instructions, 1 if N is omitted");
+    println!("  nexti | ni [N] => same as stepi but steps over call instructions");
+    println!("  break | b [ADDR | SYMBOL] [if EXPR] => set a breakpoint at the address");
+    println!("    stops only if EXPR is non-zero, EXPR is made of numbers, $registers, symbols(addresses),");
+    println!("    *(TYPE*)ADDR dereferences, (TYPE) casts and C operators, TYPE is one of u8-u64, i8-i64");
+    println!("    e.g) {} or {}", "break 0x401126".bright_yellow(), "break main+0x24".bright_yellow());
+    println!("         {}", "break work if *(i32*)hits >= 3 && $rdi != 0".bright_yellow());
+    println!("  condition [NUM] [EXPR] => stop at breakpoint NUM only if EXPR is non-zero, always if omitted");
+    println!("  ignore [NUM] [COUNT] => pass over breakpoint NUM the next COUNT times it's hit");
+    println!("  watch[/SIZE] [ADDR | SYMBOL] => stop when the memory is written and its value changes");
+    println!("    SIZE is one of b, h, w, g or the number of bytes, the symbol size or 4 if omitted");
+    println!("    e.g) {} or {}", "watch counter".bright_yellow(), "watch/g 0x555555558010".bright_yellow());
+    println!("  rwatch[/SIZE] [ADDR | SYMBOL] => stop when the memory is read");
+    println!("  awatch[/SIZE] [ADDR | SYMBOL] => stop when the memory is read or written");
+    println!("    watch is checked by single-stepping, much slower, once the debug registers are all in use");
+    println!("  delete | d [NUM] => delete breakpoint NUM, all breakpoints if NUM is omitted");
+    println!("  catch syscall [NAME | NR...] => stop at entry and exit of the system calls, any if omitted");
+    println!("    e.g) {} or {}", "catch syscall openat close".bright_yellow(), "catch syscall 59".bright_yellow());
+    println!("  delete catch => delete the syscall catchpoint");
+    println!("  trace syscalls [on | off] => print every system call with decoded arguments and return value");
+    println!("  disable [NUM] => disable breakpoint NUM, all breakpoints if NUM is omitted");
+    println!("  enable [NUM] => enable breakpoint NUM, all breakpoints if NUM is omitted");
+    println!("  x[/FMT] [ADDR] => examine memory, FMT is [COUNT][FORMAT][SIZE]");
+    println!("    FORMAT: x(hex) d(decimal) u(unsigned) c(char) s(string) i(instruction)");
+    println!("    SIZE  : b(byte) h(halfword) w(word) g(giant, 8 bytes)");
+    println!("    e.g) {} or {}", "x/4xg 0x7ffff7dd1000".bright_yellow(), "x/s 0x555555556004".bright_yellow());
+    println!("  print | p[/FMT] [EXPR] => evaluate EXPR, in decimal, hex, octal and binary if FMT is omitted");
+    println!("    FMT: x(hex) d(decimal) u(unsigned) o(octal) t(binary) c(char) a(address)");
+    println!("    EXPR takes 0x, 0o, 0b literals and every operator of the breakpoint conditions");
+    println!("    e.g) {} or {}", "p/x $rsp+0x10".bright_yellow(), "p *(u16*)counter << 4 | 0b1".bright_yellow());
+    println!("  track [ADDR | SYMBOL] [SIZE] [FMT] => show the memory at every stop, highlighted when it changes");
+    println!("    SIZE is 4 and FMT is d if omitted, FMT: x(hex) d(decimal) u(unsigned) c(char) f(float)");
+    println!("    without arguments, shows the tracked memory");
+    println!("    e.g) {} or {}", "track counter".bright_yellow(), "track $rsp+8 8 x".bright_yellow());
+    println!("  track history [NUM] => show the value of tracked memory NUM at each past stop");
+    println!("  untrack [NUM] => stop tracking memory NUM, everything if NUM is omitted");
+    println!("  search | find [OPTIONS] [PATTERN] => search readable memory for the pattern");
+    println!("    -s: ASCII string(default), -w: UTF-16 string, -b: hex bytes with ?? wildcards");
+    println!("    -1, -2, -4, -8: little endian integer of the width, PATTERN is an expression");
+    println!("    -r REGION: only the mappings with REGION in the path or the one containing the address");
+    println!("    -p PERMS: only the mappings with the permissions, e.g) rw or x");
+    println!("    e.g) {} or {}", "search -r libc /bin/sh".bright_yellow(), "search -b -p x 48 8b ?? 24".bright_yellow());
+    println!("  scan [TYPE VALUE] => find the writable memory holding VALUE, without arguments shows the candidates");
+    println!("    TYPE: i8 i16 i32 i64 u8 u16 u32 u64 f32 f64, floats match to the digits given");
+    println!("  scan next [FILTER] => keep the candidates passing FILTER, compared with the last scan");
+    println!("    FILTER: eq [VALUE], changed, unchanged, increased, decreased or VALUE");
+    println!("    e.g) {} then {}", "scan i32 100".bright_yellow(), "scan next decreased".bright_yellow());
+    println!("  scan reset => forget the candidates");
+    println!("  dump => dump [Subcommand]");
+    println!("    memory FILE START END => write the memory from START to END into FILE");
+    println!("    region NAME => write each readable mapping matching NAME as 'vmmap' does, into the current directory");
+    println!("    all DIR => write every readable mapping into DIR, listed in DIR/manifest.txt");
+    println!("    e.g) {} or {}", "dump memory code.bin $pc $pc+0x1000".bright_yellow(), "dump region heap".bright_yellow());
+    println!("  gcore [FILE] => write an ELF core file of the process into FILE, 'core.PID' if omitted");
+    println!("  restore FILE ADDR => write the content of FILE into the memory at ADDR, FILE may be quoted");
+    println!("  backtrace | bt [COUNT] => show the call stack by following the frame pointers");
+    println!("  disas [ADDR | SYMBOL] [COUNT] => disassemble COUNT instructions, the whole function if omitted");
+    println!("  set => set [Subcommand]");
+    println!("    $NAME = EXPR => set convenience variable $NAME usable in any expression, or the register");
+    println!("      $base, $libc: load addresses, $_exitcode: exit status,");
+    println!("      $_siginfo: number of the signal stopped by last, $_siginfo.si_signo, $_siginfo.si_code,");
+    println!("      $_siginfo.si_addr: the signal information, the address is given by the faults and traps");
+    println!("      e.g) {}", "set $gadget = $libc + 0x4f3d5".bright_yellow());
+    println!("    mem[/SIZE] [ADDR] [VALUE] => write VALUE to memory, SIZE is one of b, h, w, g");
+    println!("    reg [NAME] [VALUE] => write VALUE to the register, e.g) {}", "set reg rax 0x1337".bright_yellow());
+    println!("    args [ARGS...] => set arguments of the program to run");
+    println!("    env [NAME=VALUE] => set environment variable of the program to run");
+    println!("    cwd [DIR] => set working directory of the program to run");
+    println!("    disable-randomization [on | off] => disable ASLR of the program to run, on by default");
+    println!("    follow-fork-mode [parent | child] => process to debug after fork, parent by default");
+    println!("    detach-on-fork [on | off] => detach the other process after fork, or keep it as an inferior");
+    println!("  unset env [NAME] => remove NAME from the environment, all variables if omitted");
+    println!("  show => show [Subcommand]");
+    println!("    args | env | cwd => show arguments, environment or working directory to run with");
+    println!("    disable-randomization | follow-fork-mode | detach-on-fork => show the setting");
+    println!("    convenience | conv => show convenience variables");
     println!("  info => info [Subcommand]");
     println!("    regs => show registers");
+    println!("    all-registers => show registers including orig_rax, x87, SSE and AVX");
     println!("    proc => show process informations");
-    println!("  vmmap | maps => show memory maps of the process");
+    println!("    breakpoints | b => show breakpoints");
+    println!("    track => show the tracked memory");
+    println!("    watchpoints => show watchpoints and whether they use debug registers or single-stepping");
+    println!("    inferiors => show processes being debugged");
+    println!("    threads => show threads of the process");
+    println!("    signals => show how signals are handled");
+    println!("  vmmap | maps [ADDR | PERMS | NAME] => show memory maps of the process, colored by the contents");
+    println!("    only the region containing ADDR, the ones with PERMS or NAME in the path if given");
+    println!("    e.g) {} or {} or {}", "vmmap $rsp".bright_yellow(), "vmmap rwx".bright_yellow(), "vmmap libc".bright_yellow());
     println!("  kill => send signal to the attached process");
     println!("  exit | quit => Exit rsdb");
     MainLoopAction::None
 }
 
-pub fn rsdb_main(session: &mut session::Session, buffer: &String) -> MainLoopAction {
+pub fn rsdb_main(session: &mut session::Session, buffer: &str) -> MainLoopAction {
     let re = Regex::new(r"\s+").unwrap();
     let fullcmd = re.replace_all(buffer.trim(), " ");
-    let commands = Vec::from_iter(fullcmd.split(" ").map(String::from));
+    let commands = Vec::from_iter(fullcmd.split(' ').map(String::from));
     let command = &commands[0];
 
     match command.as_str() {
@@ -48,53 +204,508 @@ pub fn rsdb_main(session: &mut session::Session, buffer: &String) -> MainLoopAct
             continue_if!(commands.len() != 2, "Usage: attach [PID | Package/Process name]");
             continue_if!(session.proc.valid(), "rsdb is already holding the process, detach first");
             
+            // a name unless it looks like an expression, e.g) 31337 or $pid
             let process = &commands[1];
-            let new_target = match process.parse::<i32>() {
-                Ok(pid) => pid,
-                Err(_) => procfs::findpid(process),
+            let new_target = match process.starts_with(|c: char| c.is_ascii_digit() || c == '$' || c == '(') {
+                true => session.eval(process).map_or(-1, |pid| pid as i32),
+                false => procfs::findpid(process),
             };
             continue_if!(!procfs::check_pid(new_target), 
                          "pid doesn't exist, check again");
             command::attach(session, new_target)
         },
+        "core" | "core-file" => {
+            continue_if!(commands.len() > 2, "Usage: core [FILE]");
+            continue_if!(session.valid(), "rsdb is already holding the process, detach first");
+            command::core(session, commands.get(1).map(String::as_str))
+        },
         "detach" => {
             continue_if!(session.invalid(), "No process has been attached");
             command::detach(session)
         },
         "continue" | "c" => {
             continue_if!(session.invalid(), "No process has been attached");
-            command::cont(session.mut_proc())
+            command::cont(session)
+        },
+        "signal" => {
+            continue_if!(commands.len() != 2, "Usage: signal [SIG | 0]");
+            continue_if!(session.invalid(), "No process has been attached");
+            let sig = match commands[1].as_str() {
+                "0" => Ok(None),
+                sig => signal::parse(sig).map(Some).ok_or(()),
+            };
+            continue_if!(sig.is_err(), "Invalid signal");
+            command::signal(session, sig.unwrap())
+        },
+        "handle" => {
+            continue_if!(commands.len() < 2, "Usage: handle [SIG] [KEYWORDS...]");
+            let sig = signal::parse(&commands[1]);
+            continue_if!(sig.is_none(), "Invalid signal");
+            command::handle(session, sig.unwrap(), &commands[2..])
+        },
+        "inferior" => {
+            match commands.get(1) {
+                Some(_) => {
+                    let num = eval_count(session, rest_of_line(buffer, 1));
+                    continue_if!(num.is_err());
+                    command::inferior::select(session, num.unwrap())
+                },
+                None => command::inferior::current(session),
+            }
+        },
+        "thread" => {
+            continue_if!(session.invalid(), "No process has been attached");
+            match commands.get(1) {
+                Some(_) => {
+                    let num = eval_count(session, rest_of_line(buffer, 1));
+                    continue_if!(num.is_err());
+                    command::thread::select(session, num.unwrap())
+                },
+                None => command::thread::current(session),
+            }
         },
-        "run" | "r" => {
+        "stepi" | "si" => {
+            continue_if!(commands.len() > 2, "Usage: stepi [N]");
+            continue_if!(session.invalid(), "No process has been attached");
+            let count = parse_count(session, &commands);
+            continue_if!(count.is_err());
+            command::stepi(session, count.unwrap())
+        },
+        "nexti" | "ni" => {
+            continue_if!(commands.len() > 2, "Usage: nexti [N]");
+            continue_if!(session.invalid(), "No process has been attached");
+            let count = parse_count(session, &commands);
+            continue_if!(count.is_err());
+            command::nexti(session, count.unwrap())
+        },
+        "break" | "b" => {
+            let usage = "Usage: break [ADDR | SYMBOL] [if EXPR]";
+            let if_at = commands.iter().position(|word| word == "if").unwrap_or(commands.len());
+            continue_if!(if_at < 2 || if_at == commands.len() - 1, usage);
+
+            // the address is the text before 'if', the condition the rest after it
+            let line = rest_of_line(buffer, 1);
+            let text = line[..line.len() - rest_of_line(buffer, if_at).len()].trim_end();
+            let addr = session.eval(text);
+            continue_if!(addr.is_err());
+            let location = symbol_location(session, text);
+            let condition = match if_at == commands.len() {
+                true => Ok(None),
+                false => Expr::parse(rest_of_line(buffer, if_at + 1)).map(Some),
+            };
+            continue_if!(condition.is_err());
+            command::breakpoints::set(session, addr.unwrap(), location, condition.unwrap())
+        },
+        "condition" => {
+            continue_if!(commands.len() < 2, "Usage: condition [NUM] [EXPR]");
+            let id = eval_count(session, &commands[1]);
+            continue_if!(id.is_err());
+            let condition = match commands.len() {
+                2 => Ok(None),
+                _ => Expr::parse(rest_of_line(buffer, 2)).map(Some),
+            };
+            continue_if!(condition.is_err());
+            command::breakpoints::condition(session, id.unwrap(), condition.unwrap())
+        },
+        "ignore" => {
+            continue_if!(commands.len() < 3, "Usage: ignore [NUM] [COUNT]");
+            let id = eval_count(session, &commands[1]);
+            continue_if!(id.is_err());
+            let count = eval_count(session, rest_of_line(buffer, 2));
+            continue_if!(count.is_err());
+            command::breakpoints::ignore(session, id.unwrap(), count.unwrap())
+        },
+        w if ["watch", "rwatch", "awatch"].contains(&w.split('/').next().unwrap_or("")) => {
+            continue_if!(commands.len() < 2, "Usage: watch|rwatch|awatch[/SIZE] [ADDR | SYMBOL]");
+            let (name, size) = w.split_at(w.find('/').unwrap_or(w.len()));
+            let size = parse_watch_size(size);
+            continue_if!(size.is_err(), "Invalid size, one of b, h, w, g or the number of bytes");
+            let text = rest_of_line(buffer, 1);
+            let addr = session.eval(text);
+            continue_if!(addr.is_err());
+            let addr = addr.unwrap();
+            let location = symbol_location(session, text);
+
+            // a symbol is watched as a whole
+            let symbol_size = session.symbols.bounds(addr)
+                .filter(|(start, _)| *start == addr && session.symbols.lookup(text).is_some())
+                .map(|(_, size)| size as usize);
+            let len = size.unwrap().or(symbol_size).unwrap_or(4);
+            let kind = match name {
+                "rwatch" => WatchKind::Read,
+                "awatch" => WatchKind::Access,
+                _ => WatchKind::Write,
+            };
+            command::breakpoints::watch(session, addr, len, kind, location)
+        },
+        "delete" | "d" if commands.get(1).map(String::as_str) == Some("catch") => {
+            continue_if!(commands.len() != 2, "Usage: delete catch");
+            command::syscall::delete(session)
+        },
+        "delete" | "d" => {
+            let id = parse_bp_id(session, rest_of_line(buffer, 1));
+            continue_if!(id.is_err());
+            command::breakpoints::delete(session, id.unwrap())
+        },
+        "disable" | "enable" => {
+            let id = parse_bp_id(session, rest_of_line(buffer, 1));
+            continue_if!(id.is_err());
+            command::breakpoints::enable(session, id.unwrap(), command == "enable")
+        },
+        "catch" => {
+            continue_if!(commands.len() < 2 || commands[1] != "syscall", "Usage: catch syscall [NAME | NR...]");
+            command::syscall::catch(session, &commands[2..])
+        },
+        "trace" => {
+            let usage = "Usage: trace syscalls [on | off]";
+            continue_if!(commands.len() < 2 || commands.len() > 3 || commands[1] != "syscalls", usage);
+            let on = match commands.get(2).map(String::as_str) {
+                Some("on") | None => Ok(true),
+                Some("off") => Ok(false),
+                _ => Err(()),
+            };
+            continue_if!(on.is_err(), usage);
+            command::syscall::trace(session, on.unwrap())
+        },
+        "run" | "r" | "start" | "starti" => {
             continue_if!(session.valid(), "rsdb is already holding the process, detach first");
             continue_if!(session.elf.is_none(), "File is not available!");
-            command::run(session)
+            if commands.len() > 1 {
+                continue_if!(session.launch.parse_args(rest_of_line(buffer, 1)).is_err(), "Invalid arguments");
+            }
+            match command.as_str() {
+                "start" => command::start(session),
+                "starti" => command::starti(session),
+                _ => command::run(session),
+            }
         },
         "info" => {
             continue_if!(commands.len() != 2, "Usage: info [Subcommand], help for more details");
             match commands[1].as_str() {
                 "regs" | "r" => {
-                    continue_if!(session.invalid(), "No process has been attached");
-                    command::info::regs(session.mut_proc());
+                    continue_if!(!session.has_target(), "No process or core file");
+                    command::info::regs(session);
+                },
+                "all-registers" => {
+                    continue_if!(!session.has_target(), "No process or core file");
+                    command::info::all_registers(session);
                 },
                 "proc" => {
                     continue_if!(session.invalid(), "No process has been attached");
                     command::info::proc(session);
                 },
+                "breakpoints" | "b" => {
+                    command::info::breakpoints(session);
+                },
+                "watchpoints" => {
+                    command::info::watchpoints(session);
+                },
+                "track" => {
+                    command::track::list(session);
+                },
+                "inferiors" => {
+                    command::info::inferiors(session);
+                },
+                "threads" => {
+                    continue_if!(!session.has_target(), "No process or core file");
+                    command::info::threads(session);
+                },
+                "signals" | "signal" => {
+                    command::info::signals(session, None);
+                },
                 subcommand => println!("{}'{}'", "info: invalid subcommand: ".red(), subcommand),
             }
             MainLoopAction::None
         },
-        "vmmap" | "maps" => {
+        x if x == "x" || x.starts_with("x/") => {
+            continue_if!(commands.len() < 2, "Usage: x[/FMT] [ADDR]");
+            continue_if!(!session.has_target(), "No process or core file");
+            let format = command::memory::Format::parse(&x[1..]);
+            continue_if!(format.is_err(), "Invalid format, e.g) x/4xg");
+            let addr = session.eval(&commands[1..].join(" "));
+            continue_if!(addr.is_err());
+            command::memory::examine(session, &format.unwrap(), addr.unwrap())
+        },
+        p if ["print", "p"].contains(&p.split('/').next().unwrap_or("")) => {
+            continue_if!(commands.len() < 2, "Usage: print[/FMT] [EXPR]");
+            let format = command::print::Format::parse(&p[p.find('/').unwrap_or(p.len())..]);
+            continue_if!(format.is_err(), "Invalid format, one of x, d, u, o, t, c, a");
+            let value = session.eval(rest_of_line(buffer, 1));
+            continue_if!(value.is_err());
+            command::print::print(session, format.unwrap(), value.unwrap())
+        },
+        "track" if commands.len() == 1 => command::track::list(session),
+        "track" if commands[1] == "history" => {
+            continue_if!(commands.len() < 3, "Usage: track history [NUM]");
+            let id = eval_count(session, rest_of_line(buffer, 2));
+            continue_if!(id.is_err());
+            command::track::history(session, id.unwrap())
+        },
+        "track" => {
+            let usage = "Usage: track [ADDR | SYMBOL] [SIZE] [x | d | u | c | f]";
+            continue_if!(commands.len() > 4, usage);
+            let addr = session.eval(&commands[1]);
+            continue_if!(addr.is_err());
+            let location = symbol_location(session, &commands[1]);
+            let size = match commands.get(2) {
+                Some(size) => eval_count(session, size),
+                None => Ok(4),
+            };
+            continue_if!(size.is_err());
+            let size = size.unwrap();
+            let fmt = match commands.get(3).map(String::as_str) {
+                None => 'd',
+                Some(fmt) if fmt.len() == 1 && "xducf".contains(fmt) => fmt.chars().next().unwrap_or('d'),
+                Some(_) => '?',
+            };
+            continue_if!(fmt == '?', usage);
+            continue_if!(size == 0 || size > 4096, "Invalid size, 1 to 4096 bytes");
+            continue_if!(fmt == 'f' && size != 4 && size != 8, "Invalid size, floats are 4 or 8 bytes");
+            command::track::add(session, addr.unwrap(), size, fmt, location)
+        },
+        "untrack" => {
+            let id = parse_bp_id(session, rest_of_line(buffer, 1));
+            continue_if!(id.is_err());
+            command::track::delete(session, id.unwrap())
+        },
+        "scan" if commands.len() == 1 => command::scan::list(session),
+        "scan" if commands[1] == "reset" => command::scan::reset(session),
+        "scan" if commands[1] == "next" => {
+            let usage = "Usage: scan next [eq [VALUE] | changed | unchanged | increased | decreased | VALUE]";
+            continue_if!(commands.len() < 3, usage);
+            continue_if!(session.invalid(), "No process has been attached");
+            let ty = session.scan.as_ref().map(|scan| scan.ty);
+            continue_if!(ty.is_none(), "No scan in progress, start with 'scan TYPE VALUE'");
+            let ty = ty.unwrap();
+            let filter = match commands[2].as_str() {
+                "changed" => Ok(Filter::Changed),
+                "unchanged" => Ok(Filter::Unchanged),
+                "increased" => Ok(Filter::Increased),
+                "decreased" => Ok(Filter::Decreased),
+                "eq" if commands.len() == 3 => Ok(Filter::Unchanged),
+                "eq" => command::scan::parse_expected(session, ty, &commands[3..].join(" ")).map(Filter::Equal),
+                _ => command::scan::parse_expected(session, ty, &commands[2..].join(" ")).map(Filter::Equal),
+            };
+            continue_if!(filter.is_err());
+            command::scan::next(session, &filter.unwrap())
+        },
+        "scan" => {
+            let usage = "Usage: scan [TYPE VALUE | next FILTER | reset], TYPE: i8 i16 i32 i64 u8 u16 u32 u64 f32 f64";
+            continue_if!(commands.len() < 3, usage);
+            continue_if!(session.invalid(), "No process has been attached");
+            let ty = ValueType::parse(&commands[1]);
+            continue_if!(ty.is_none(), usage);
+            let ty = ty.unwrap();
+            let expected = command::scan::parse_expected(session, ty, &commands[2..].join(" "));
+            continue_if!(expected.is_err());
+            command::scan::start(session, ty, &expected.unwrap())
+        },
+        "search" | "find" => {
+            let usage = "Usage: search [-b | -s | -w | -1 | -2 | -4 | -8] [-r REGION] [-p PERMS] [PATTERN]";
+            continue_if!(commands.len() < 2, usage);
             continue_if!(session.invalid(), "No process has been attached");
-            command::vmmap(session.mut_proc())
+            let query = command::search::Query::parse(session, &commands[1..], rest_of_line(buffer, 1));
+            continue_if!(query.is_err(), usage);
+            command::search::search(session, &query.unwrap())
+        },
+        "dump" => {
+            let usage = "Usage: dump [memory FILE START END | region NAME | all DIR]";
+            continue_if!(commands.len() < 3, usage);
+            continue_if!(session.invalid(), "No process has been attached");
+            match (commands[1].as_str(), commands.len()) {
+                ("memory", 5) => {
+                    let start = session.eval(&commands[3]);
+                    continue_if!(start.is_err());
+                    let end = session.eval(&commands[4]);
+                    continue_if!(end.is_err());
+                    let (start, end) = (start.unwrap(), end.unwrap());
+                    continue_if!(start >= end, "Invalid range, START must be below END");
+                    command::dump::memory(session, &commands[2], start, end)
+                },
+                ("region", 3) => command::dump::region(session, &commands[2]),
+                ("all", 3) => command::dump::all(session, &commands[2]),
+                _ => {
+                    println!("{}", usage);
+                    MainLoopAction::None
+                },
+            }
+        },
+        "gcore" | "generate-core-file" => {
+            continue_if!(commands.len() > 2, "Usage: gcore [FILE]");
+            continue_if!(session.invalid(), "No process has been attached");
+            command::dump::gcore(session, commands.get(1).map(String::as_str))
+        },
+        "restore" => {
+            continue_if!(commands.len() < 3, "Usage: restore FILE ADDR");
+            continue_if!(session.invalid(), "No process has been attached");
+            let addr = session.eval(&commands[commands.len() - 1]);
+            continue_if!(addr.is_err());
+
+            // the file is the rest before the address, quoted if it has spaces
+            let file = rest_of_line(buffer, 1);
+            let file = file[..file.len() - commands[commands.len() - 1].len()].trim_end();
+            let file = file.strip_prefix('"').and_then(|file| file.strip_suffix('"')).unwrap_or(file);
+            command::dump::restore(session, file, addr.unwrap())
+        },
+        "backtrace" | "bt" => {
+            continue_if!(commands.len() > 2, "Usage: backtrace [COUNT]");
+            continue_if!(!session.has_target(), "No process or core file");
+            let count = match commands.get(1) {
+                Some(count) => eval_count(session, count).map(Some),
+                None => Ok(None),
+            };
+            continue_if!(count.is_err());
+            command::backtrace::backtrace(session, count.unwrap())
+        },
+        "disas" | "disassemble" => {
+            continue_if!(commands.len() > 3, "Usage: disas [ADDR | SYMBOL] [COUNT]");
+            continue_if!(!session.has_target(), "No process or core file");
+            let addr = match commands.get(1) {
+                Some(addr) => session.eval(addr),
+                None => session.target().getreg("pc"),
+            };
+            continue_if!(addr.is_err());
+            let count = match commands.get(2) {
+                Some(count) => eval_count(session, count).map(Some),
+                None => Ok(None),
+            };
+            continue_if!(count.is_err());
+            command::disas::disassemble(session, addr.unwrap(), count.unwrap())
+        },
+        "set" => {
+            continue_if!(commands.len() < 2, "Usage: set [Subcommand], help for more details");
+            match commands[1].as_str() {
+                var if var.starts_with('$') => {
+                    let usage = "Usage: set $NAME = EXPR";
+                    let assignment = commands[1..].join(" ");
+                    let (name, expr) = match assignment[1..].split_once('=') {
+                        Some((name, expr)) => (name.trim().to_string(), expr.trim().to_string()),
+                        None => (String::new(), String::new()),
+                    };
+                    continue_if!(name.is_empty() || expr.is_empty(), usage);
+                    continue_if!(!name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'), "Invalid variable name");
+                    let value = session.eval(&expr);
+                    continue_if!(value.is_err());
+                    command::set::variable(session, &name, value.unwrap());
+                },
+                mem if mem == "mem" || mem.starts_with("mem/") => {
+                    continue_if!(commands.len() < 4, "Usage: set mem[/SIZE] [ADDR] [VALUE]");
+                    continue_if!(session.invalid(), "No process has been attached");
+                    let size = match &mem[3..] {
+                        "" | "/w" => 4,
+                        "/b" => 1,
+                        "/h" => 2,
+                        "/g" => 8,
+                        _ => 0,
+                    };
+                    continue_if!(size == 0, "Invalid size, one of b, h, w, g");
+                    let addr = session.eval(&commands[2]);
+                    continue_if!(addr.is_err());
+                    let value = session.eval(rest_of_line(buffer, 3));
+                    continue_if!(value.is_err());
+                    command::set::mem(session, addr.unwrap(), value.unwrap(), size);
+                },
+                "reg" => {
+                    continue_if!(commands.len() < 4, "Usage: set reg [NAME] [VALUE]");
+                    continue_if!(session.invalid(), "No process has been attached");
+                    let value = session.eval(rest_of_line(buffer, 3));
+                    continue_if!(value.is_err());
+                    command::set::reg(session, &commands[2], value.unwrap());
+                },
+                "args" => {
+                    command::set::args(session, rest_of_line(buffer, 2));
+                },
+                "env" | "environment" => {
+                    continue_if!(commands.len() < 3, "Usage: set env [NAME=VALUE | NAME VALUE]");
+                    let (name, value) = match commands[2].split_once('=') {
+                        Some((name, _)) => {
+                            let value = commands[2..].join(" ")[name.len() + 1..].to_string();
+                            (name.to_string(), value)
+                        },
+                        None => (commands[2].clone(), commands[3..].join(" ")),
+                    };
+                    continue_if!(name.is_empty(), "Invalid variable name");
+                    command::set::env(session, &name, &value);
+                },
+                "follow-fork-mode" => {
+                    let usage = "Usage: set follow-fork-mode [parent | child]";
+                    continue_if!(commands.len() != 3, usage);
+                    let mode = match commands[2].as_str() {
+                        "parent" => Ok(session::FollowForkMode::Parent),
+                        "child" => Ok(session::FollowForkMode::Child),
+                        _ => Err(()),
+                    };
+                    continue_if!(mode.is_err(), usage);
+                    command::set::follow_fork_mode(session, mode.unwrap());
+                },
+                "detach-on-fork" => {
+                    let usage = "Usage: set detach-on-fork [on | off]";
+                    continue_if!(commands.len() != 3, usage);
+                    let detach = match commands[2].as_str() {
+                        "on" => Ok(true),
+                        "off" => Ok(false),
+                        _ => Err(()),
+                    };
+                    continue_if!(detach.is_err(), usage);
+                    command::set::detach_on_fork(session, detach.unwrap());
+                },
+                "disable-randomization" => {
+                    let usage = "Usage: set disable-randomization [on | off]";
+                    continue_if!(commands.len() != 3, usage);
+                    let disable = match commands[2].as_str() {
+                        "on" => Ok(true),
+                        "off" => Ok(false),
+                        _ => Err(()),
+                    };
+                    continue_if!(disable.is_err(), usage);
+                    command::set::disable_randomization(session, disable.unwrap());
+                },
+                "cwd" => {
+                    continue_if!(commands.len() > 3, "Usage: set cwd [DIR]");
+                    command::set::cwd(session, commands.get(2).map(String::as_str));
+                },
+                subcommand => println!("{}'{}'", "set: invalid subcommand: ".red(), subcommand),
+            }
+            MainLoopAction::None
+        },
+        "unset" => {
+            continue_if!(commands.len() < 2, "Usage: unset env [NAME]");
+            match commands[1].as_str() {
+                "env" | "environment" => {
+                    continue_if!(commands.len() > 3, "Usage: unset env [NAME]");
+                    command::set::unset_env(session, commands.get(2).map(String::as_str));
+                },
+                subcommand => println!("{}'{}'", "unset: invalid subcommand: ".red(), subcommand),
+            }
+            MainLoopAction::None
+        },
+        "show" => {
+            continue_if!(commands.len() != 2, "Usage: show [Subcommand], help for more details");
+            match commands[1].as_str() {
+                "args" => command::show::args(session),
+                "env" | "environment" => command::show::env(session),
+                "cwd" => command::show::cwd(session),
+                "disable-randomization" => command::show::disable_randomization(session),
+                "follow-fork-mode" => command::show::follow_fork_mode(session),
+                "detach-on-fork" => command::show::detach_on_fork(session),
+                "convenience" | "conv" => command::show::convenience(session),
+                subcommand => {
+                    println!("{}'{}'", "show: invalid subcommand: ".red(), subcommand);
+                    MainLoopAction::None
+                },
+            }
+        },
+        "vmmap" | "maps" => {
+            continue_if!(commands.len() > 2, "Usage: vmmap [ADDR | PERMS | NAME]");
+            continue_if!(!session.has_target(), "No process or core file");
+            command::vmmap(session, commands.get(1).map(String::as_str))
         },
         "kill" => {
             continue_if!(commands.len() != 1, "Usage: kill");
             continue_if!(session.invalid(), "No process has been attached");
-            command::kill(session.mut_proc())
+            command::kill(session)
         },
-        "exit" | "quit" | "q" => command::quit(session.mut_proc()),
+        "exit" | "quit" | "q" => command::quit(session),
         "help" | "?" => rsdb_help(),
         "" => MainLoopAction::None,
         invalid_cmd => {
@@ -103,3 +714,37 @@ pub fn rsdb_main(session: &mut session::Session, buffer: &String) -> MainLoopAct
         },
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rest_of_line_keeps_the_text() {
+        assert_eq!(rest_of_line("  break  $base + 0x10 ", 1), "$base + 0x10");
+        assert_eq!(rest_of_line("set mem/b $sp  0x40 + 1", 3), "0x40 + 1");
+        assert_eq!(rest_of_line("delete", 1), "");
+        assert_eq!(rest_of_line("a b", 5), "");
+    }
+
+    #[test]
+    fn counts_are_bounded() {
+        let session = session::Session::new();
+        assert_eq!(eval_count(&session, "2 * 3"), Ok(6));
+        assert_eq!(eval_count(&session, "0xffffffff"), Ok(u32::MAX as usize));
+        assert!(eval_count(&session, "-1").is_err());
+        assert!(eval_count(&session, "1 << 32").is_err());
+        assert_eq!(parse_bp_id(&session, ""), Ok(None));
+        assert_eq!(parse_bp_id(&session, "1 + 1"), Ok(Some(2)));
+        assert!(parse_bp_id(&session, "one").is_err());
+    }
+
+    #[test]
+    fn watch_sizes() {
+        assert_eq!(parse_watch_size(""), Ok(None));
+        assert_eq!(parse_watch_size("/g"), Ok(Some(8)));
+        assert_eq!(parse_watch_size("/3"), Ok(Some(3)));
+        assert!(parse_watch_size("/0").is_err());
+        assert!(parse_watch_size("/q").is_err());
+    }
+}
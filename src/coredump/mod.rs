@@ -0,0 +1,53 @@
+// ELF core files of the Linux layout, the same as the kernel and gdb write.
+// Notes carry the process and thread state, PT_LOAD segments the memory.
+
+pub mod reader;
+pub mod writer;
+
+pub const ET_CORE: u16 = 4;
+pub const PT_LOAD: u32 = 1;
+pub const PT_NOTE: u32 = 4;
+
+// p_flags
+pub const PF_X: u32 = 1;
+pub const PF_W: u32 = 2;
+pub const PF_R: u32 = 4;
+
+pub const EHDR_SIZE: usize = 64;
+pub const PHDR_SIZE: usize = 56;
+
+// Note types, all in the "CORE" namespace
+pub const NT_PRSTATUS: u32 = 1;
+pub const NT_PRFPREG: u32 = 2;
+pub const NT_PRPSINFO: u32 = 3;
+pub const NT_AUXV: u32 = 6;
+pub const NT_FILE: u32 = 0x4649_4c45;
+pub const NOTE_NAME: &[u8] = b"CORE\0";
+
+// struct elf_prstatus: siginfo, cursig, sigpend, sighold, pid, ppid, pgrp, sid,
+// 4 timevals and then the general purpose registers followed by pr_fpvalid
+pub const PRSTATUS_CURSIG: usize = 12;
+pub const PRSTATUS_PID: usize = 32;
+pub const PRSTATUS_REGS: usize = 112;
+
+// struct elf_prpsinfo of 64-bit targets
+pub const PRPSINFO_SIZE: usize = 136;
+pub const PRPSINFO_FNAME: usize = 40;
+pub const PRPSINFO_PSARGS: usize = 56;
+
+pub fn align_up(value: u64, align: u64) -> u64 {
+    value.div_ceil(align) * align
+}
+
+// Note entry: namesz, descsz and type, followed by the name and @desc padded to 4 bytes
+pub fn encode_note(kind: u32, desc: &[u8]) -> Vec<u8> {
+    let mut note = Vec::new();
+    note.extend_from_slice(&(NOTE_NAME.len() as u32).to_le_bytes());
+    note.extend_from_slice(&(desc.len() as u32).to_le_bytes());
+    note.extend_from_slice(&kind.to_le_bytes());
+    note.extend_from_slice(NOTE_NAME);
+    note.resize(align_up(note.len() as u64, 4) as usize, 0);
+    note.extend_from_slice(desc);
+    note.resize(align_up(note.len() as u64, 4) as usize, 0);
+    note
+}
@@ -0,0 +1,459 @@
+use std::fs;
+use std::os::unix::fs::FileExt;
+use std::path::{Path, PathBuf};
+
+use super::*;
+use crate::arch::{Regs, RegisterFile};
+use crate::process::maps::{MemoryMap, MemoryRegion};
+use crate::traits::Target;
+
+// Auxiliary vector entry of the entry point, the executable is the file mapped there
+const AT_ENTRY: u64 = 9;
+
+// PT_LOAD: memory [vaddr, vaddr + memsz), the first filesz bytes are at offset of the file.
+// Only the segments of which the end doesn't overflow are kept, filesz is at most memsz.
+struct Segment {
+    vaddr: u64,
+    memsz: u64,
+    offset: u64,
+    filesz: u64,
+}
+
+// File mapped at [start, end), from NT_FILE
+struct MappedFile {
+    start: u64,
+    end: u64,
+    offset: u64,
+    path: String,
+}
+
+// Thread state in NT_PRSTATUS
+pub struct CoreThread {
+    pub tid: i32,
+
+    // signal the thread stopped by, 0 if none
+    pub signal: i32,
+    regs: Regs,
+}
+
+// ELF core file read in place of a live process, nothing in it can be changed
+pub struct CoreFile {
+    file: fs::File,
+    segments: Vec<Segment>,
+    pub threads: Vec<CoreThread>,
+    current: i32,
+    pid: i32,
+
+    // empty if the core file doesn't tell
+    exe: PathBuf,
+    maps: MemoryMap,
+
+    // command line the process was started with, from NT_PRPSINFO
+    pub psargs: String,
+}
+
+fn u16_at(buf: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([buf[offset], buf[offset + 1]])
+}
+
+fn u32_at(buf: &[u8], offset: usize) -> u32 {
+    let mut raw = [0u8; 4];
+    raw.copy_from_slice(&buf[offset..offset + 4]);
+    u32::from_le_bytes(raw)
+}
+
+fn u64_at(buf: &[u8], offset: usize) -> u64 {
+    let mut raw = [0u8; 8];
+    raw.copy_from_slice(&buf[offset..offset + 8]);
+    u64::from_le_bytes(raw)
+}
+
+// NUL terminated string in @buf
+fn str_at(buf: &[u8]) -> String {
+    let len = buf.iter().position(|b| *b == 0).unwrap_or(buf.len());
+    String::from_utf8_lossy(&buf[..len]).to_string()
+}
+
+// (type, desc) of the notes in PT_NOTE
+fn parse_notes(notes: &[u8]) -> Vec<(u32, &[u8])> {
+    let mut list = Vec::new();
+    let mut offset = 0;
+    while offset + 12 <= notes.len() {
+        let namesz = u32_at(notes, offset) as usize;
+        let descsz = u32_at(notes, offset + 4) as usize;
+        let kind = u32_at(notes, offset + 8);
+        let desc = offset + 12 + align_up(namesz as u64, 4) as usize;
+        if desc + descsz > notes.len() {
+            break;
+        }
+        list.push((kind, &notes[desc..desc + descsz]));
+        offset = desc + align_up(descsz as u64, 4) as usize;
+    }
+    list
+}
+
+// NT_PRSTATUS, None if too short for the registers of the architecture
+fn parse_prstatus(desc: &[u8]) -> Option<CoreThread> {
+    let regs_size = std::mem::size_of::<Regs>();
+    if desc.len() < PRSTATUS_REGS + regs_size {
+        return None;
+    }
+
+    // general purpose registers are laid out as user_regs_struct
+    let regs = unsafe { std::ptr::read_unaligned(desc[PRSTATUS_REGS..].as_ptr() as *const Regs) };
+    Some(CoreThread {
+        tid: u32_at(desc, PRSTATUS_PID) as i32,
+        signal: u16_at(desc, PRSTATUS_CURSIG) as i32,
+        regs,
+    })
+}
+
+// NT_FILE: count, page size, (start, end, offset in pages) of each file, then the paths.
+// The note is ignored if it can't hold as many files as it counts.
+fn parse_file_note(desc: &[u8]) -> Vec<MappedFile> {
+    if desc.len() < 16 {
+        return Vec::new();
+    }
+    let count = u64_at(desc, 0);
+    let page = u64_at(desc, 8);
+    let names_offset = count.checked_mul(24)
+        .and_then(|size| size.checked_add(16))
+        .filter(|offset| *offset <= desc.len() as u64);
+    let names_offset = match names_offset {
+        Some(offset) => offset as usize,
+        None => return Vec::new(),
+    };
+    let names = desc[names_offset..].split(|b| *b == 0);
+    desc[16..names_offset].chunks_exact(24).zip(names)
+        .filter_map(|(entry, name)| Some(MappedFile {
+            start: u64_at(entry, 0),
+            end: u64_at(entry, 8),
+            offset: u64_at(entry, 16).checked_mul(page)?,
+            path: String::from_utf8_lossy(name).to_string(),
+        }))
+        .collect()
+}
+
+fn auxv_entry(auxv: &[u8], kind: u64) -> Option<u64> {
+    auxv.chunks_exact(16)
+        .find(|entry| u64_at(entry, 0) == kind)
+        .map(|entry| u64_at(entry, 8))
+}
+
+impl CoreFile {
+    // Reads the headers and notes, the memory is read from the file on demand
+    pub fn open(path: &Path) -> Result<Self, ()> {
+        let file = fs::File::open(path).map_err(|errstr| {
+            println!("Cannot open '{}': {}", path.display(), errstr);
+        })?;
+        let mut ehdr = [0u8; EHDR_SIZE];
+        let is_core = file.read_exact_at(&mut ehdr, 0).is_ok() &&
+            ehdr[..4] == [0x7f, b'E', b'L', b'F'] && ehdr[4] == 2 && ehdr[5] == 1 &&
+            u16_at(&ehdr, 16) == ET_CORE;
+        if !is_core {
+            println!("'{}' is not a 64-bit little endian core file", path.display());
+            return Err(());
+        }
+        if u16_at(&ehdr, 18) != Regs::ELF_MACHINE {
+            println!("'{}' is a core file of another architecture", path.display());
+            return Err(());
+        }
+
+        // sizes in the headers are checked against the file before anything is allocated
+        let file_len = file.metadata().map(|meta| meta.len()).unwrap_or(0);
+        let within = |offset: u64, size: u64| offset.checked_add(size).is_some_and(|end| end <= file_len);
+
+        let phoff = u64_at(&ehdr, 32);
+        let phentsize = u16_at(&ehdr, 54) as usize;
+        let phnum = u16_at(&ehdr, 56) as usize;
+        if phentsize < PHDR_SIZE || !within(phoff, (phentsize * phnum) as u64) {
+            println!("Cannot read the program headers of '{}'", path.display());
+            return Err(());
+        }
+        let mut phdrs = vec![0u8; phentsize * phnum];
+        if file.read_exact_at(&mut phdrs, phoff).is_err() {
+            println!("Cannot read the program headers of '{}'", path.display());
+            return Err(());
+        }
+
+        let mut segments = Vec::new();
+        let mut flags = Vec::new();
+        let mut notes = Vec::new();
+        for phdr in phdrs.chunks_exact(phentsize) {
+            let (offset, vaddr, filesz, memsz) = (u64_at(phdr, 8), u64_at(phdr, 16), u64_at(phdr, 32), u64_at(phdr, 40));
+            match u32_at(phdr, 0) {
+                // memory wrapping around the address space is left out
+                PT_LOAD if vaddr.checked_add(memsz).is_some() => {
+                    segments.push(Segment { vaddr, memsz, offset, filesz: filesz.min(memsz) });
+                    flags.push(u32_at(phdr, 4));
+                },
+                PT_NOTE if !within(offset, filesz) => {
+                    println!("Notes of '{}' run past the end of the file, ignored", path.display());
+                },
+                PT_NOTE => {
+                    let mut buf = vec![0u8; filesz as usize];
+                    if file.read_exact_at(&mut buf, offset).is_ok() {
+                        notes.extend(buf);
+                    }
+                },
+                _ => (),
+            }
+        }
+
+        let mut threads = Vec::new();
+        let (mut pid, mut psargs) = (None, String::new());
+        let (mut files, mut entry) = (Vec::new(), None);
+        for (kind, desc) in parse_notes(&notes) {
+            match kind {
+                NT_PRSTATUS => threads.extend(parse_prstatus(desc)),
+                NT_PRPSINFO if desc.len() >= PRPSINFO_SIZE => {
+                    pid = Some(u32_at(desc, 24) as i32);
+                    psargs = str_at(&desc[PRPSINFO_PSARGS..PRPSINFO_SIZE]);
+                },
+                NT_AUXV => entry = auxv_entry(desc, AT_ENTRY),
+                NT_FILE => files = parse_file_note(desc),
+                _ => (),
+            }
+        }
+        if threads.is_empty() {
+            println!("No thread found in the core file '{}'", path.display());
+            return Err(());
+        }
+
+        // the mapped files come from NT_FILE, core files keep no inode
+        // so the same path is given the same made-up one
+        let mut paths: Vec<String> = Vec::new();
+        let mut regions = Vec::new();
+        for (segment, flags) in segments.iter().zip(flags) {
+            let mapped = files.iter().find(|file| file.start <= segment.vaddr && segment.vaddr < file.end);
+            let (path, offset) = match mapped {
+                Some(file) => (file.path.clone(), file.offset + (segment.vaddr - file.start)),
+                None => (String::new(), 0),
+            };
+            let inode = match paths.iter().position(|known| *known == path) {
+                _ if path.is_empty() => 0,
+                Some(index) => index + 1,
+                None => {
+                    paths.push(path.clone());
+                    paths.len()
+                },
+            };
+            regions.push(MemoryRegion {
+                start: segment.vaddr,
+                end: segment.vaddr + segment.memsz,
+                read: flags & PF_R != 0,
+                write: flags & PF_W != 0,
+                exec: flags & PF_X != 0,
+                shared: false,
+                offset,
+                dev: "00:00".to_string(),
+                inode: inode as u64,
+                path,
+            });
+        }
+
+        // the stack is anonymous in the core, told by the stack pointer of the main thread
+        let pid = pid.unwrap_or(threads[0].tid);
+        let sp = threads.iter()
+            .find(|thread| thread.tid == pid)
+            .and_then(|thread| thread.regs.get("sp"));
+        if let Some(region) = regions.iter_mut().find(|region| sp.is_some_and(|sp| region.contains(sp))) {
+            if region.path.is_empty() {
+                region.path = "[stack]".to_string();
+            }
+        }
+
+        // the executable is mapped at the entry point, or comes first.
+        // Left empty without the files, the command line may not even name a path.
+        let exe = entry
+            .and_then(|entry| files.iter().find(|file| file.start <= entry && entry < file.end))
+            .or_else(|| files.first())
+            .map_or_else(String::new, |file| file.path.clone());
+        segments.sort_by_key(|segment| segment.vaddr);
+        Ok(CoreFile {
+            file,
+            segments,
+            current: threads[0].tid,
+            pid,
+            threads,
+            exe: PathBuf::from(exe),
+            maps: MemoryMap::from_regions(regions),
+            psargs,
+        })
+    }
+
+    // Reads as much as @buf from @addr within one segment, returns the bytes read
+    fn read_piece(&self, addr: u64, buf: &mut [u8]) -> usize {
+        let index = self.segments.partition_point(|segment| segment.vaddr + segment.memsz <= addr);
+        let segment = match self.segments.get(index).filter(|segment| segment.vaddr <= addr) {
+            Some(segment) => segment,
+            None => return 0,
+        };
+        let len = buf.len().min((segment.vaddr + segment.memsz - addr) as usize);
+        if addr < segment.vaddr + segment.filesz {
+            let len = len.min((segment.vaddr + segment.filesz - addr) as usize);
+            return self.file.read_at(&mut buf[..len], segment.offset + (addr - segment.vaddr)).unwrap_or(0);
+        }
+
+        // left out of the core such as the code never written, read from the file mapped
+        let region = match self.maps.find(addr).filter(|region| region.path.starts_with('/')) {
+            Some(region) => region,
+            None => return 0,
+        };
+        fs::File::open(&region.path)
+            .and_then(|file| file.read_at(&mut buf[..len], region.offset + (addr - region.start)))
+            .unwrap_or(0)
+    }
+}
+
+impl Target for CoreFile {
+    fn pid(&self) -> i32 { self.pid }
+
+    fn tid(&self) -> i32 { self.current }
+
+    fn tids(&self) -> Vec<i32> {
+        self.threads.iter().map(|thread| thread.tid).collect()
+    }
+
+    fn get_exe(&self) -> &PathBuf { &self.exe }
+
+    fn maps(&self) -> &MemoryMap { &self.maps }
+
+    fn read_memory(&self, addr: u64, len: usize) -> Result<Vec<u8>, ()> {
+        let mut buf = vec![0u8; len];
+        let mut done = 0;
+        while done < len {
+            match self.read_piece(addr + done as u64, &mut buf[done..]) {
+                0 => return Err(()),
+                read => done += read,
+            }
+        }
+        Ok(buf)
+    }
+
+    fn write_memory(&self, _addr: u64, _data: &[u8]) -> Result<(), ()> {
+        Err(())
+    }
+
+    fn thread_regs(&self, tid: i32) -> Result<Regs, ()> {
+        self.threads.iter()
+            .find(|thread| thread.tid == tid)
+            .map(|thread| thread.regs)
+            .ok_or(())
+    }
+
+    fn set_thread_regs(&self, _tid: i32, _regs: &Regs) -> Result<(), ()> {
+        Err(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file_note(count: u64, entries: &[[u64; 3]], names: &[u8]) -> Vec<u8> {
+        let mut desc = Vec::new();
+        desc.extend_from_slice(&count.to_le_bytes());
+        desc.extend_from_slice(&0x1000u64.to_le_bytes());
+        for value in entries.iter().flatten() {
+            desc.extend_from_slice(&value.to_le_bytes());
+        }
+        desc.extend_from_slice(names);
+        desc
+    }
+
+    #[test]
+    fn files_of_the_note() {
+        let desc = file_note(2, &[[0x400000, 0x401000, 2], [0x7f0000, 0x7f8000, 0]], b"/tmp/game\0/lib/libc.so.6\0");
+        let files = parse_file_note(&desc);
+        assert_eq!(files.len(), 2);
+        assert_eq!((files[0].start, files[0].end, files[0].offset), (0x400000, 0x401000, 0x2000));
+        assert_eq!((files[0].path.as_str(), files[1].path.as_str()), ("/tmp/game", "/lib/libc.so.6"));
+    }
+
+    #[test]
+    fn broken_file_notes_are_ignored() {
+        assert!(parse_file_note(&[0; 15]).is_empty());
+        assert!(parse_file_note(&file_note(u64::MAX, &[], b"")).is_empty());
+        assert!(parse_file_note(&file_note(u64::MAX / 24 + 1, &[], b"")).is_empty());
+        assert!(parse_file_note(&file_note(2, &[[0x400000, 0x401000, 0]], b"/tmp/game\0")).is_empty());
+
+        // the offset in bytes doesn't fit
+        let files = parse_file_note(&file_note(1, &[[0x400000, 0x401000, u64::MAX]], b"/tmp/game\0"));
+        assert!(files.is_empty());
+    }
+
+    #[test]
+    fn notes_stop_at_a_truncated_one() {
+        let mut notes = encode_note(NT_AUXV, &[1; 16]);
+        notes.extend(encode_note(NT_FILE, &[2; 20]));
+        let list = parse_notes(&notes);
+        assert_eq!(list.len(), 2);
+        assert_eq!((list[0].0, list[0].1), (NT_AUXV, &[1u8; 16][..]));
+        assert_eq!((list[1].0, list[1].1), (NT_FILE, &[2u8; 20][..]));
+        assert_eq!(parse_notes(&notes[..notes.len() - 4]).len(), 1);
+    }
+
+    #[test]
+    fn entry_point_of_the_auxv() {
+        let auxv: Vec<u8> = [[6, 0x1000], [AT_ENTRY, 0x401020], [0, 0]].iter().flatten()
+            .flat_map(|value: &u64| value.to_le_bytes())
+            .collect();
+        assert_eq!(auxv_entry(&auxv, AT_ENTRY), Some(0x401020));
+        assert_eq!(auxv_entry(&auxv, 3), None);
+    }
+
+    // Core file of @phdrs, (type, offset, vaddr, filesz, memsz) each, followed by @tail
+    fn write_core(name: &str, phdrs: &[(u32, u64, u64, u64, u64)], phnum: u16, tail: &[u8]) -> PathBuf {
+        let mut bytes = vec![0x7f, b'E', b'L', b'F', 2, 1, 1];
+        bytes.resize(EHDR_SIZE, 0);
+        bytes[16..18].copy_from_slice(&ET_CORE.to_le_bytes());
+        bytes[18..20].copy_from_slice(&Regs::ELF_MACHINE.to_le_bytes());
+        bytes[32..40].copy_from_slice(&(EHDR_SIZE as u64).to_le_bytes());
+        bytes[54..56].copy_from_slice(&(PHDR_SIZE as u16).to_le_bytes());
+        bytes[56..58].copy_from_slice(&phnum.to_le_bytes());
+        for (kind, offset, vaddr, filesz, memsz) in phdrs {
+            bytes.extend_from_slice(&kind.to_le_bytes());
+            bytes.extend_from_slice(&PF_R.to_le_bytes());
+            for value in [*offset, *vaddr, 0, *filesz, *memsz, 0x1000] {
+                bytes.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+        bytes.extend_from_slice(tail);
+        let path = std::env::temp_dir().join(format!("rsdb-reader-{}-{}", std::process::id(), name));
+        fs::write(&path, bytes).unwrap();
+        path
+    }
+
+    fn open(path: PathBuf) -> Result<CoreFile, ()> {
+        let core = CoreFile::open(&path);
+        fs::remove_file(&path).unwrap();
+        core
+    }
+
+    #[test]
+    fn headers_past_the_end_are_rejected() {
+        assert!(open(write_core("phnum", &[], u16::MAX, &[])).is_err());
+
+        // the notes claim far more than the file has
+        let notes_offset = (EHDR_SIZE + PHDR_SIZE) as u64;
+        assert!(open(write_core("notes", &[(PT_NOTE, notes_offset, 0, u64::MAX / 2, 0)], 1, &[])).is_err());
+    }
+
+    #[test]
+    fn overflowing_segments_are_left_out() {
+        let thread = encode_note(NT_PRSTATUS, &vec![0; PRSTATUS_REGS + std::mem::size_of::<Regs>() + 8]);
+        let notes_offset = (EHDR_SIZE + PHDR_SIZE * 3) as u64;
+        let phdrs = [
+            (PT_NOTE, notes_offset, 0, thread.len() as u64, 0),
+            (PT_LOAD, 0, u64::MAX - 0xfff, 0, 0x2000),
+            (PT_LOAD, 0, 0x1000, 0x2000, 0x1000),
+        ];
+        let core = open(write_core("overflow", &phdrs, 3, &thread)).unwrap();
+        assert_eq!(core.segments.len(), 1);
+        assert_eq!(core.segments[0].filesz, 0x1000);
+        assert_eq!(core.maps().iter().count(), 1);
+        assert!(core.read_memory(u64::MAX - 0x10, 8).is_err());
+        assert_eq!(core.read_memory(0x1000, 4), Ok(vec![0x7f, b'E', b'L', b'F']));
+    }
+}
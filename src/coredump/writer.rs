@@ -0,0 +1,492 @@
+use std::fs;
+use std::os::unix::fs::FileExt;
+use std::path::Path;
+
+use super::*;
+use crate::arch::{Regs, RegisterFile};
+use crate::breakpoint::BreakpointTable;
+use crate::process::{Proc, procfs, maps::MemoryRegion};
+use crate::ptrace;
+use crate::traits::Target;
+
+// Bytes copied from '/proc/{PID}/mem' at once
+const CHUNK_SIZE: usize = 1 << 20;
+
+// Large enough for the floating point registers of any architecture
+const FPREGS_MAX: usize = 4096;
+
+fn page_size() -> u64 {
+    match unsafe { libc::sysconf(libc::_SC_PAGESIZE) } {
+        size if size > 0 => size as u64,
+        _ => 4096,
+    }
+}
+
+fn put_u32(buf: &mut [u8], offset: usize, value: u32) {
+    buf[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+}
+
+fn put_str(buf: &mut [u8], offset: usize, max: usize, text: &str) {
+    let len = text.len().min(max - 1);
+    buf[offset..offset + len].copy_from_slice(&text.as_bytes()[..len]);
+}
+
+// NT_PRSTATUS of the thread @tid with the signal it stopped by
+fn prstatus(tid: i32, signal: i32, stat: &procfs::ProcStat, regs: &Regs, fpvalid: bool) -> Vec<u8> {
+    let regs_size = std::mem::size_of::<Regs>();
+    let mut desc = vec![0u8; PRSTATUS_REGS + regs_size + 8];
+
+    // general purpose registers are laid out as user_regs_struct
+    let raw = unsafe { std::slice::from_raw_parts(regs as *const Regs as *const u8, regs_size) };
+    desc[PRSTATUS_REGS..PRSTATUS_REGS + regs_size].copy_from_slice(raw);
+    put_u32(&mut desc, 0, signal as u32);
+    desc[PRSTATUS_CURSIG..PRSTATUS_CURSIG + 2].copy_from_slice(&(signal as u16).to_le_bytes());
+    for (i, id) in [tid, stat.ppid, stat.pgrp, stat.session].iter().enumerate() {
+        put_u32(&mut desc, PRSTATUS_PID + i * 4, *id as u32);
+    }
+    put_u32(&mut desc, PRSTATUS_REGS + regs_size, fpvalid as u32);
+    desc
+}
+
+// pr_state as the kernel sets, the bit of the task state plus one, e.g) 4 for 't' of the
+// tracing stop. 'x', 'K' and 'W' of the old kernels are dead, killable and waking ones.
+fn task_state(state: char) -> u8 {
+    match state {
+        'S' => 1,
+        'D' | 'K' => 2,
+        'T' => 3,
+        't' => 4,
+        'X' | 'x' => 5,
+        'Z' => 6,
+        'P' => 7,
+        'I' => 8,
+        _ => 0,
+    }
+}
+
+// Whether the region goes into the core file, not the ones of the kernel
+// such as '[vvar]' which can't be read and '[vsyscall]' as gdb leaves them out
+fn is_dumped(region: &MemoryRegion) -> bool {
+    !["[vvar]", "[vvar_vclock]", "[vsyscall]"].contains(&region.path.as_str())
+}
+
+// NT_PRPSINFO: state, ids, the name and the command line @args cut to 79 bytes
+fn prpsinfo(pid: i32, stat: &procfs::ProcStat, (uid, gid): (u32, u32), args: &str) -> Vec<u8> {
+    let mut desc = vec![0u8; PRPSINFO_SIZE];
+    desc[0] = task_state(stat.state);
+    desc[1] = stat.state as u8;
+    desc[2] = (stat.state == 'Z') as u8;
+    desc[3] = stat.nice as i8 as u8;
+    put_u32(&mut desc, 16, uid);
+    put_u32(&mut desc, 20, gid);
+    for (i, id) in [pid, stat.ppid, stat.pgrp, stat.session].iter().enumerate() {
+        put_u32(&mut desc, 24 + i * 4, *id as u32);
+    }
+    put_str(&mut desc, PRPSINFO_FNAME, 16, &stat.comm);
+    put_str(&mut desc, PRPSINFO_PSARGS, 80, args);
+    desc
+}
+
+// NT_FILE: count, page size, (start, end, offset in pages) of each mapped file, then the paths
+fn file_note(regions: &[&MemoryRegion], page: u64) -> Vec<u8> {
+    let files: Vec<&&MemoryRegion> = regions.iter().filter(|region| region.path.starts_with('/')).collect();
+    let mut desc = Vec::new();
+    desc.extend_from_slice(&(files.len() as u64).to_le_bytes());
+    desc.extend_from_slice(&page.to_le_bytes());
+    for region in files.iter() {
+        for value in [region.start, region.end, region.offset / page] {
+            desc.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+    for region in files.iter() {
+        desc.extend_from_slice(region.path.as_bytes());
+        desc.push(0);
+    }
+    desc
+}
+
+// Copies the region into @file at @offset, returns the bytes copied,
+// which stop at the first part that can't be read such as guard pages
+fn copy_region(mem: &fs::File, breakpoints: &BreakpointTable, region: &MemoryRegion,
+               file: &fs::File, offset: u64) -> Result<u64, ()> {
+    let mut buf = vec![0u8; CHUNK_SIZE];
+    let mut addr = region.start;
+    while region.read && addr < region.end {
+        let len = (region.end - addr).min(CHUNK_SIZE as u64) as usize;
+        let read = mem.read_at(&mut buf[..len], addr).unwrap_or(0);
+        if read == 0 {
+            break;
+        }
+        breakpoints.unpatch(addr, &mut buf[..read]);
+        file.write_all_at(&buf[..read], offset + (addr - region.start)).map_err(|errstr| {
+            println!("Cannot write the core file: {}", errstr);
+        })?;
+        addr += read as u64;
+    }
+    Ok(addr - region.start)
+}
+
+// Writes the core file of the stopped process @proc to @path, returns the file size
+pub fn write(proc: &Proc, breakpoints: &BreakpointTable, path: &Path) -> Result<u64, ()> {
+    let pid = proc.target;
+    let stat = procfs::get_proc_stat(pid).ok_or_else(|| {
+        println!("Cannot read from: '/proc/{}/stat'", pid);
+    })?;
+    let page = page_size();
+    let regions: Vec<&MemoryRegion> = proc.maps().iter().filter(|region| is_dumped(region)).collect();
+
+    // the current thread comes first, debuggers take it as the one crashed
+    let mut tids = vec![proc.tid()];
+    tids.extend(proc.threads.tids().into_iter().filter(|tid| *tid != proc.tid()));
+    let mut notes = Vec::new();
+    for (i, tid) in tids.iter().enumerate() {
+        let regs = ptrace::getregset_as::<Regs>(*tid, NT_PRSTATUS as i32).map_err(|_| {
+            println!("Failed to read registers of thread {}", tid);
+        })?;
+        let mut fpregs = vec![0u8; FPREGS_MAX];
+        let fpregs = ptrace::getregset(*tid, NT_PRFPREG as i32, &mut fpregs)
+            .map(|size| fpregs[..size].to_vec())
+            .ok();
+        let signal = proc.threads.get(*tid)
+            .and_then(|thread| thread.pending_signal)
+            .map_or(0, |signal| signal as i32);
+        notes.extend(encode_note(NT_PRSTATUS, &prstatus(*tid, signal, &stat, &regs, fpregs.is_some())));
+        if i == 0 {
+            let ids = procfs::get_proc_ids(pid).unwrap_or((0, 0));
+            let args = procfs::get_proc_cmdline(pid).unwrap_or_default().replace('\0', " ");
+            notes.extend(encode_note(NT_PRPSINFO, &prpsinfo(pid, &stat, ids, args.trim_end())));
+            notes.extend(encode_note(NT_AUXV, &procfs::get_proc_auxv(pid)?));
+            notes.extend(encode_note(NT_FILE, &file_note(&regions, page)));
+        }
+        if let Some(fpregs) = fpregs {
+            notes.extend(encode_note(NT_PRFPREG, &fpregs));
+        }
+    }
+
+    let file = fs::File::create(path).map_err(|errstr| {
+        println!("Cannot create '{}': {}", path.display(), errstr);
+    })?;
+    let mem = procfs::open_proc_mem(pid)?;
+    write_core(&file, &notes, &regions, page, |region, offset| copy_region(&mem, breakpoints, region, &file, offset))
+}
+
+// Lays out the core file: the headers, @notes and then the memory of @regions,
+// which @copy writes at the file offset given and returns the bytes written.
+// Memory goes first past the room for the headers and notes, the program headers
+// are written last when the size of each segment is known. Returns the file size.
+fn write_core(file: &fs::File, notes: &[u8], regions: &[&MemoryRegion], page: u64,
+              mut copy: impl FnMut(&MemoryRegion, u64) -> Result<u64, ()>) -> Result<u64, ()> {
+    let phnum = regions.len() + 1;
+    let notes_offset = (EHDR_SIZE + PHDR_SIZE * phnum) as u64;
+    let mut offset = align_up(notes_offset + notes.len() as u64, page);
+
+    // PT_NOTE and then PT_LOAD for every region, unreadable ones have no content in the file
+    let mut phdrs = Vec::new();
+    phdrs.extend(phdr(PT_NOTE, 0, notes_offset, 0, notes.len() as u64, 0, 1));
+    for region in regions.iter() {
+        let size = copy(region, offset)?;
+        let flags = [(region.read, PF_R), (region.write, PF_W), (region.exec, PF_X)].iter()
+            .filter(|(set, _)| *set)
+            .fold(0, |flags, (_, flag)| flags | flag);
+        phdrs.extend(phdr(PT_LOAD, flags, offset, region.start, size, region.size(), page));
+        offset += align_up(size, page);
+    }
+
+    let mut head = ehdr(phnum as u16);
+    head.extend(phdrs);
+    head.extend_from_slice(notes);
+    file.write_all_at(&head, 0)
+        .and_then(|_| file.set_len(offset))
+        .map_err(|errstr| {
+            println!("Cannot write the core file: {}", errstr);
+        })?;
+    Ok(offset)
+}
+
+// Elf64_Ehdr of a little endian core file for the build target
+fn ehdr(phnum: u16) -> Vec<u8> {
+    let mut head = vec![0x7f, b'E', b'L', b'F', 2, 1, 1];
+    head.resize(16, 0);
+    head.extend_from_slice(&ET_CORE.to_le_bytes());
+    head.extend_from_slice(&Regs::ELF_MACHINE.to_le_bytes());
+    head.extend_from_slice(&1u32.to_le_bytes());
+    head.extend_from_slice(&0u64.to_le_bytes());
+    head.extend_from_slice(&(EHDR_SIZE as u64).to_le_bytes());
+    head.extend_from_slice(&0u64.to_le_bytes());
+    head.extend_from_slice(&0u32.to_le_bytes());
+    for half in [EHDR_SIZE as u16, PHDR_SIZE as u16, phnum, 0, 0, 0] {
+        head.extend_from_slice(&half.to_le_bytes());
+    }
+    head
+}
+
+// Elf64_Phdr
+fn phdr(kind: u32, flags: u32, offset: u64, vaddr: u64, filesz: u64, memsz: u64, align: u64) -> Vec<u8> {
+    let mut phdr = Vec::with_capacity(PHDR_SIZE);
+    phdr.extend_from_slice(&kind.to_le_bytes());
+    phdr.extend_from_slice(&flags.to_le_bytes());
+    for value in [offset, vaddr, 0, filesz, memsz, align] {
+        phdr.extend_from_slice(&value.to_le_bytes());
+    }
+    phdr
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coredump::reader::CoreFile;
+    use std::convert::TryInto;
+    use std::mem::{offset_of, size_of};
+
+    // struct elf_prstatus and elf_prpsinfo of <linux/elfcore.h> for 64-bit targets
+    #[repr(C)]
+    struct ElfPrstatus {
+        si_signo: i32,
+        si_code: i32,
+        si_errno: i32,
+        cursig: i16,
+        sigpend: u64,
+        sighold: u64,
+        pid: i32,
+        ppid: i32,
+        pgrp: i32,
+        sid: i32,
+        times: [[i64; 2]; 4],
+        reg: Regs,
+        fpvalid: i32,
+    }
+
+    #[repr(C)]
+    struct ElfPrpsinfo {
+        state: u8,
+        sname: u8,
+        zomb: u8,
+        nice: i8,
+        flag: u64,
+        uid: u32,
+        gid: u32,
+        pid: i32,
+        ppid: i32,
+        pgrp: i32,
+        sid: i32,
+        fname: [u8; 16],
+        psargs: [u8; 80],
+    }
+
+    fn stat() -> procfs::ProcStat {
+        procfs::ProcStat { comm: "game".to_string(), state: 't', ppid: 10, pgrp: 11, session: 12, nice: -2 }
+    }
+
+    fn region(start: u64, end: u64, path: &str) -> MemoryRegion {
+        MemoryRegion {
+            start, end, read: true, write: path.is_empty(), exec: !path.is_empty(), shared: false,
+            offset: 0x2000, dev: "08:01".to_string(), inode: 1, path: path.to_string(),
+        }
+    }
+
+    fn u32_at(buf: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes(buf[offset..offset + 4].try_into().unwrap())
+    }
+
+    fn u64_at(buf: &[u8], offset: usize) -> u64 {
+        u64::from_le_bytes(buf[offset..offset + 8].try_into().unwrap())
+    }
+
+    #[test]
+    fn layout_matches_the_kernel() {
+        assert_eq!(offset_of!(ElfPrstatus, cursig), PRSTATUS_CURSIG);
+        assert_eq!(offset_of!(ElfPrstatus, pid), PRSTATUS_PID);
+        assert_eq!(offset_of!(ElfPrstatus, reg), PRSTATUS_REGS);
+        assert_eq!(offset_of!(ElfPrstatus, fpvalid), PRSTATUS_REGS + size_of::<Regs>());
+        assert_eq!(size_of::<ElfPrstatus>(), PRSTATUS_REGS + size_of::<Regs>() + 8);
+        assert_eq!(offset_of!(ElfPrpsinfo, uid), 16);
+        assert_eq!(offset_of!(ElfPrpsinfo, pid), 24);
+        assert_eq!(offset_of!(ElfPrpsinfo, fname), PRPSINFO_FNAME);
+        assert_eq!(offset_of!(ElfPrpsinfo, psargs), PRPSINFO_PSARGS);
+        assert_eq!(size_of::<ElfPrpsinfo>(), PRPSINFO_SIZE);
+    }
+
+    #[test]
+    fn elf_header() {
+        let head = ehdr(3);
+        assert_eq!(head.len(), EHDR_SIZE);
+        assert_eq!(head[..7], [0x7f, b'E', b'L', b'F', 2, 1, 1]);
+        assert_eq!(u16::from_le_bytes([head[16], head[17]]), ET_CORE);
+        assert_eq!(u16::from_le_bytes([head[18], head[19]]), Regs::ELF_MACHINE);
+        assert_eq!(u64_at(&head, 32), EHDR_SIZE as u64);
+        assert_eq!(head[52..60], [EHDR_SIZE as u8, 0, PHDR_SIZE as u8, 0, 3, 0, 0, 0]);
+    }
+
+    #[test]
+    fn program_header() {
+        let header = phdr(PT_LOAD, PF_R | PF_X, 0x3000, 0x400000, 0x800, 0x1000, 0x1000);
+        assert_eq!(header.len(), PHDR_SIZE);
+        assert_eq!((u32_at(&header, 0), u32_at(&header, 4)), (PT_LOAD, PF_R | PF_X));
+        let fields: Vec<u64> = (8..PHDR_SIZE).step_by(8).map(|offset| u64_at(&header, offset)).collect();
+        assert_eq!(fields, vec![0x3000, 0x400000, 0, 0x800, 0x1000, 0x1000]);
+    }
+
+    #[test]
+    fn notes_are_padded() {
+        let note = encode_note(NT_AUXV, &[1, 2, 3, 4, 5]);
+        assert_eq!(note.len(), 12 + 8 + 8);
+        assert_eq!((u32_at(&note, 0), u32_at(&note, 4), u32_at(&note, 8)), (5, 5, NT_AUXV));
+        assert_eq!(&note[12..20], b"CORE\0\0\0\0");
+        assert_eq!(&note[20..], &[1, 2, 3, 4, 5, 0, 0, 0]);
+    }
+
+    #[test]
+    fn process_notes() {
+        let mut regs: Regs = unsafe { std::mem::zeroed() };
+        regs.set("pc", 0x401000).unwrap();
+        let desc = prstatus(42, 11, &stat(), &regs, true);
+        assert_eq!(desc.len(), PRSTATUS_REGS + size_of::<Regs>() + 8);
+        assert_eq!((u32_at(&desc, 0), u16::from_le_bytes([desc[PRSTATUS_CURSIG], desc[PRSTATUS_CURSIG + 1]])), (11, 11));
+        let ids: Vec<u32> = (0..4).map(|i| u32_at(&desc, PRSTATUS_PID + i * 4)).collect();
+        assert_eq!(ids, vec![42, 10, 11, 12]);
+        let stored = unsafe { std::ptr::read_unaligned(desc[PRSTATUS_REGS..].as_ptr() as *const Regs) };
+        assert_eq!(stored.get("pc"), Some(0x401000));
+        assert_eq!(u32_at(&desc, PRSTATUS_REGS + size_of::<Regs>()), 1);
+
+        let args = "x".repeat(100);
+        let desc = prpsinfo(42, &stat(), (1000, 100), &args);
+        assert_eq!(desc.len(), PRPSINFO_SIZE);
+        assert_eq!((desc[0], desc[1], desc[3] as i8), (4, b't', -2));
+        assert_eq!((u32_at(&desc, 16), u32_at(&desc, 20), u32_at(&desc, 24)), (1000, 100, 42));
+        assert_eq!(&desc[PRPSINFO_FNAME..PRPSINFO_FNAME + 5], b"game\0");
+        assert_eq!(desc[PRPSINFO_PSARGS..PRPSINFO_SIZE].iter().filter(|b| **b == b'x').count(), 79);
+        assert_eq!(desc[PRPSINFO_SIZE - 1], 0);
+    }
+
+    #[test]
+    fn task_states_of_the_kernel() {
+        let states: Vec<u8> = "RSDTtXZPIxKW?".chars().map(task_state).collect();
+        assert_eq!(states, vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 5, 2, 0, 0]);
+        let idle = procfs::ProcStat { state: 'I', ..stat() };
+        let desc = prpsinfo(42, &idle, (0, 0), "");
+        assert_eq!((desc[0], desc[1], desc[2]), (8, b'I', 0));
+        let zombie = procfs::ProcStat { state: 'Z', ..stat() };
+        assert_eq!(prpsinfo(42, &zombie, (0, 0), "")[..3], [6, b'Z', 1]);
+    }
+
+    #[test]
+    fn kernel_regions_are_left_out() {
+        assert!(is_dumped(&region(0x400000, 0x401000, "/tmp/game")));
+        assert!(is_dumped(&region(0x7ffe0000, 0x7ffe2000, "[vdso]")));
+        assert!(is_dumped(&region(0x7ffe0000, 0x7ffe2000, "")));
+        assert!(!is_dumped(&region(0x7ffd0000, 0x7ffd4000, "[vvar]")));
+        assert!(!is_dumped(&region(0xffffffffff600000, 0xffffffffff601000, "[vsyscall]")));
+    }
+
+    #[test]
+    fn file_note_lists_the_files_only() {
+        let (exe, heap) = (region(0x400000, 0x401000, "/tmp/game"), region(0x500000, 0x510000, "[heap]"));
+        let desc = file_note(&[&exe, &heap], 0x1000);
+        assert_eq!((u64_at(&desc, 0), u64_at(&desc, 8)), (1, 0x1000));
+        assert_eq!((u64_at(&desc, 16), u64_at(&desc, 24), u64_at(&desc, 32)), (0x400000, 0x401000, 2));
+        assert_eq!(&desc[40..], b"/tmp/game\0");
+    }
+
+    #[test]
+    fn core_file_layout() {
+        let path = std::env::temp_dir().join(format!("rsdb-writer-{}", std::process::id()));
+        let file = fs::File::create(&path).unwrap();
+        let (code, data) = (region(0x400000, 0x402000, "/tmp/game"), region(0x600000, 0x601000, ""));
+        let notes = encode_note(NT_AUXV, &[7; 16]);
+        let page = 0x1000;
+
+        // the code is cut short as if the rest couldn't be read
+        let size = write_core(&file, &notes, &[&code, &data], page, |region, offset| {
+            let len = if region.exec { 0x10 } else { region.size() };
+            file.write_all_at(&vec![region.start as u8 + 1; len as usize], offset).unwrap();
+            Ok(len)
+        }).unwrap();
+        let bytes = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(size, bytes.len() as u64);
+        assert_eq!(size, 3 * page);
+
+        let notes_offset = EHDR_SIZE + PHDR_SIZE * 3;
+        assert_eq!(bytes[..EHDR_SIZE], ehdr(3)[..]);
+        assert_eq!(bytes[EHDR_SIZE..EHDR_SIZE + PHDR_SIZE], phdr(PT_NOTE, 0, notes_offset as u64, 0, notes.len() as u64, 0, 1)[..]);
+        assert_eq!(bytes[notes_offset..notes_offset + notes.len()], notes[..]);
+
+        let load = |i: usize| &bytes[EHDR_SIZE + PHDR_SIZE * i..EHDR_SIZE + PHDR_SIZE * (i + 1)];
+        assert_eq!(load(1), &phdr(PT_LOAD, PF_R | PF_X, page, 0x400000, 0x10, 0x2000, page)[..]);
+        assert_eq!(load(2), &phdr(PT_LOAD, PF_R | PF_W, 2 * page, 0x600000, 0x1000, 0x1000, page)[..]);
+        assert_eq!(bytes[page as usize..page as usize + 0x11], [[1; 0x10].as_slice(), &[0]].concat()[..]);
+        assert!(bytes[2 * page as usize..].iter().all(|b| *b == 1));
+    }
+
+    // Core file of a made-up process with the executable, a library, the heap and the stack
+    fn write_fake_core(path: &Path, with_files: bool) {
+        let exe = region(0x400000, 0x402000, "/tmp/my game");
+        let lib = region(0x7f0000, 0x7f1000, "/usr/lib/libc.so.6");
+        let heap = region(0x500000, 0x501000, "[heap]");
+        let stack = region(0x7ffe0000, 0x7ffe2000, "");
+        let regions = [&lib, &exe, &heap, &stack];
+
+        let mut notes = Vec::new();
+        for (tid, pc, sp) in [(100, 0x401000, 0x7ffe1ff0), (101, 0x7f0010, 0x500800)] {
+            let mut regs: Regs = unsafe { std::mem::zeroed() };
+            regs.set("pc", pc).unwrap();
+            regs.set("sp", sp).unwrap();
+            let signal = if tid == 100 { 11 } else { 0 };
+            notes.extend(encode_note(NT_PRSTATUS, &prstatus(tid, signal, &stat(), &regs, false)));
+            if tid == 100 {
+                notes.extend(encode_note(NT_PRPSINFO, &prpsinfo(100, &stat(), (0, 0), "game --level 2")));
+                let auxv: Vec<u8> = [9, 0x400100, 0, 0].iter().flat_map(|value: &u64| value.to_le_bytes()).collect();
+                notes.extend(encode_note(NT_AUXV, &auxv));
+                if with_files {
+                    notes.extend(encode_note(NT_FILE, &file_note(&regions, 0x1000)));
+                }
+            }
+        }
+
+        // each byte tells the page it's in
+        let file = fs::File::create(path).unwrap();
+        write_core(&file, &notes, &regions, 0x1000, |region, offset| {
+            let bytes: Vec<u8> = (region.start..region.end).map(|addr| (addr >> 12) as u8).collect();
+            file.write_all_at(&bytes, offset).unwrap();
+            Ok(region.size())
+        }).unwrap();
+    }
+
+    #[test]
+    fn core_file_reads_back() {
+        let path = std::env::temp_dir().join(format!("rsdb-core-{}", std::process::id()));
+        write_fake_core(&path, true);
+        let core = CoreFile::open(&path);
+        fs::remove_file(&path).unwrap();
+        let core = core.unwrap();
+
+        assert_eq!((core.pid(), core.tid(), core.tids()), (100, 100, vec![100, 101]));
+        assert_eq!((core.threads[0].signal, core.threads[1].signal), (11, 0));
+        assert_eq!(core.psargs, "game --level 2");
+        let regs = core.thread_regs(101).unwrap();
+        assert_eq!((regs.get("pc"), regs.get("sp")), (Some(0x7f0010), Some(0x500800)));
+        assert!(core.thread_regs(102).is_err());
+
+        // the executable is the file at the entry point, the stack is told by the main thread
+        assert_eq!(core.get_exe(), Path::new("/tmp/my game"));
+        let paths: Vec<&str> = core.maps().iter().map(|region| region.path.as_str()).collect();
+        assert_eq!(paths, vec!["/tmp/my game", "", "/usr/lib/libc.so.6", "[stack]"]);
+        assert_eq!(core.maps().find(0x400000).map(|region| region.offset), Some(0x2000));
+
+        assert_eq!(core.read_memory(0x401ffe, 2), Ok(vec![0x01, 0x01]));
+        assert_eq!(core.read_memory(0x7ffe0ffe, 4), Ok(vec![0xe0, 0xe0, 0xe1, 0xe1]));
+        assert!(core.read_memory(0x402000, 1).is_err());
+        assert!(core.write_memory(0x401000, &[0]).is_err());
+    }
+
+    #[test]
+    fn unknown_executable_is_left_empty() {
+        let path = std::env::temp_dir().join(format!("rsdb-core-nofile-{}", std::process::id()));
+        write_fake_core(&path, false);
+        let core = CoreFile::open(&path);
+        fs::remove_file(&path).unwrap();
+        let core = core.unwrap();
+        assert!(core.get_exe().as_os_str().is_empty());
+        assert!(core.maps().iter().all(|region| region.path.is_empty() || region.path == "[stack]"));
+    }
+}
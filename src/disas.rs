@@ -0,0 +1,135 @@
+use capstone::prelude::*;
+use capstone::arch::ArchOperand;
+use capstone::InsnGroupType;
+
+pub struct Instruction {
+    pub addr: u64,
+    pub size: usize,
+    pub mnemonic: String,
+    pub operands: String,
+
+    // destination of direct jumps and calls
+    pub target: Option<u64>,
+}
+
+#[cfg(target_arch = "x86_64")]
+fn capstone() -> CsResult<Capstone> {
+    Capstone::new()
+        .x86()
+        .mode(arch::x86::ArchMode::Mode64)
+        .syntax(arch::x86::ArchSyntax::Intel)
+        .detail(true)
+        .build()
+}
+
+#[cfg(target_arch = "aarch64")]
+fn capstone() -> CsResult<Capstone> {
+    Capstone::new()
+        .arm64()
+        .mode(arch::arm64::ArchMode::Arm)
+        .detail(true)
+        .build()
+}
+
+#[cfg(target_arch = "x86_64")]
+fn immediate(operand: &ArchOperand) -> Option<u64> {
+    match operand {
+        ArchOperand::X86Operand(op) => match op.op_type {
+            arch::x86::X86OperandType::Imm(imm) => Some(imm as u64),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+fn immediate(operand: &ArchOperand) -> Option<u64> {
+    match operand {
+        ArchOperand::Arm64Operand(op) => match op.op_type {
+            arch::arm64::Arm64OperandType::Imm(imm) => Some(imm as u64),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+// Branch target of direct jumps and calls, the last immediate operand since
+// 'tbz x0, #3, #0x401126' tests a bit first. Memory and register operands are indirect.
+fn branch_target(cs: &Capstone, insn: &capstone::Insn) -> Option<u64> {
+    let detail = cs.insn_detail(insn).ok()?;
+    let is_branch = detail.groups().any(|group| {
+        group.0 as u32 == InsnGroupType::CS_GRP_JUMP || group.0 as u32 == InsnGroupType::CS_GRP_CALL
+    });
+    if !is_branch {
+        return None;
+    }
+    detail.arch_detail().operands().iter().rev().find_map(immediate)
+}
+
+// Decodes up to @count instructions of @code located at @addr,
+// stops at the first invalid instruction.
+pub fn disassemble(code: &[u8], addr: u64, count: usize) -> Result<Vec<Instruction>, ()> {
+    let cs = capstone().map_err(|_| ())?;
+    let insns = cs.disasm_count(code, addr, count).map_err(|_| ())?;
+    Ok(insns.iter()
+        .map(|insn| Instruction {
+            addr: insn.address(),
+            size: insn.bytes().len(),
+            mnemonic: insn.mnemonic().unwrap_or("").to_string(),
+            operands: insn.op_str().unwrap_or("").to_string(),
+            target: branch_target(&cs, &insn),
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn targets(code: &[u8], addr: u64) -> Vec<Option<u64>> {
+        disassemble(code, addr, 16).unwrap().iter().map(|insn| insn.target).collect()
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    #[test]
+    fn direct_branches_have_targets() {
+        // call 0x1005; je 0x1009; jmp 0x1000
+        let code = [0xe8, 0x00, 0x00, 0x00, 0x00, 0x74, 0x02, 0xeb, 0xf7];
+        assert_eq!(targets(&code, 0x1000), vec![Some(0x1005), Some(0x1009), Some(0x1000)]);
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    #[test]
+    fn indirect_branches_have_none() {
+        // call [rip+0x2fe2]; jmp rax; mov eax, 0x1000; ret
+        let code = [0xff, 0x15, 0xe2, 0x2f, 0x00, 0x00, 0xff, 0xe0,
+                    0xb8, 0x00, 0x10, 0x00, 0x00, 0xc3];
+        assert_eq!(targets(&code, 0x1000), vec![None, None, None, None]);
+    }
+
+    #[cfg(target_arch = "aarch64")]
+    #[test]
+    fn direct_branches_have_targets() {
+        // bl 0x1010; tbz x0, #3, 0x1008; b.eq 0x1000
+        let code: Vec<u8> = [0x9400_0004u32, 0x3618_0020, 0x54ff_ffc0].iter()
+            .flat_map(|insn| insn.to_le_bytes())
+            .collect();
+        assert_eq!(targets(&code, 0x1000), vec![Some(0x1010), Some(0x1008), Some(0x1000)]);
+    }
+
+    #[cfg(target_arch = "aarch64")]
+    #[test]
+    fn indirect_branches_have_none() {
+        // blr x1; br x2; ret; mov x0, #0x10
+        let code: Vec<u8> = [0xd63f_0020u32, 0xd61f_0040, 0xd65f_03c0, 0xd280_0200].iter()
+            .flat_map(|insn| insn.to_le_bytes())
+            .collect();
+        assert_eq!(targets(&code, 0x1000), vec![None, None, None, None]);
+    }
+
+    #[test]
+    fn stops_at_invalid_instruction() {
+        let insns = disassemble(&[0xff; 4], 0x1000, 4).unwrap_or_default();
+        assert!(insns.len() <= 1);
+    }
+}
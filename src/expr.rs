@@ -0,0 +1,465 @@
+// Integer expressions of breakpoint conditions and the commands taking values,
+// e.g) '$rax == 0x10 && *(u32*)($rsp+8) > counter'
+//
+// Values are 64-bit, compared and divided as signed integers.
+// Symbols evaluate to their addresses, dereference them to read the memory.
+// '$name' is a register or a convenience variable, see Session::variable.
+
+use colored::*;
+
+use crate::session::Session;
+use crate::arch::{Regs, RegisterFile};
+
+// Integer type of casts and dereferences, e.g) 'u32' of '(u32*)'
+#[derive(Clone, Copy)]
+struct Type {
+    size: usize,
+    signed: bool,
+}
+
+impl Type {
+    fn parse(name: &str) -> Option<Type> {
+        let (signed, bits) = match name.split_at(1) {
+            ("u", bits) => (false, bits),
+            ("i", bits) => (true, bits),
+            _ => return None,
+        };
+        match bits {
+            "8" | "16" | "32" | "64" => Some(Type { size: bits.parse::<usize>().ok()? / 8, signed }),
+            _ => None,
+        }
+    }
+
+    // Truncates @value to the type, the signed ones are sign-extended back to 64 bits
+    fn convert(self, value: u64) -> u64 {
+        let shift = 64 - self.size * 8;
+        match self.signed {
+            true => (((value << shift) as i64) >> shift) as u64,
+            false => (value << shift) >> shift,
+        }
+    }
+}
+
+enum Node {
+    Number(u64),
+    Variable(String),
+    Symbol(String),
+    Cast(Type, Box<Node>),
+    Deref(Type, Box<Node>),
+    Unary(&'static str, Box<Node>),
+    Binary(&'static str, Box<Node>, Box<Node>),
+}
+
+enum Token {
+    Number(u64),
+    Variable(String),
+    Ident(String),
+    Punct(&'static str),
+}
+
+// Longer ones first so that '<<' isn't taken as two '<'
+const PUNCTS: &[&str] = &[
+    "<<", ">>", "<=", ">=", "==", "!=", "&&", "||",
+    "+", "-", "*", "/", "%", "&", "|", "^", "!", "~", "<", ">", "(", ")",
+];
+
+// Binding power of the binary operators, the higher binds tighter
+fn precedence(op: &str) -> Option<u8> {
+    match op {
+        "||" => Some(1),
+        "&&" => Some(2),
+        "|" => Some(3),
+        "^" => Some(4),
+        "&" => Some(5),
+        "==" | "!=" => Some(6),
+        "<" | "<=" | ">" | ">=" => Some(7),
+        "<<" | ">>" => Some(8),
+        "+" | "-" => Some(9),
+        "*" | "/" | "%" => Some(10),
+        _ => None,
+    }
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '.' || c == '@'
+}
+
+// Hexadecimal(0x), octal(0o or 0), binary(0b) or decimal literal
+fn parse_number(s: &str) -> Option<u64> {
+    let (digits, radix) = match s.get(..2) {
+        Some("0x") | Some("0X") => (&s[2..], 16),
+        Some("0o") | Some("0O") => (&s[2..], 8),
+        Some("0b") | Some("0B") => (&s[2..], 2),
+        _ if s.len() > 1 && s.starts_with('0') => (&s[1..], 8),
+        _ => (s, 10),
+    };
+    u64::from_str_radix(digits, radix).ok()
+}
+
+// Splits @text into tokens with their offsets for the error messages
+fn tokenize(text: &str) -> Result<Vec<(usize, Token)>, ()> {
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+    while pos < text.len() {
+        let rest = &text[pos..];
+        let c = rest.chars().next().unwrap_or(' ');
+        if c.is_whitespace() {
+            pos += c.len_utf8();
+            continue;
+        }
+
+        let (len, token) = if c.is_ascii_digit() {
+            let len = rest.find(|c: char| !is_ident_char(c)).unwrap_or(rest.len());
+            match parse_number(&rest[..len]) {
+                Some(number) => (len, Token::Number(number)),
+                None => return syntax_error(text, pos),
+            }
+        } else if c == '$' || is_ident_char(c) {
+            let skip = if c == '$' { 1 } else { 0 };
+            let len = rest[skip..].find(|c: char| !is_ident_char(c)).map_or(rest.len(), |len| len + skip);
+            match c {
+                '$' if len == 1 => return syntax_error(text, pos),
+                '$' => (len, Token::Variable(rest[1..len].to_string())),
+                _ => (len, Token::Ident(rest[..len].to_string())),
+            }
+        } else {
+            match PUNCTS.iter().find(|punct| rest.starts_with(*punct)) {
+                Some(punct) => (punct.len(), Token::Punct(punct)),
+                None => return syntax_error(text, pos),
+            }
+        };
+        tokens.push((pos, token));
+        pos += len;
+    }
+    Ok(tokens)
+}
+
+fn syntax_error<T>(text: &str, pos: usize) -> Result<T, ()> {
+    match text.get(pos..).filter(|rest| !rest.is_empty()) {
+        Some(rest) => println!("{}'{}'", "Syntax error in expression, near ".red(), rest),
+        None => println!("{}", "Syntax error in expression, unexpected end".red()),
+    }
+    Err(())
+}
+
+// Recursive descent parser over the tokens
+struct Parser<'a> {
+    text: &'a str,
+    tokens: Vec<(usize, Token)>,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(_, token)| token)
+    }
+
+    fn peek_punct(&self, n: usize) -> Option<&'static str> {
+        match self.tokens.get(self.pos + n) {
+            Some((_, Token::Punct(punct))) => Some(punct),
+            _ => None,
+        }
+    }
+
+    fn error<T>(&self) -> Result<T, ()> {
+        let offset = self.tokens.get(self.pos).map_or(self.text.len(), |(offset, _)| *offset);
+        syntax_error(self.text, offset)
+    }
+
+    fn expect(&mut self, punct: &str) -> Result<(), ()> {
+        match self.peek_punct(0) {
+            Some(p) if p == punct => {
+                self.pos += 1;
+                Ok(())
+            },
+            _ => self.error(),
+        }
+    }
+
+    // Binary operators binding tighter than @min
+    fn binary(&mut self, min: u8) -> Result<Node, ()> {
+        let mut lhs = self.unary()?;
+        while let Some(op) = self.peek_punct(0) {
+            let prec = match precedence(op) {
+                Some(prec) if prec > min => prec,
+                _ => break,
+            };
+            self.pos += 1;
+            let rhs = self.binary(prec)?;
+            lhs = Node::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    // '(TYPE)' or '(TYPE*)' at the current position as (type, pointer, tokens)
+    fn cast(&self) -> Option<(Type, bool, usize)> {
+        if self.peek_punct(0) != Some("(") {
+            return None;
+        }
+        let ty = match self.tokens.get(self.pos + 1) {
+            Some((_, Token::Ident(name))) => Type::parse(name)?,
+            _ => return None,
+        };
+        match (self.peek_punct(2), self.peek_punct(3)) {
+            (Some(")"), _) => Some((ty, false, 3)),
+            (Some("*"), Some(")")) => Some((ty, true, 4)),
+            _ => None,
+        }
+    }
+
+    fn unary(&mut self) -> Result<Node, ()> {
+        match self.peek_punct(0) {
+            Some(op) if op == "-" || op == "!" || op == "~" => {
+                self.pos += 1;
+                Ok(Node::Unary(op, Box::new(self.unary()?)))
+            },
+            // 8 bytes unless a pointer cast says the size, e.g) '*(u32*)$rsp'
+            Some("*") => {
+                self.pos += 1;
+                match self.cast() {
+                    Some((ty, true, len)) => {
+                        self.pos += len;
+                        Ok(Node::Deref(ty, Box::new(self.unary()?)))
+                    },
+                    _ => Ok(Node::Deref(Type { size: 8, signed: false }, Box::new(self.unary()?))),
+                }
+            },
+            // pointers are 64-bit whatever they point to
+            Some("(") => match self.cast() {
+                Some((ty, pointer, len)) => {
+                    self.pos += len;
+                    let ty = if pointer { Type { size: 8, signed: false } } else { ty };
+                    Ok(Node::Cast(ty, Box::new(self.unary()?)))
+                },
+                None => self.primary(),
+            },
+            _ => self.primary(),
+        }
+    }
+
+    fn primary(&mut self) -> Result<Node, ()> {
+        let node = match self.peek() {
+            Some(Token::Number(number)) => Node::Number(*number),
+            Some(Token::Variable(name)) => Node::Variable(name.clone()),
+            Some(Token::Ident(name)) => Node::Symbol(name.clone()),
+            Some(Token::Punct("(")) => {
+                self.pos += 1;
+                let node = self.binary(0)?;
+                self.expect(")")?;
+                return Ok(node);
+            },
+            _ => return self.error(),
+        };
+        self.pos += 1;
+        Ok(node)
+    }
+}
+
+pub struct Expr {
+    text: String,
+    root: Node,
+}
+
+impl Expr {
+    // Prints what's wrong with @text on failure
+    pub fn parse(text: &str) -> Result<Expr, ()> {
+        let tokens = tokenize(text)?;
+        let mut parser = Parser { text, tokens, pos: 0 };
+        let root = parser.binary(0)?;
+        if parser.pos < parser.tokens.len() {
+            return parser.error();
+        }
+        Ok(Expr { text: text.to_string(), root })
+    }
+
+    // Expression as the user wrote it
+    pub fn text(&self) -> &str { &self.text }
+
+    // Evaluates with the registers of the thread @tid, prints what's wrong on failure
+    pub fn eval(&self, session: &Session, tid: i32) -> Result<u64, ()> {
+        eval(&self.root, session, tid)
+    }
+}
+
+fn eval(node: &Node, session: &Session, tid: i32) -> Result<u64, ()> {
+    match node {
+        Node::Number(number) => Ok(*number),
+        Node::Variable(name) => variable(session, name, tid),
+        Node::Symbol(name) => session.symbols.lookup(name).ok_or_else(|| {
+            println!("{}'{}'", "No symbol in current context: ".red(), name);
+        }),
+        Node::Cast(ty, inner) => Ok(ty.convert(eval(inner, session, tid)?)),
+        Node::Deref(ty, inner) => {
+            let addr = eval(inner, session, tid)?;
+            let bytes = match session.has_target() {
+                true => session.target().read_memory(addr, ty.size).ok(),
+                false => None,
+            };
+            let bytes = bytes.ok_or_else(|| {
+                println!("{}{:#x}", "Cannot access memory at address ".red(), addr);
+            })?;
+            let mut raw = [0u8; 8];
+            raw[..ty.size].copy_from_slice(&bytes);
+            Ok(ty.convert(u64::from_le_bytes(raw)))
+        },
+        Node::Unary(op, inner) => {
+            let value = eval(inner, session, tid)?;
+            match *op {
+                "-" => Ok(value.wrapping_neg()),
+                "!" => Ok((value == 0) as u64),
+                _ => Ok(!value),
+            }
+        },
+        // evaluated only as far as needed, e.g) '$rdi != 0 && *(u8*)$rdi == 0x41'
+        Node::Binary("&&", lhs, rhs) => {
+            Ok((eval(lhs, session, tid)? != 0 && eval(rhs, session, tid)? != 0) as u64)
+        },
+        Node::Binary("||", lhs, rhs) => {
+            Ok((eval(lhs, session, tid)? != 0 || eval(rhs, session, tid)? != 0) as u64)
+        },
+        Node::Binary(op, lhs, rhs) => {
+            let (a, b) = (eval(lhs, session, tid)?, eval(rhs, session, tid)?);
+            binary(op, a, b)
+        },
+    }
+}
+
+// Register of @tid, the value given by the debugger such as '$base' or the one set by the user
+fn variable(session: &Session, name: &str, tid: i32) -> Result<u64, ()> {
+    if Regs::find(name).is_none() {
+        return session.variable(name).ok_or_else(|| {
+            println!("{}${}", "No register or convenience variable: ".red(), name);
+        });
+    }
+    match session.has_target() {
+        true => session.target().thread_regs(tid)?.get(name).ok_or(()),
+        false => {
+            println!("{}", "No registers, no process has been attached".red());
+            Err(())
+        },
+    }
+}
+
+fn binary(op: &str, a: u64, b: u64) -> Result<u64, ()> {
+    let (sa, sb) = (a as i64, b as i64);
+    let value = match op {
+        "+" => a.wrapping_add(b),
+        "-" => a.wrapping_sub(b),
+        "*" => a.wrapping_mul(b),
+        "/" | "%" if b == 0 => {
+            println!("{}", "Division by zero".red());
+            return Err(());
+        },
+        "/" => sa.wrapping_div(sb) as u64,
+        "%" => sa.wrapping_rem(sb) as u64,
+        "<<" | ">>" if b >= 64 => {
+            println!("{}{}", "Shift count out of range: ".red(), sb);
+            return Err(());
+        },
+        "<<" => a << b,
+        ">>" => a >> b,
+        "&" => a & b,
+        "|" => a | b,
+        "^" => a ^ b,
+        "==" => (a == b) as u64,
+        "!=" => (a != b) as u64,
+        "<" => (sa < sb) as u64,
+        "<=" => (sa <= sb) as u64,
+        ">" => (sa > sb) as u64,
+        _ => (sa >= sb) as u64,
+    };
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval_with(session: &Session, text: &str) -> Result<u64, ()> {
+        Expr::parse(text)?.eval(session, 0)
+    }
+
+    fn eval_text(text: &str) -> Result<u64, ()> {
+        eval_with(&Session::new(), text)
+    }
+
+    #[test]
+    fn numbers_in_every_radix() {
+        assert_eq!(parse_number("42"), Some(42));
+        assert_eq!(parse_number("0x2a"), Some(42));
+        assert_eq!(parse_number("0o52"), Some(42));
+        assert_eq!(parse_number("052"), Some(42));
+        assert_eq!(parse_number("0b101010"), Some(42));
+        assert_eq!(parse_number("0"), Some(0));
+        assert_eq!(parse_number("0x"), None);
+        assert_eq!(parse_number("09"), None);
+    }
+
+    #[test]
+    fn precedence_follows_c() {
+        assert_eq!(eval_text("1+2*3"), Ok(7));
+        assert_eq!(eval_text("(1+2)*3"), Ok(9));
+        assert_eq!(eval_text("1<<2+1"), Ok(8));
+        assert_eq!(eval_text("1|2&3"), Ok(3));
+        assert_eq!(eval_text("6^3&1"), Ok(7));
+        assert_eq!(eval_text("1 == 1 && 2 < 1 || 3 >= 3"), Ok(1));
+        assert_eq!(eval_text("10-4-3"), Ok(3));
+    }
+
+    #[test]
+    fn unary_operators_and_casts() {
+        assert_eq!(eval_text("-1"), Ok(u64::MAX));
+        assert_eq!(eval_text("!0"), Ok(1));
+        assert_eq!(eval_text("!5"), Ok(0));
+        assert_eq!(eval_text("~0"), Ok(u64::MAX));
+        assert_eq!(eval_text("-(2*3)"), Ok(6u64.wrapping_neg()));
+        assert_eq!(eval_text("(u8)0x1ff"), Ok(0xff));
+        assert_eq!(eval_text("(i8)0xff"), Ok(u64::MAX));
+        assert_eq!(eval_text("(u16)-1"), Ok(0xffff));
+    }
+
+    #[test]
+    fn signed_compare_and_division() {
+        assert_eq!(eval_text("-1 < 0"), Ok(1));
+        assert_eq!(eval_text("-7 / 2"), Ok((-3i64) as u64));
+        assert_eq!(eval_text("-7 % 2"), Ok((-1i64) as u64));
+        assert_eq!(eval_text("1 << 63 >> 63"), Ok(1));
+    }
+
+    #[test]
+    fn arithmetic_errors() {
+        assert!(eval_text("1/0").is_err());
+        assert!(eval_text("1%0").is_err());
+        assert!(eval_text("1<<64").is_err());
+        assert!(eval_text("1>>64").is_err());
+        assert!(eval_text("1<<-1").is_err());
+    }
+
+    #[test]
+    fn syntax_errors() {
+        for text in &["", "1+", "(1", "1)", "$", "1 2", "(u8*)", "(u7)1", "0x"] {
+            assert!(Expr::parse(text).is_err(), "{}", text);
+        }
+        assert_eq!(Expr::parse(" 1 + 2 ").map(|expr| expr.text().to_string()), Ok(" 1 + 2 ".to_string()));
+    }
+
+    #[test]
+    fn registers_and_memory_need_a_target() {
+        assert!(Expr::parse("$rax").is_ok());
+        assert!(eval_text("$pc").is_err());
+        assert!(eval_text("*(u32*)0x1000").is_err());
+        assert!(eval_text("*0x1000").is_err());
+        assert!(eval_text("main").is_err());
+    }
+
+    #[test]
+    fn convenience_variables() {
+        let mut session = Session::new();
+        session.variables.insert("count".to_string(), 3);
+        assert_eq!(eval_with(&session, "$count * 2"), Ok(6));
+        assert!(eval_with(&session, "$missing").is_err());
+        // short-circuit skips the failing side
+        assert_eq!(eval_with(&session, "$count || $missing"), Ok(1));
+        assert_eq!(eval_with(&session, "0 && *0"), Ok(0));
+    }
+}
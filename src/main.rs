@@ -10,9 +10,20 @@ use clap::{App, Arg, ArgMatches};
 mod traits;
 
 mod session;
+mod breakpoint;
+mod watchpoint;
+mod track;
+mod scan;
+mod coredump;
+mod expr;
+mod symbol;
+mod disas;
+mod signal;
+mod syscall;
 mod cli;
 mod process;
 mod ptrace;
+mod arch;
 
 enum PlatformChecks {
     UnsupportedOS,
@@ -24,12 +35,11 @@ fn preprocess_arg_parser(session: &mut session::Session, parser: &ArgMatches) {
     if let Some(arg_pid) = parser.value_of("pid") {
         if let Ok(pid) = i32::from_str(arg_pid) {
             if ptrace::attach_wait(pid).is_ok() {
-                session.set_target(pid as i32).unwrap_or(0);
+                session.set_target(pid).unwrap_or(0);
 
                 // print current pc
-                let pc = session.proc.getreg("pc").unwrap_or_default();
+                let pc = session.target().getreg("pc").unwrap_or_default();
                 println!("Successfully attached to pid: {}", pid);
-                println!("Stopped at: pc={:#x}", pc);
                 
                 // set elf with '/proc/{PID}/exe'
                 match session.set_elf(session.get_exe().to_path_buf()) {
@@ -40,12 +50,26 @@ fn preprocess_arg_parser(session: &mut session::Session, parser: &ArgMatches) {
                         println!("  err : {:?}", e);
                     },
                 }
+                println!("Stopped at: pc={}", session.format_addr(pc));
             }
         }
     }
 
+    // --args <PATH> [ARGS]...
+    let file = match parser.values_of("args") {
+        Some(mut values) => {
+            let file = values.next();
+            let args: Vec<String> = values.map(String::from).collect();
+            if session.launch.set_args(&args).is_err() {
+                println!("Invalid arguments: '{}'", args.join(" "));
+            }
+            file
+        },
+        None => parser.value_of("file"),
+    };
+
     // -f, --file <PATH>
-    if let Some(file_str) = parser.value_of("file") {
+    if let Some(file_str) = file {
         match session.set_elf(PathBuf::from(file_str)) {
             Ok(_) => {
                 println!("Path to file is available: '{}'", file_str);
@@ -58,6 +82,11 @@ fn preprocess_arg_parser(session: &mut session::Session, parser: &ArgMatches) {
             },
         }
     }
+
+    // --core <PATH>, after the file so that its symbols are used
+    if let Some(core_str) = parser.value_of("core") {
+        cli::command::core(session, Some(core_str));
+    }
 }
 
 fn platform_checks() -> Result<(), PlatformChecks> {
@@ -85,7 +114,7 @@ fn enter_cli(session: &mut session::Session) {
 
     // Commandline prerequisites for rustyline
     let mut reader = rustyline::Editor::<()>::new();
-    let shell = String::from("rsdb ~> ".bright_blue().to_string());
+    let shell = "rsdb ~> ".bright_blue().to_string();
 
     // Main commandline loop
     loop {
@@ -127,6 +156,18 @@ fn main() -> Result<(), i32> {
                         .required(false)
                         .conflicts_with("pid")
                 )
+                .arg(
+                    Arg::from_usage(concat!("--args <PATH>... 'Spawn a specific executable with ",
+                                            "the arguments following it'"))
+                        .required(false)
+                        .allow_hyphen_values(true)
+                        .conflicts_with_all(&["pid", "file"])
+                )
+                .arg(
+                    Arg::from_usage("--core <PATH> 'Inspect an ELF core file of the executable'")
+                        .required(false)
+                        .conflicts_with("pid")
+                )
             .get_matches();
 
     match platform_checks() {
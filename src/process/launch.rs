@@ -0,0 +1,359 @@
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::path::*;
+use std::process::{Command, Stdio};
+
+// Longer ones first, '2>>' must not be taken as '2>'
+const REDIRECT_OPERATORS: &[&str] = &["2>>", "2>", ">>", ">", "<"];
+
+// Redirection of a standard stream of the spawned process
+#[derive(Clone)]
+pub enum Redirect {
+    // '< PATH'
+    Input(PathBuf),
+
+    // '> PATH' or '>> PATH' if append
+    Output(PathBuf, bool),
+}
+
+impl Redirect {
+    fn open(&self) -> std::io::Result<File> {
+        match self {
+            Redirect::Input(path) => File::open(path),
+            Redirect::Output(path, append) => {
+                OpenOptions::new()
+                    .write(true)
+                    .create(true)
+                    .append(*append)
+                    .truncate(!*append)
+                    .open(path)
+            },
+        }
+    }
+}
+
+// Word of the command line, redirections are recognized only in the part typed bare
+struct Word {
+    text: String,
+
+    // bytes at the start of @text typed without quotes or backslashes
+    bare: usize,
+}
+
+// Splits @line into words as a shell does: '...' is taken as it is, "..." takes \" and \\
+// and a backslash outside of quotes escapes the next character. Fails on an open quote.
+fn split_words(line: &str) -> Result<Vec<Word>, ()> {
+    let mut words = Vec::new();
+    let mut chars = line.chars().peekable();
+    loop {
+        while chars.peek().is_some_and(|c| c.is_whitespace()) {
+            chars.next();
+        }
+        if chars.peek().is_none() {
+            return Ok(words);
+        }
+
+        let (mut text, mut bare) = (String::new(), None);
+        while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+            match c {
+                '\'' | '"' => {
+                    bare.get_or_insert(text.len());
+                    loop {
+                        match chars.next() {
+                            Some(end) if end == c => break,
+                            Some('\\') if c == '"' => match chars.next() {
+                                Some(escaped @ ('"' | '\\')) => text.push(escaped),
+                                Some(other) => {
+                                    text.push('\\');
+                                    text.push(other);
+                                },
+                                None => (),
+                            },
+                            Some(other) => text.push(other),
+                            None => {
+                                println!("Unterminated quote: {}", line);
+                                return Err(());
+                            },
+                        }
+                    }
+                },
+                '\\' => {
+                    bare.get_or_insert(text.len());
+                    text.push(chars.next().unwrap_or('\\'));
+                },
+                c => text.push(c),
+            }
+        }
+        words.push(Word { bare: bare.unwrap_or(text.len()), text });
+    }
+}
+
+// Quotes @arg so that 'show args' reads back the same, if a shell would split or redirect it
+fn quote(arg: &str) -> String {
+    let plain = !arg.is_empty() &&
+        !arg.contains(|c: char| c.is_whitespace() || "'\"\\".contains(c)) &&
+        !REDIRECT_OPERATORS.iter().any(|op| arg.starts_with(op));
+    match plain {
+        true => arg.to_string(),
+        false => format!("'{}'", arg.replace('\'', "'\\''")),
+    }
+}
+
+impl std::fmt::Display for Redirect {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Redirect::Input(path) => write!(f, "< {}", path.display()),
+            Redirect::Output(path, false) => write!(f, "> {}", path.display()),
+            Redirect::Output(path, true) => write!(f, ">> {}", path.display()),
+        }
+    }
+}
+
+// How 'run' spawns the program, kept across runs like gdb does
+pub struct LaunchConfig {
+    pub args: Vec<String>,
+
+    // Variables set(Some) or unset(None) on top of the rsdb's environment
+    pub env: BTreeMap<String, Option<String>>,
+
+    // Starts from the empty environment instead, set by 'unset env'
+    pub clear_env: bool,
+
+    // Working directory, rsdb's one if None
+    pub cwd: Option<PathBuf>,
+
+    pub stdin: Option<Redirect>,
+    pub stdout: Option<Redirect>,
+    pub stderr: Option<Redirect>,
+
+    // Spawns the program with ASLR disabled, on by default
+    pub disable_randomization: bool,
+}
+
+impl LaunchConfig {
+    pub fn new() -> Self {
+        LaunchConfig {
+            args: Vec::new(),
+            env: BTreeMap::new(),
+            clear_env: false,
+            cwd: None,
+            stdin: None,
+            stdout: None,
+            stderr: None,
+            disable_randomization: true,
+        }
+    }
+
+    // Takes the arguments split already such as the ones of '--args'
+    pub fn set_args(&mut self, args: &[String]) -> Result<(), ()> {
+        let words = args.iter()
+            .map(|arg| Word { text: arg.clone(), bare: arg.len() })
+            .collect();
+        self.set_words(words)
+    }
+
+    // Parses the line typed after 'run' or 'set args', quoted as a shell does
+    pub fn parse_args(&mut self, line: &str) -> Result<(), ()> {
+        self.set_words(split_words(line)?)
+    }
+
+    // Shell style redirections are taken out of the arguments:
+    // '< IN', '> OUT', '>> OUT', '2> ERR', '2>> ERR'.
+    fn set_words(&mut self, words: Vec<Word>) -> Result<(), ()> {
+        let mut plain = Vec::new();
+        let (mut stdin, mut stdout, mut stderr) = (None, None, None);
+        let mut iter = words.into_iter();
+        while let Some(word) = iter.next() {
+            let op = match REDIRECT_OPERATORS.iter().find(|op| word.text[..word.bare].starts_with(*op)) {
+                Some(op) => *op,
+                None => {
+                    plain.push(word.text);
+                    continue;
+                },
+            };
+            let path = match &word.text[op.len()..] {
+                "" => match iter.next() {
+                    Some(path) => path.text,
+                    None => {
+                        println!("Missing file name after '{}'", op);
+                        return Err(());
+                    },
+                },
+                path => path.to_string(),
+            };
+            let path = PathBuf::from(path);
+            match op {
+                "<" => stdin = Some(Redirect::Input(path)),
+                "2>" | "2>>" => stderr = Some(Redirect::Output(path, op.ends_with(">>"))),
+                _ => stdout = Some(Redirect::Output(path, op.ends_with(">>"))),
+            }
+        }
+
+        self.args = plain;
+        self.stdin = stdin;
+        self.stdout = stdout;
+        self.stderr = stderr;
+        Ok(())
+    }
+
+    // 'arg1 'arg 2' < in.txt > out.txt'
+    pub fn args_string(&self) -> String {
+        let redirects = [&self.stdin, &self.stdout, &self.stderr];
+        let mut list: Vec<String> = self.args.iter().map(|arg| quote(arg)).collect();
+        for (fd, redirect) in redirects.iter().enumerate() {
+            let (op, path) = match redirect {
+                Some(Redirect::Input(path)) => ("<", path),
+                Some(Redirect::Output(path, false)) => (">", path),
+                Some(Redirect::Output(path, true)) => (">>", path),
+                None => continue,
+            };
+            let prefix = if fd == 2 { "2" } else { "" };
+            list.push(format!("{}{} {}", prefix, op, quote(&path.to_string_lossy())));
+        }
+        list.join(" ")
+    }
+
+    // Command to execute @file with, the working directory is checked and
+    // redirection files are opened here so that failures are reported before forking.
+    pub fn command(&self, file: &Path) -> Result<Command, ()> {
+        let file = file.canonicalize().map_err(|e| {
+            println!("Cannot find '{}': {}", file.display(), e);
+        })?;
+        let mut cmd = Command::new(file);
+        cmd.args(&self.args);
+        if self.clear_env {
+            cmd.env_clear();
+        }
+        for (name, value) in &self.env {
+            match value {
+                Some(value) => cmd.env(name, value),
+                None => cmd.env_remove(name),
+            };
+        }
+        if let Some(cwd) = &self.cwd {
+            if !cwd.is_dir() {
+                println!("Cannot change the working directory to '{}': not a directory", cwd.display());
+                return Err(());
+            }
+            cmd.current_dir(cwd);
+        }
+
+        let open = |redirect: &Redirect| {
+            redirect.open()
+                .map(Stdio::from)
+                .map_err(|e| println!("Cannot open '{}': {}", redirect, e))
+        };
+        if let Some(redirect) = &self.stdin {
+            cmd.stdin(open(redirect)?);
+        }
+        if let Some(redirect) = &self.stdout {
+            cmd.stdout(open(redirect)?);
+        }
+        if let Some(redirect) = &self.stderr {
+            cmd.stderr(open(redirect)?);
+        }
+        Ok(cmd)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(line: &str) -> Vec<String> {
+        split_words(line).unwrap().into_iter().map(|word| word.text).collect()
+    }
+
+    fn parsed(line: &str) -> LaunchConfig {
+        let mut config = LaunchConfig::new();
+        config.parse_args(line).unwrap();
+        config
+    }
+
+    fn path_of(redirect: &Option<Redirect>) -> Option<(String, bool)> {
+        match redirect {
+            Some(Redirect::Input(path)) => Some((path.display().to_string(), false)),
+            Some(Redirect::Output(path, append)) => Some((path.display().to_string(), *append)),
+            None => None,
+        }
+    }
+
+    #[test]
+    fn splits_on_whitespace() {
+        assert_eq!(words("  a  b\tc "), vec!["a", "b", "c"]);
+        assert!(words("   ").is_empty());
+    }
+
+    #[test]
+    fn quotes_keep_spaces() {
+        assert_eq!(words(r#""hello world" 'a  b' c"d e"f"#), vec!["hello world", "a  b", "cd ef"]);
+        assert_eq!(words(r#""" ''"#), vec!["", ""]);
+        assert_eq!(words(r#"'it'\''s' "say \"hi\"" a\ b \$x"#), vec!["it's", r#"say "hi""#, "a b", "$x"]);
+        assert_eq!(words(r#"'no \escape' "keep \n""#), vec![r"no \escape", r"keep \n"]);
+    }
+
+    #[test]
+    fn open_quote_fails() {
+        assert!(split_words("'abc").is_err());
+        assert!(split_words(r#""abc\"#).is_err());
+    }
+
+    #[test]
+    fn redirections_are_taken_out() {
+        let config = parsed("-v input.bin < in.txt >out.txt 2>> err.txt");
+        assert_eq!(config.args, vec!["-v", "input.bin"]);
+        assert_eq!(path_of(&config.stdin), Some(("in.txt".to_string(), false)));
+        assert_eq!(path_of(&config.stdout), Some(("out.txt".to_string(), false)));
+        assert_eq!(path_of(&config.stderr), Some(("err.txt".to_string(), true)));
+
+        let config = parsed(">> log 2> 'my errors'");
+        assert_eq!(path_of(&config.stdout), Some(("log".to_string(), true)));
+        assert_eq!(path_of(&config.stderr), Some(("my errors".to_string(), false)));
+    }
+
+    #[test]
+    fn quoted_operators_are_arguments() {
+        let config = parsed(r#"'<' ">" \>x >"out file""#);
+        assert_eq!(config.args, vec!["<", ">", ">x"]);
+        assert!(config.stdin.is_none());
+        assert_eq!(path_of(&config.stdout), Some(("out file".to_string(), false)));
+    }
+
+    #[test]
+    fn missing_file_name_fails() {
+        let mut config = LaunchConfig::new();
+        assert!(config.parse_args("a >").is_err());
+        assert!(config.set_args(&["<".to_string()]).is_err());
+    }
+
+    #[test]
+    fn set_args_takes_words_as_they_are() {
+        let mut config = LaunchConfig::new();
+        config.set_args(&["hello world".to_string(), "'q'".to_string(), ">".to_string(), "out".to_string()]).unwrap();
+        assert_eq!(config.args, vec!["hello world", "'q'"]);
+        assert_eq!(path_of(&config.stdout), Some(("out".to_string(), false)));
+    }
+
+    #[test]
+    fn args_string_reads_back() {
+        let config = parsed(r#""hello world" it\'s '' plain '>' < 'in file'"#);
+        let shown = config.args_string();
+        assert_eq!(shown, r#"'hello world' 'it'\''s' '' plain '>' < 'in file'"#);
+        let again = parsed(&shown);
+        assert_eq!(again.args, config.args);
+        assert_eq!(path_of(&again.stdin), path_of(&config.stdin));
+    }
+
+    #[test]
+    fn command_checks_the_working_directory() {
+        let exe = std::env::current_exe().unwrap();
+        let mut config = LaunchConfig::new();
+        config.cwd = Some(PathBuf::from("/nonexistent/rsdb"));
+        assert!(config.command(&exe).is_err());
+        config.cwd = Some(exe.clone());
+        assert!(config.command(&exe).is_err());
+        config.cwd = Some(std::env::temp_dir());
+        assert!(config.command(&exe).is_ok());
+        assert!(config.command(Path::new("/nonexistent/rsdb")).is_err());
+    }
+}
@@ -0,0 +1,211 @@
+use std::path::Path;
+
+use super::procfs;
+
+// One line of '/proc/{PID}/maps'
+pub struct MemoryRegion {
+    pub start: u64,
+    pub end: u64,
+    pub read: bool,
+    pub write: bool,
+    pub exec: bool,
+    pub shared: bool,
+    pub offset: u64,
+    pub dev: String,
+    pub inode: u64,
+
+    // file mapped or pseudo path such as '[heap]', empty for anonymous mappings
+    pub path: String,
+}
+
+impl MemoryRegion {
+    // e.g) '00400000-00401000 r-xp 00000000 08:01 1234   /tmp/a.out'
+    fn parse(line: &str) -> Option<Self> {
+        // the path may contain spaces, it's the rest of the line after the padding
+        let mut fields = line.splitn(6, char::is_whitespace);
+        let (start, end) = fields.next()?.split_once('-')?;
+        let perms = fields.next()?.as_bytes();
+        let offset = fields.next()?;
+        let dev = fields.next()?;
+        let inode = fields.next()?;
+        let path = fields.next().unwrap_or("").trim();
+
+        if perms.len() != 4 {
+            return None;
+        }
+        Some(MemoryRegion {
+            start: u64::from_str_radix(start, 16).ok()?,
+            end: u64::from_str_radix(end, 16).ok()?,
+            read: perms[0] == b'r',
+            write: perms[1] == b'w',
+            exec: perms[2] == b'x',
+            shared: perms[3] == b's',
+            offset: u64::from_str_radix(offset, 16).ok()?,
+            dev: dev.to_string(),
+            inode: inode.parse::<u64>().ok()?,
+            path: path.to_string(),
+        })
+    }
+
+    pub fn size(&self) -> u64 { self.end - self.start }
+
+    pub fn contains(&self, addr: u64) -> bool {
+        self.start <= addr && addr < self.end
+    }
+
+    // 'r-xp' as the maps shows
+    pub fn perms(&self) -> String {
+        let flag = |set: bool, c: char| if set { c } else { '-' };
+        [flag(self.read, 'r'), flag(self.write, 'w'), flag(self.exec, 'x'),
+         if self.shared { 's' } else { 'p' }].iter().collect()
+    }
+
+    // Last component of the path, e.g) 'libc.so.6', the pseudo paths as they are
+    pub fn name(&self) -> &str {
+        match self.path.starts_with('/') {
+            true => self.path.rsplit('/').next().unwrap_or(&self.path),
+            false => &self.path,
+        }
+    }
+
+    // Whether it's the shared library @name, e.g) 'libc' for libc.so.6 or libc-2.31.so
+    fn is_library(&self, name: &str) -> bool {
+        let file = self.name();
+        self.path.starts_with('/') &&
+            (file.starts_with(&format!("{}.so", name)) || file.starts_with(&format!("{}-", name)))
+    }
+
+    // Whether @pattern matches, which is either the permissions like 'rwx' or 'rw'
+    // the region has at least, or a part of the path
+    pub fn matches(&self, pattern: &str) -> bool {
+        if !pattern.is_empty() && pattern.chars().all(|c| "rwx".contains(c)) {
+            return pattern.chars().all(|c| match c {
+                'r' => self.read,
+                'w' => self.write,
+                _ => self.exec,
+            });
+        }
+        self.path.contains(pattern)
+    }
+}
+
+// Memory regions of the process sorted by address
+pub struct MemoryMap {
+    regions: Vec<MemoryRegion>,
+}
+
+impl MemoryMap {
+    pub fn new() -> Self {
+        MemoryMap { regions: Vec::new() }
+    }
+
+    // @regions of a core file, sorted here
+    pub fn from_regions(mut regions: Vec<MemoryRegion>) -> Self {
+        regions.sort_by_key(|region| region.start);
+        MemoryMap { regions }
+    }
+
+    pub fn read(target: i32) -> Result<Self, ()> {
+        let maps = procfs::get_proc_maps(target)?;
+        Ok(MemoryMap { regions: maps.lines().filter_map(MemoryRegion::parse).collect() })
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, MemoryRegion> {
+        self.regions.iter()
+    }
+
+    // Region containing @addr
+    pub fn find(&self, addr: u64) -> Option<&MemoryRegion> {
+        let index = self.regions.partition_point(|region| region.end <= addr);
+        self.regions.get(index).filter(|region| region.contains(addr))
+    }
+
+    // Lowest address @path is mapped at, which is the load address of the ELF
+    pub fn load_base(&self, path: &Path) -> Option<u64> {
+        let path = path.to_str()?;
+        self.regions.iter()
+            .find(|region| region.path == path)
+            .map(|region| region.start)
+    }
+
+    // Load address of the shared library @name, e.g) 'libc'
+    pub fn library_base(&self, name: &str) -> Option<u64> {
+        self.regions.iter()
+            .find(|region| region.is_library(name))
+            .map(|region| region.start)
+    }
+
+    // Load address of the file mapped at @region, for the offsets in the file.
+    // The file is told by the device and inode, anonymous mappings have inode 0.
+    pub fn base_of(&self, region: &MemoryRegion) -> u64 {
+        self.regions.iter()
+            .find(|other| region.inode != 0 && other.dev == region.dev && other.inode == region.inode)
+            .map_or(region.start, |other| other.start)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_mapping() {
+        let region = MemoryRegion::parse("00400000-00401000 r-xp 00001000 08:01 1234       /tmp/a.out").unwrap();
+        assert_eq!((region.start, region.end, region.size()), (0x400000, 0x401000, 0x1000));
+        assert_eq!(region.perms(), "r-xp");
+        assert_eq!((region.offset, region.dev.as_str(), region.inode), (0x1000, "08:01", 1234));
+        assert_eq!(region.path, "/tmp/a.out");
+        assert_eq!(region.name(), "a.out");
+    }
+
+    #[test]
+    fn path_keeps_its_spaces() {
+        let region = MemoryRegion::parse("7f00-8f00 rw-s 00000000 00:05 42    /tmp/my  file.bin").unwrap();
+        assert!(region.shared && region.write);
+        assert_eq!(region.path, "/tmp/my  file.bin");
+        assert_eq!(region.name(), "my  file.bin");
+    }
+
+    #[test]
+    fn deleted_file() {
+        let region = MemoryRegion::parse("1000-2000 r--p 00000000 fd:00 99   /tmp/gone (deleted)").unwrap();
+        assert_eq!(region.path, "/tmp/gone (deleted)");
+        assert!(region.matches("gone"));
+    }
+
+    #[test]
+    fn anonymous_mapping() {
+        for line in &["7ffd0000-7ffd1000 rw-p 00000000 00:00 0", "7ffd0000-7ffd1000 rw-p 00000000 00:00 0 "] {
+            let region = MemoryRegion::parse(line).unwrap();
+            assert_eq!(region.path, "");
+            assert_eq!(region.inode, 0);
+        }
+    }
+
+    #[test]
+    fn pseudo_paths() {
+        let region = MemoryRegion::parse("55550000-55570000 rw-p 00000000 00:00 0          [heap]").unwrap();
+        assert_eq!(region.path, "[heap]");
+        assert_eq!(region.name(), "[heap]");
+        let region = MemoryRegion::parse("ffffffffff600000-ffffffffff601000 --xp 00000000 00:00 0  [vsyscall]").unwrap();
+        assert_eq!(region.path, "[vsyscall]");
+        assert!(region.matches("x") && !region.matches("r"));
+    }
+
+    #[test]
+    fn malformed_lines() {
+        assert!(MemoryRegion::parse("").is_none());
+        assert!(MemoryRegion::parse("1000 r-xp 0 08:01 1").is_none());
+        assert!(MemoryRegion::parse("1000-2000 r-x 0 08:01 1").is_none());
+        assert!(MemoryRegion::parse("1000-zz r-xp 0 08:01 1").is_none());
+    }
+
+    #[test]
+    fn libraries_by_name() {
+        let libc = MemoryRegion::parse("7f00-8f00 r-xp 00000000 08:01 7 /usr/lib/libc.so.6").unwrap();
+        let old = MemoryRegion::parse("7f00-8f00 r-xp 00000000 08:01 7 /lib/libc-2.31.so").unwrap();
+        let other = MemoryRegion::parse("7f00-8f00 r-xp 00000000 08:01 7 /lib/libcrypt.so.1").unwrap();
+        assert!(libc.is_library("libc") && old.is_library("libc"));
+        assert!(!other.is_library("libc"));
+    }
+}
@@ -1,33 +1,42 @@
 use std::path::*;
 use std::os::unix::prelude::CommandExt;
-use libc::user_regs_struct;
 use linux_personality::personality;
+use nix::sys::uio::{self, IoVec, RemoteIoVec};
+use nix::sys::wait::{waitpid, WaitPidFlag};
 
 use crate::traits::*;
 use crate::ptrace;
+use crate::arch::{Regs, RegisterFile};
 
 pub mod procfs;
+pub mod launch;
+pub mod thread;
+pub mod maps;
 
 pub type PidType = nix::unistd::Pid;
 
+const WORD_SIZE: u64 = std::mem::size_of::<u64>() as u64;
+
 pub struct Proc {
     pub target: i32,
-    pub file: PathBuf,
     cmdline: String,
     exe: PathBuf,
     cwd: PathBuf,
-    maps: String,
+    maps: maps::MemoryMap,
+
+    // Traced threads including the main thread, 'target' itself
+    pub threads: thread::ThreadList,
 }
 
 impl Proc {
     pub fn new() -> Self {
         Proc { 
             target: -1, 
-            file: PathBuf::new(),
             cmdline: String::from(""), 
             exe: PathBuf::new(), 
             cwd: PathBuf::new(),
-            maps: String::from(""),
+            maps: maps::MemoryMap::new(),
+            threads: thread::ThreadList::new(),
         }
     }
 
@@ -38,10 +47,7 @@ impl Proc {
         }
 
         self.target = pid;
-        self.cmdline = match procfs::get_proc_cmdline(pid) {
-            Ok(cmdline) => cmdline,
-            Err(_) => String::from(""),
-        };
+        self.cmdline = procfs::get_proc_cmdline(pid).unwrap_or_default();
         self.exe = match procfs::get_proc_exe(pid) {
             Ok(exe) => exe,
             Err(_) => PathBuf::new(),
@@ -50,22 +56,45 @@ impl Proc {
             Ok(cwd) => cwd,
             Err(_) => PathBuf::new(),
         };
-        self.maps = match procfs::get_proc_maps(pid) {
-            Ok(maps) => maps,
-            Err(_) => String::from(""),
-        };
+        self.update_maps();
+        self.threads.reset(pid);
         Ok(pid)
     }
 
+    // Attaches to the rest of threads, repeated until no thread shows up
+    // since running ones may create threads meanwhile.
+    pub fn attach_threads(&mut self) -> Result<(), ()> {
+        loop {
+            let new: Vec<i32> = procfs::get_proc_tasks(self.target)?
+                .into_iter()
+                .filter(|tid| !self.threads.contains(*tid))
+                .collect();
+            if new.is_empty() {
+                break;
+            }
+            for tid in new {
+                if ptrace::attach_wait(tid).is_ok() {
+                    self.threads.add(tid);
+                }
+            }
+        }
+
+        // threads and child processes created from now on are traced automatically
+        let options = libc::PTRACE_O_TRACECLONE | libc::PTRACE_O_TRACEFORK |
+                      libc::PTRACE_O_TRACEVFORK | libc::PTRACE_O_TRACEEXEC |
+                      libc::PTRACE_O_TRACESYSGOOD;
+        for tid in self.threads.tids() {
+            ptrace::setoptions(tid, options)?;
+        }
+        Ok(())
+    }
+
     pub fn get_pid(&self) -> nix::unistd::Pid {
         PidType::from_raw(self.target)
     }
     
     pub fn update(&mut self) {
-        self.cmdline = match procfs::get_proc_cmdline(self.target) {
-            Ok(cmdline) => cmdline,
-            Err(_) => String::from(""),
-        };
+        self.cmdline = procfs::get_proc_cmdline(self.target).unwrap_or_default();
         self.exe = match procfs::get_proc_exe(self.target) {
             Ok(exe) => exe,
             Err(_) => PathBuf::new(),
@@ -74,14 +103,9 @@ impl Proc {
             Ok(cwd) => cwd,
             Err(_) => PathBuf::new(),
         };
-        self.maps = match procfs::get_proc_maps(self.target) {
-            Ok(maps) => maps,
-            Err(_) => String::from(""),
-        };
+        self.update_maps();
     }
 
-    pub fn get_exe(&self) -> &PathBuf { &self.exe }
-
     pub fn dump(&self) {
         println!("pid = {}", self.target);
         println!("cmdline = '{}'", self.cmdline);
@@ -89,52 +113,102 @@ impl Proc {
         println!("cwd = '{}'", self.cwd.display());
     }
 
-    pub fn dump_maps(&self) {
-        println!("{}", self.maps);
+    // Reads the memory regions again, they change as the process maps and unmaps memory
+    pub fn update_maps(&mut self) {
+        self.maps = maps::MemoryMap::read(self.target).unwrap_or_else(|_| maps::MemoryMap::new());
+    }
+
+    pub fn release(&mut self) {
+        use colored::Colorize;
+        println!("{}{}", "Releasing process: ".red(), self.target);
+        
+        self.target = -1;
+        self.cmdline.clear();
+        self.exe.clear();
+        self.cwd.clear();
+        self.maps = maps::MemoryMap::new();
+        self.threads.clear();
+    }
+
+    // Kills the process and reaps its threads, the main thread goes last
+    // since its exit is not reported until the others are reaped.
+    pub fn kill(&self) -> Result<(), ()> {
+        ptrace::sigkill(self.target)?;
+        let mut tids = self.threads.tids();
+        tids.sort_by_key(|tid| *tid == self.target);
+        for tid in tids {
+            let _ = waitpid(PidType::from_raw(tid), Some(WaitPidFlag::__WALL));
+        }
+        Ok(())
     }
+}
+
+impl Target for Proc {
+    fn pid(&self) -> i32 { self.target }
+
+    // Thread id of the current thread, which ptrace requests are made to
+    fn tid(&self) -> i32 { self.threads.current() }
+
+    fn tids(&self) -> Vec<i32> { self.threads.tids() }
+
+    fn get_exe(&self) -> &PathBuf { &self.exe }
 
-    pub fn getregs(&self) -> Result<user_regs_struct, () >{
-        ptrace::getregs(self.target)
+    fn maps(&self) -> &maps::MemoryMap { &self.maps }
+
+    // Reads @len bytes at @addr, the part process_vm_readv can't access is read by PTRACE_PEEKDATA
+    fn read_memory(&self, addr: u64, len: usize) -> Result<Vec<u8>, ()> {
+        let mut buf = vec![0u8; len];
+        let read = uio::process_vm_readv(
+            self.get_pid(),
+            &[IoVec::from_mut_slice(&mut buf)],
+            &[RemoteIoVec { base: addr as usize, len }],
+        ).unwrap_or(0);
+
+        let (start, end) = (addr + read as u64, addr + len as u64);
+        let mut word_addr = start & !(WORD_SIZE - 1);
+        while start < end && word_addr < end {
+            let word = ptrace::peekdata(self.tid(), word_addr)?.to_le_bytes();
+            for (i, byte) in word.iter().enumerate() {
+                let cur = word_addr + i as u64;
+                if cur >= start && cur < end {
+                    buf[(cur - addr) as usize] = *byte;
+                }
+            }
+            word_addr += WORD_SIZE;
+        }
+        Ok(buf)
     }
 
-    pub fn getreg(&self, regname: &str) -> Result<u64, ()> {
-        match self.getregs() {
-            Ok(regs) => {
-                match regname {
-                    "rax" => Ok(regs.rax),
-                    "rbx" => Ok(regs.rbx),
-                    "rcx" => Ok(regs.rcx),
-                    "rdx" => Ok(regs.rdx),
-                    "r8"  => Ok(regs.r8),
-                    "r9"  => Ok(regs.r9),
-                    "r10" => Ok(regs.r10),
-                    "r11" => Ok(regs.r11),
-                    "r12" => Ok(regs.r12),
-                    "r13" => Ok(regs.r13),
-                    "r14" => Ok(regs.r14),
-                    "r15" => Ok(regs.r15),
-                    "rsp" => Ok(regs.rsp),
-                    "rbp" => Ok(regs.rbp),
-                    "rip" | "pc" => Ok(regs.rip),
-                    _ => Err(()),
+    // Writes @data at @addr, read-only mappings such as .text are written by PTRACE_POKEDATA
+    fn write_memory(&self, addr: u64, data: &[u8]) -> Result<(), ()> {
+        let written = uio::process_vm_writev(
+            self.get_pid(),
+            &[IoVec::from_slice(data)],
+            &[RemoteIoVec { base: addr as usize, len: data.len() }],
+        ).unwrap_or(0);
+
+        let (start, end) = (addr + written as u64, addr + data.len() as u64);
+        let mut word_addr = start & !(WORD_SIZE - 1);
+        while start < end && word_addr < end {
+            let mut word = ptrace::peekdata(self.tid(), word_addr)?.to_le_bytes();
+            for (i, byte) in word.iter_mut().enumerate() {
+                let cur = word_addr + i as u64;
+                if cur >= start && cur < end {
+                    *byte = data[(cur - addr) as usize];
                 }
             }
-            Err(_) => Err(()),
+            ptrace::pokedata(self.tid(), word_addr, u64::from_le_bytes(word))?;
+            word_addr += WORD_SIZE;
         }
+        Ok(())
     }
 
-    pub fn dump_regs(&self) {
-        ptrace::dumpregs(&self.getregs().unwrap());
+    fn thread_regs(&self, tid: i32) -> Result<Regs, ()> {
+        Regs::read(tid)
     }
 
-    pub fn release(&mut self) {
-        use colored::Colorize;
-        println!("{}{}", "Releasing process: ".red(), self.target);
-        
-        self.target = -1;
-        self.cmdline.clear();
-        self.exe.clear();
-        self.cwd.clear();
+    fn set_thread_regs(&self, tid: i32, regs: &Regs) -> Result<(), ()> {
+        regs.write(tid)
     }
 }
 
@@ -143,7 +217,12 @@ impl Valid for Proc {
 }
 
 // Spawn, attach and wait
-pub fn spawn_file(file: &PathBuf) -> i32 {
+pub fn spawn_file(file: &Path, config: &launch::LaunchConfig) -> i32 {
+    let mut command = match config.command(file) {
+        Ok(command) => command,
+        Err(_) => return -1,
+    };
+
     match unsafe{ nix::unistd::fork() } {
         Ok(nix::unistd::ForkResult::Child) => {
             // ptrace(PTRACE_TRACEME, ...);
@@ -151,23 +230,28 @@ pub fn spawn_file(file: &PathBuf) -> i32 {
                 println!("ptrace::traceme() failed with code {}", e);
             });
 
-            // disable ASLR
-            personality(linux_personality::ADDR_NO_RANDOMIZE).unwrap_or_else(|_| {
-                println!("failed to disable ASLR");
-                linux_personality::Personality::empty()
-            });
+            // disable ASLR unless 'set disable-randomization off'
+            if config.disable_randomization {
+                personality(linux_personality::ADDR_NO_RANDOMIZE).unwrap_or_else(|_| {
+                    println!("failed to disable ASLR");
+                    linux_personality::Personality::empty()
+                });
+            }
 
-            // run executable on this process
-            std::process::Command::new(file.as_path())
-                .exec();
+            // run executable on this process, returns only on failure
+            let err = command.exec();
+            println!("Failed to execute '{}': {}", file.display(), err);
 
-            // this is child process
-            -1
+            // this is child process, must not return to the rsdb's main loop
+            std::process::exit(127)
         },
         Ok(nix::unistd::ForkResult::Parent { child }) => {
             println!("Successfully spawned a child with");
             println!("  path: {}", file.canonicalize().unwrap().display());
             println!("  pid : {}", child.as_raw());
+            if !config.args_string().is_empty() {
+                println!("  args: {}", config.args_string());
+            }
 
             child.as_raw()
         },
@@ -50,6 +50,88 @@ pub fn get_proc_maps(target: i32) -> Result<String, ()> {
     }
 }
 
+// '/proc/{PID}/mem' to read the memory in large chunks at any offset
+pub fn open_proc_mem(target: i32) -> Result<fs::File, ()> {
+    let path = format!("/proc/{}/mem", target);
+    fs::File::open(&path).map_err(|errstr| {
+        println!("Cannot open: '{}': {}", path, errstr);
+    })
+}
+
+// Thread ids in '/proc/{PID}/task'
+pub fn get_proc_tasks(target: i32) -> Result<Vec<i32>, ()> {
+    let mut path = PathBuf::from("/proc");
+    path.push(target.to_string());
+    path.push("task");
+    match fs::read_dir(&path) {
+        Ok(dir) => Ok(dir
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| entry.file_name().to_str()?.parse::<i32>().ok())
+            .collect()),
+        Err(errstr) => {
+            println!("Cannot read from: '{}': {}", path.display(), errstr);
+            Err(())
+        },
+    }
+}
+
+// Name of the thread in '/proc/{PID}/task/{TID}/comm'
+pub fn get_thread_name(target: i32, tid: i32) -> Option<String> {
+    let path = format!("/proc/{}/task/{}/comm", target, tid);
+    fs::read_to_string(path).ok().map(|name| name.trim_end().to_string())
+}
+
+// Process id the thread @tid belongs to, 'Tgid' in '/proc/{TID}/status'
+pub fn get_tgid(tid: i32) -> Option<i32> {
+    let status = fs::read_to_string(format!("/proc/{}/status", tid)).ok()?;
+    status.lines()
+        .find_map(|line| line.strip_prefix("Tgid:"))
+        .and_then(|tgid| tgid.trim().parse::<i32>().ok())
+}
+
+// Auxiliary vector the process started with, '/proc/{PID}/auxv'
+pub fn get_proc_auxv(target: i32) -> Result<Vec<u8>, ()> {
+    let path = format!("/proc/{}/auxv", target);
+    fs::read(&path).map_err(|errstr| {
+        println!("Cannot read from: '{}': {}", path, errstr);
+    })
+}
+
+// Fields of '/proc/{PID}/stat' a core file keeps
+pub struct ProcStat {
+    pub comm: String,
+    pub state: char,
+    pub ppid: i32,
+    pub pgrp: i32,
+    pub session: i32,
+    pub nice: i64,
+}
+
+// '1234 (a.out) t 1200 1234 1200 ...', the name in parentheses may contain spaces
+pub fn get_proc_stat(target: i32) -> Option<ProcStat> {
+    let stat = fs::read_to_string(format!("/proc/{}/stat", target)).ok()?;
+    let (head, rest) = stat.rsplit_once(')')?;
+    let comm = head.split_once('(')?.1.to_string();
+    let fields: Vec<&str> = rest.split_whitespace().collect();
+    Some(ProcStat {
+        comm,
+        state: fields.first()?.chars().next()?,
+        ppid: fields.get(1)?.parse().ok()?,
+        pgrp: fields.get(2)?.parse().ok()?,
+        session: fields.get(3)?.parse().ok()?,
+        nice: fields.get(16)?.parse().ok()?,
+    })
+}
+
+// Real user and group id, 'Uid' and 'Gid' in '/proc/{PID}/status'
+pub fn get_proc_ids(target: i32) -> Option<(u32, u32)> {
+    let status = fs::read_to_string(format!("/proc/{}/status", target)).ok()?;
+    let real = |key: &str| status.lines()
+        .find_map(|line| line.strip_prefix(key))
+        .and_then(|ids| ids.split_whitespace().next()?.parse::<u32>().ok());
+    Some((real("Uid:")?, real("Gid:")?))
+}
+
 pub fn check_pid(pid: i32) -> bool {
     unsafe { libc::kill(pid, 0) == KILL_SUCCESS }
 }
@@ -67,7 +149,7 @@ pub fn findpid(from: &str) -> i32 {
             innerpath
         };
         if let Ok(cmd) = fs::read_to_string(&newpath) {
-            let cmd_first = cmd.split(" ").nth(0);
+            let cmd_first = cmd.split(' ').next();
             if let Some(executable) = cmd_first {
                 if !executable.contains(from) {
                     continue;
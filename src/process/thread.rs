@@ -0,0 +1,111 @@
+use nix::sys::signal::Signal;
+
+use crate::syscall::Call;
+use crate::watchpoint::Hit;
+
+pub struct Thread {
+    // Number shown to the user, never reused while the process is alive
+    pub num: usize,
+    pub tid: i32,
+
+    // Signal to deliver to the thread on the next resume
+    pub pending_signal: Option<Signal>,
+
+    // Trapped on a breakpoint while other thread was reporting a stop,
+    // rewound to hit it again on resume rather than stepping over it
+    pub pending_hit: bool,
+
+    // System call the thread is in, tells syscall-entry stops from syscall-exit ones
+    pub syscall: Option<Call>,
+
+    // Watchpoint hit not reported yet
+    pub watch_hit: Option<Hit>,
+}
+
+// Threads of the traced process, registers are read from the current one
+pub struct ThreadList {
+    list: Vec<Thread>,
+    next_num: usize,
+    current: i32,
+}
+
+impl ThreadList {
+    pub fn new() -> Self {
+        ThreadList { list: Vec::new(), next_num: 1, current: -1 }
+    }
+
+    // Starts over with the main thread @leader of a new process
+    pub fn reset(&mut self, leader: i32) {
+        self.clear();
+        self.add(leader);
+        self.current = leader;
+    }
+
+    pub fn clear(&mut self) {
+        self.list.clear();
+        self.next_num = 1;
+        self.current = -1;
+    }
+
+    // Returns the number of the thread, a new one if @tid is not in the list
+    pub fn add(&mut self, tid: i32) -> usize {
+        if let Some(thread) = self.get(tid) {
+            return thread.num;
+        }
+        let num = self.next_num;
+        self.next_num += 1;
+        self.list.push(Thread { num, tid, pending_signal: None, pending_hit: false, syscall: None, watch_hit: None });
+        num
+    }
+
+    // Removes the thread, the first one becomes current if it was the current one
+    pub fn remove(&mut self, tid: i32) -> Option<Thread> {
+        let index = self.list.iter().position(|thread| thread.tid == tid)?;
+        let thread = self.list.remove(index);
+        if self.current == tid {
+            self.current = self.list.first().map_or(-1, |thread| thread.tid);
+        }
+        Some(thread)
+    }
+
+    pub fn get(&self, tid: i32) -> Option<&Thread> {
+        self.list.iter().find(|thread| thread.tid == tid)
+    }
+
+    pub fn get_mut(&mut self, tid: i32) -> Option<&mut Thread> {
+        self.list.iter_mut().find(|thread| thread.tid == tid)
+    }
+
+    pub fn find_by_num(&self, num: usize) -> Option<&Thread> {
+        self.list.iter().find(|thread| thread.num == num)
+    }
+
+    pub fn contains(&self, tid: i32) -> bool {
+        self.get(tid).is_some()
+    }
+
+    pub fn current(&self) -> i32 { self.current }
+
+    pub fn current_mut(&mut self) -> Option<&mut Thread> {
+        let current = self.current;
+        self.get_mut(current)
+    }
+
+    pub fn select(&mut self, tid: i32) -> Result<(), ()> {
+        match self.contains(tid) {
+            true => {
+                self.current = tid;
+                Ok(())
+            },
+            false => Err(()),
+        }
+    }
+
+    pub fn tids(&self) -> Vec<i32> {
+        self.list.iter().map(|thread| thread.tid).collect()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Thread> {
+        self.list.iter()
+    }
+}
@@ -5,10 +5,11 @@ use nix::errno::Errno;
 
 use colored::*;
 
+use crate::arch::{Regs, RegisterFile};
+
 const NULL: *mut i32 = ptr::null_mut();
 
 // print error if global error is set, similar to perror(...);
-#[macro_export]
 macro_rules! rsdb_ptrace {
     ($($ptrace_args: expr),*) => {
         unsafe {
@@ -24,14 +25,32 @@ macro_rules! rsdb_ptrace {
     };
 }
 
+// PTRACE_PEEK* returns the word itself, so -1 is only an error if errno is set
+macro_rules! rsdb_ptrace_peek {
+    ($request: expr, $target: expr, $addr: expr) => {
+        unsafe {
+            Errno::clear();
+            let word = libc::ptrace($request, $target, $addr as *mut c_void, NULL);
+            if word == -1 && Errno::last() != Errno::UnknownErrno {
+                let errstr: String = format!("ptrace: {}", Errno::last().desc());
+                println!("{}", errstr.red());
+                Err(())
+            } else {
+                Ok(word as u64)
+            }
+        }
+    };
+}
+
 pub fn attach(target: i32) -> Result<i64, ()> {
     rsdb_ptrace!(PTRACE_ATTACH, target, NULL, NULL)
 }
 
+// @target can be any thread of the process
 pub fn attach_wait(target: i32) -> Result<i64, ()> {
     unsafe {
         attach(target)?;
-        match waitpid(target, NULL, WSTOPPED) {
+        match waitpid(target, NULL, __WALL) {
             -1 => Err(()),
             i => Ok(i as i64),
         }
@@ -42,39 +61,129 @@ pub fn detach(target: i32) -> Result<i64, ()> {
     rsdb_ptrace!(PTRACE_DETACH, target, NULL, NULL)
 }
 
-pub fn cont(target: i32) -> Result<i64, ()> {
-    rsdb_ptrace!(PTRACE_CONT, target, NULL, NULL)
+// @options of PTRACE_O_*, e.g) PTRACE_O_TRACECLONE
+pub fn setoptions(target: i32, options: i32) -> Result<i64, ()> {
+    rsdb_ptrace!(PTRACE_SETOPTIONS, target, NULL, options as *mut c_void)
+}
+
+// Message of the last PTRACE_EVENT_* stop, such as the tid of the new thread
+pub fn geteventmsg(target: i32) -> Result<u64, ()> {
+    let mut msg: c_ulong = 0;
+    rsdb_ptrace!(PTRACE_GETEVENTMSG, target, NULL, &mut msg as *mut _ as *mut c_void)?;
+    Ok(msg as u64)
+}
+
+// @signal is delivered to the process on resume, 0 for none
+pub fn cont(target: i32, signal: i32) -> Result<i64, ()> {
+    rsdb_ptrace!(PTRACE_CONT, target, NULL, signal as *mut c_void)
+}
+
+pub fn singlestep(target: i32, signal: i32) -> Result<i64, ()> {
+    rsdb_ptrace!(PTRACE_SINGLESTEP, target, NULL, signal as *mut c_void)
+}
+
+// Resumes until the next syscall-entry or syscall-exit stop
+pub fn syscall(target: i32, signal: i32) -> Result<i64, ()> {
+    rsdb_ptrace!(PTRACE_SYSCALL, target, NULL, signal as *mut c_void)
+}
+
+pub fn peektext(target: i32, addr: u64) -> Result<u64, ()> {
+    rsdb_ptrace_peek!(PTRACE_PEEKTEXT, target, addr)
+}
+
+pub fn peekdata(target: i32, addr: u64) -> Result<u64, ()> {
+    rsdb_ptrace_peek!(PTRACE_PEEKDATA, target, addr)
+}
+
+pub fn poketext(target: i32, addr: u64, data: u64) -> Result<i64, ()> {
+    rsdb_ptrace!(PTRACE_POKETEXT, target, addr as *mut c_void, data as *mut c_void)
+}
+
+pub fn pokedata(target: i32, addr: u64, data: u64) -> Result<i64, ()> {
+    rsdb_ptrace!(PTRACE_POKEDATA, target, addr as *mut c_void, data as *mut c_void)
 }
 
+// Reads the word at @offset of 'struct user', e.g) debug registers
+#[cfg(target_arch = "x86_64")]
+pub fn peekuser(target: i32, offset: u64) -> Result<u64, ()> {
+    rsdb_ptrace_peek!(PTRACE_PEEKUSER, target, offset)
+}
+
+#[cfg(target_arch = "x86_64")]
+pub fn pokeuser(target: i32, offset: u64, data: u64) -> Result<i64, ()> {
+    rsdb_ptrace!(PTRACE_POKEUSER, target, offset as *mut c_void, data as *mut c_void)
+}
+
+// Signal information of the last signal-delivery-stop
+pub fn getsiginfo(target: i32) -> Result<siginfo_t, ()> {
+    let mut data = mem::MaybeUninit::<siginfo_t>::zeroed();
+    rsdb_ptrace!(PTRACE_GETSIGINFO, target, NULL, data.as_mut_ptr() as *mut c_void)?;
+    Ok(unsafe { data.assume_init() })
+}
+
+// Kills the whole process, threads have to be reaped by the caller
 pub fn sigkill(target: i32) -> Result<i64, ()> {
-    let ret = rsdb_ptrace!(PTRACE_KILL, target, libc::SIGKILL, NULL);
-    unsafe { waitpid(target, NULL, WSTOPPED); }
-    ret
+    rsdb_ptrace!(PTRACE_KILL, target, libc::SIGKILL, NULL)
 }
 
-pub fn getregs(target: i32) -> Result<user_regs_struct, ()> {
+#[cfg(target_arch = "x86_64")]
+pub fn getfpregs(target: i32) -> Result<user_fpregs_struct, ()> {
     let mut data = mem::MaybeUninit::uninit();
-    rsdb_ptrace!(PTRACE_GETREGS, target, NULL, 
+    rsdb_ptrace!(PTRACE_GETFPREGS, target, NULL, 
                  data.as_mut_ptr() as *const _ as *mut c_void)?;
     Ok(unsafe { data.assume_init() })
 }
 
-pub fn dumpregs(regs: &user_regs_struct) {
-    println!("  rax: {:16x} {:20}", regs.rax, regs.rax);
-    println!("  rbx: {:16x} {:20}", regs.rbx, regs.rbx);
-    println!("  rcx: {:16x} {:20}", regs.rcx, regs.rcx);
-    println!("  rdx: {:16x} {:20}", regs.rdx, regs.rdx);
-    println!("  rdi: {:16x} {:20}", regs.rdi, regs.rdi);
-    println!("  rdx: {:16x} {:20}", regs.rdx, regs.rdx);
-    println!("  r8 : {:16x} {:20}", regs.r8, regs.r8);
-    println!("  r9 : {:16x} {:20}", regs.r9, regs.r9);
-    println!("  r10: {:16x} {:20}", regs.r10, regs.r10);
-    println!("  r11: {:16x} {:20}", regs.r11, regs.r11);
-    println!("  r12: {:16x} {:20}", regs.r12, regs.r12);
-    println!("  r13: {:16x} {:20}", regs.r13, regs.r13);
-    println!("  r14: {:16x} {:20}", regs.r14, regs.r14);
-    println!("  r15: {:16x} {:20}", regs.r15, regs.r15);
-    println!("  rsp: {:16x} {:20}", regs.rsp, regs.rsp);
-    println!("  rbp: {:16x} {:20}", regs.rbp, regs.rbp);
-    println!("  rip: {:16x} {:20}", regs.rip, regs.rip);
-}
\ No newline at end of file
+// Reads register set @nt_type into @buf, returns the size kernel filled
+pub fn getregset(target: i32, nt_type: i32, buf: &mut [u8]) -> Result<usize, ()> {
+    let mut iov = iovec {
+        iov_base: buf.as_mut_ptr() as *mut c_void,
+        iov_len: buf.len(),
+    };
+    rsdb_ptrace!(PTRACE_GETREGSET, target, nt_type as *mut c_void, 
+                 &mut iov as *mut _ as *mut c_void)?;
+    Ok(iov.iov_len)
+}
+
+// Reads register set @nt_type laid out as @T, e.g) NT_PRSTATUS as user_regs_struct
+pub fn getregset_as<T>(target: i32, nt_type: i32) -> Result<T, ()> {
+    let mut data = mem::MaybeUninit::<T>::zeroed();
+    let mut iov = iovec {
+        iov_base: data.as_mut_ptr() as *mut c_void,
+        iov_len: mem::size_of::<T>(),
+    };
+    rsdb_ptrace!(PTRACE_GETREGSET, target, nt_type as *mut c_void, 
+                 &mut iov as *mut _ as *mut c_void)?;
+    Ok(unsafe { data.assume_init() })
+}
+
+pub fn setregset<T>(target: i32, nt_type: i32, data: &T) -> Result<i64, ()> {
+    let mut iov = iovec {
+        iov_base: data as *const _ as *mut c_void,
+        iov_len: mem::size_of::<T>(),
+    };
+    rsdb_ptrace!(PTRACE_SETREGSET, target, nt_type as *mut c_void, 
+                 &mut iov as *mut _ as *mut c_void)
+}
+
+// Writes register set @nt_type from @buf, for the sets only partially written
+#[cfg(target_arch = "aarch64")]
+pub fn setregset_bytes(target: i32, nt_type: i32, buf: &[u8]) -> Result<i64, ()> {
+    let mut iov = iovec {
+        iov_base: buf.as_ptr() as *mut c_void,
+        iov_len: buf.len(),
+    };
+    rsdb_ptrace!(PTRACE_SETREGSET, target, nt_type as *mut c_void, 
+                 &mut iov as *mut _ as *mut c_void)
+}
+
+// @symbolize annotates register values pointing to symbols
+pub fn dumpregs(regs: &Regs, symbolize: impl Fn(u64) -> Option<String>) {
+    for desc in Regs::registers() {
+        let value = (desc.get)(regs);
+        let annotation = Regs::decode(desc.name, value)
+            .or_else(|| symbolize(value).map(|sym| format!("<{}>", sym)))
+            .unwrap_or_default();
+        println!("  {:6} {:16x} {:20} {}", desc.name, value, value, annotation);
+    }
+}
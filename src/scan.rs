@@ -0,0 +1,494 @@
+use std::cmp::Ordering;
+use std::fs;
+use std::os::unix::fs::FileExt;
+
+use crate::breakpoint::BreakpointTable;
+use crate::process::maps::MemoryRegion;
+
+// Bytes read from '/proc/{PID}/mem' at once
+const CHUNK_SIZE: usize = 1 << 20;
+
+// Candidates listed one by one from this many on, kept in the chunks read before that
+const MAX_LISTED: usize = 1 << 16;
+
+// Type of the values scanned, 'scan TYPE VALUE'
+#[derive(Clone, Copy, PartialEq)]
+pub enum ValueType {
+    I8, I16, I32, I64,
+    U8, U16, U32, U64,
+    F32, F64,
+}
+
+impl ValueType {
+    // 'i32', 'u8', 'f64' and the like
+    pub fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "i8" => ValueType::I8,
+            "i16" => ValueType::I16,
+            "i32" => ValueType::I32,
+            "i64" => ValueType::I64,
+            "u8" => ValueType::U8,
+            "u16" => ValueType::U16,
+            "u32" => ValueType::U32,
+            "u64" => ValueType::U64,
+            "f32" => ValueType::F32,
+            "f64" => ValueType::F64,
+            _ => return None,
+        })
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ValueType::I8 => "i8",
+            ValueType::I16 => "i16",
+            ValueType::I32 => "i32",
+            ValueType::I64 => "i64",
+            ValueType::U8 => "u8",
+            ValueType::U16 => "u16",
+            ValueType::U32 => "u32",
+            ValueType::U64 => "u64",
+            ValueType::F32 => "f32",
+            ValueType::F64 => "f64",
+        }
+    }
+
+    pub fn size(self) -> usize {
+        match self {
+            ValueType::I8 | ValueType::U8 => 1,
+            ValueType::I16 | ValueType::U16 => 2,
+            ValueType::I32 | ValueType::U32 | ValueType::F32 => 4,
+            ValueType::I64 | ValueType::U64 | ValueType::F64 => 8,
+        }
+    }
+
+    pub fn is_float(self) -> bool {
+        matches!(self, ValueType::F32 | ValueType::F64)
+    }
+
+    // Integer @value as the type, None if it doesn't fit, e.g) 300 for u8 or -1 for u32
+    pub fn value_of(self, value: u64) -> Option<Value> {
+        let exact = match self {
+            ValueType::F32 | ValueType::F64 => return None,
+            ValueType::I8 | ValueType::I16 | ValueType::I32 | ValueType::I64 => value as i64 as i128,
+            _ => value as i128,
+        };
+        match self.bits_value(value) {
+            Value::Int(truncated) if truncated == exact => Some(Value::Int(exact)),
+            _ => None,
+        }
+    }
+
+    // Value of the lower size() bytes of @bits, sign extended for the signed types
+    fn bits_value(self, bits: u64) -> Value {
+        let shift = 64 - self.size() as u32 * 8;
+        match self {
+            ValueType::F32 => Value::Float(f32::from_bits(bits as u32) as f64),
+            ValueType::F64 => Value::Float(f64::from_bits(bits)),
+            ValueType::I8 | ValueType::I16 | ValueType::I32 | ValueType::I64 =>
+                Value::Int((((bits << shift) as i64) >> shift) as i128),
+            _ => Value::Int(((bits << shift) >> shift) as i128),
+        }
+    }
+
+    // Value of the little endian @bytes, which are size() bytes long
+    fn decode(self, bytes: &[u8]) -> Value {
+        let mut raw = [0u8; 8];
+        raw[..bytes.len()].copy_from_slice(bytes);
+        self.bits_value(u64::from_le_bytes(raw))
+    }
+}
+
+// Integers of any type fit in i128, so signed and unsigned compare alike
+#[derive(Clone, Copy)]
+pub enum Value {
+    Int(i128),
+    Float(f64),
+}
+
+impl Value {
+    fn compare(&self, other: &Value) -> Option<Ordering> {
+        match (self, other) {
+            (Value::Int(a), Value::Int(b)) => Some(a.cmp(b)),
+            (Value::Float(a), Value::Float(b)) => a.partial_cmp(b),
+            _ => None,
+        }
+    }
+}
+
+// Value to look for. Floats match within @tolerance, e.g) 0.05 for '1.5'
+// since the display rounds them, integers have to be exact.
+pub struct Expected {
+    pub value: Value,
+    pub tolerance: f64,
+}
+
+impl Expected {
+    fn matches(&self, value: &Value) -> bool {
+        match (&self.value, value) {
+            (Value::Float(target), Value::Float(value)) => (value - target).abs() <= self.tolerance,
+            _ => self.value.compare(value) == Some(Ordering::Equal),
+        }
+    }
+}
+
+// How 'scan next' narrows down the candidates, comparing with the value of the last scan
+pub enum Filter {
+    Equal(Expected),
+    Changed,
+    Unchanged,
+    Increased,
+    Decreased,
+}
+
+impl Filter {
+    fn matches(&self, last: &Value, now: &Value) -> bool {
+        let order = now.compare(last);
+        match self {
+            Filter::Equal(expected) => expected.matches(now),
+            Filter::Changed => order != Some(Ordering::Equal),
+            Filter::Unchanged => order == Some(Ordering::Equal),
+            Filter::Increased => order == Some(Ordering::Greater),
+            Filter::Decreased => order == Some(Ordering::Less),
+        }
+    }
+}
+
+// Chunk of memory as it was at the last scan, the candidates in it are
+// marked in @hits by their index, i.e. the offset divided by the type size
+struct Block {
+    start: u64,
+    bytes: Vec<u8>,
+    hits: Vec<u64>,
+}
+
+impl Block {
+    // Indices of the candidates in ascending order
+    fn indices(&self) -> impl Iterator<Item = usize> + '_ {
+        self.hits.iter().enumerate().flat_map(|(word, bits)| {
+            let mut bits = *bits;
+            std::iter::from_fn(move || {
+                if bits == 0 {
+                    return None;
+                }
+                let bit = bits.trailing_zeros() as usize;
+                bits &= bits - 1;
+                Some(word * 64 + bit)
+            })
+        })
+    }
+}
+
+// Bitmap of @count values with the ones @hit set, None if there's none
+fn bitmap(count: usize, mut hit: impl FnMut(usize) -> bool) -> Option<Vec<u64>> {
+    let mut hits = vec![0u64; count.div_ceil(64)];
+    let mut any = false;
+    for index in (0..count).filter(|index| hit(*index)) {
+        hits[index / 64] |= 1 << (index % 64);
+        any = true;
+    }
+    if any { Some(hits) } else { None }
+}
+
+// A snapshot of the chunks takes less memory than listing the matches
+// while there are many of them, e.g) the first scan for 0
+enum Candidates {
+    Blocks(Vec<Block>),
+
+    // sorted addresses, and their value at the last scan packed by the type size
+    List { addrs: Vec<u64>, values: Vec<u8> },
+}
+
+// Candidate addresses of a value scan, 'scan' and 'scan next'
+pub struct Scan {
+    pub ty: ValueType,
+
+    // number of the scans so far, 1 after the first one
+    pub rounds: usize,
+
+    count: usize,
+    candidates: Candidates,
+}
+
+// Reads @buf at @addr, with the original bytes where the breakpoints are patched.
+// Returns the bytes read, less than the buffer if it runs into unmapped memory.
+fn read_chunk(mem: &fs::File, breakpoints: &BreakpointTable, addr: u64, buf: &mut [u8]) -> usize {
+    let mut read = 0;
+    while read < buf.len() {
+        match mem.read_at(&mut buf[read..], addr + read as u64) {
+            Ok(0) | Err(_) => break,
+            Ok(n) => read += n,
+        }
+    }
+    breakpoints.unpatch(addr, &mut buf[..read]);
+    read
+}
+
+impl Scan {
+    // Scans @regions for @expected at the addresses aligned to the type size,
+    // reading a chunk at a time so that hundreds of MB of heap are fine
+    pub fn first(mem: &fs::File, breakpoints: &BreakpointTable, regions: &[&MemoryRegion],
+                 ty: ValueType, expected: &Expected) -> Self {
+        let size = ty.size();
+        let mut blocks = Vec::new();
+        let mut buf = vec![0u8; CHUNK_SIZE];
+        for region in regions {
+            // the regions are page aligned, so are the chunks and no value lies across them
+            let mut addr = region.start;
+            while addr < region.end {
+                let len = (region.end - addr).min(CHUNK_SIZE as u64) as usize;
+                let read = read_chunk(mem, breakpoints, addr, &mut buf[..len]);
+                let bytes = &buf[..read];
+                let hits = bitmap(read / size, |index| expected.matches(&ty.decode(&bytes[index * size..(index + 1) * size])));
+                if let Some(hits) = hits {
+                    blocks.push(Block { start: addr, bytes: bytes.to_vec(), hits });
+                }
+
+                // [vvar] and the like can't be read, skipped
+                if read < len {
+                    break;
+                }
+                addr += len as u64;
+            }
+        }
+        let mut scan = Scan { ty, rounds: 1, count: 0, candidates: Candidates::Blocks(blocks) };
+        scan.settle();
+        scan
+    }
+
+    // Reads the candidates again and keeps the ones passing @filter with the new values
+    pub fn next(&mut self, mem: &fs::File, breakpoints: &BreakpointTable, filter: &Filter) {
+        let candidates = std::mem::replace(&mut self.candidates, Candidates::Blocks(Vec::new()));
+        self.candidates = match candidates {
+            Candidates::Blocks(blocks) => Candidates::Blocks(self.next_blocks(blocks, mem, breakpoints, filter)),
+            Candidates::List { addrs, values } => self.next_list(&addrs, &values, mem, breakpoints, filter),
+        };
+        self.rounds += 1;
+        self.settle();
+    }
+
+    // Rereads each chunk as a whole, the unreadable candidates are dropped
+    fn next_blocks(&self, blocks: Vec<Block>, mem: &fs::File, breakpoints: &BreakpointTable,
+                   filter: &Filter) -> Vec<Block> {
+        let size = self.ty.size();
+        let decode = |bytes: &[u8], index: usize| self.ty.decode(&bytes[index * size..(index + 1) * size]);
+        blocks.into_iter()
+            .filter_map(|block| {
+                let mut bytes = vec![0u8; block.bytes.len()];
+                let read = read_chunk(mem, breakpoints, block.start, &mut bytes);
+                let mut candidates = block.indices().take_while(|index| (index + 1) * size <= read).peekable();
+                let hits = bitmap(block.bytes.len() / size, |index| {
+                    candidates.next_if_eq(&index).is_some() && filter.matches(&decode(&block.bytes, index), &decode(&bytes, index))
+                })?;
+                Some(Block { start: block.start, bytes, hits })
+            })
+            .collect()
+    }
+
+    // The nearby candidates are read together, the unreadable ones are dropped
+    fn next_list(&self, last_addrs: &[u64], last_values: &[u8], mem: &fs::File, breakpoints: &BreakpointTable,
+                 filter: &Filter) -> Candidates {
+        let size = self.ty.size();
+        let mut addrs = Vec::new();
+        let mut values = Vec::new();
+        let mut buf = vec![0u8; CHUNK_SIZE];
+        let mut i = 0;
+        while i < last_addrs.len() {
+            // candidates [i, j) fit in a chunk from the address of i
+            let start = last_addrs[i];
+            let j = i + last_addrs[i..].partition_point(|addr| addr + size as u64 <= start + CHUNK_SIZE as u64);
+            let len = (last_addrs[j - 1] - start) as usize + size;
+            let read = read_chunk(mem, breakpoints, start, &mut buf[..len]);
+
+            let mut k = i;
+            while k < j && (last_addrs[k] - start) as usize + size <= read {
+                let offset = (last_addrs[k] - start) as usize;
+                let bytes = &buf[offset..offset + size];
+                let last = self.ty.decode(&last_values[k * size..(k + 1) * size]);
+                if filter.matches(&last, &self.ty.decode(bytes)) {
+                    addrs.push(last_addrs[k]);
+                    values.extend_from_slice(bytes);
+                }
+                k += 1;
+            }
+
+            // the one stopped the read is gone, carry on from the next
+            i = if k < j { k + 1 } else { j };
+        }
+        Candidates::List { addrs, values }
+    }
+
+    // Counts the candidates, and lists them once they are few enough
+    fn settle(&mut self) {
+        self.count = match &self.candidates {
+            Candidates::Blocks(blocks) => blocks.iter().map(|block| block.indices().count()).sum(),
+            Candidates::List { addrs, .. } => addrs.len(),
+        };
+        if let Candidates::Blocks(blocks) = &self.candidates {
+            if self.count <= MAX_LISTED {
+                let size = self.ty.size();
+                let mut addrs = Vec::with_capacity(self.count);
+                let mut values = Vec::with_capacity(self.count * size);
+                for block in blocks {
+                    for index in block.indices() {
+                        addrs.push(block.start + (index * size) as u64);
+                        values.extend_from_slice(&block.bytes[index * size..(index + 1) * size]);
+                    }
+                }
+                self.candidates = Candidates::List { addrs, values };
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize { self.count }
+
+    // (address, value at the last scan) of the candidates
+    pub fn iter(&self) -> Box<dyn Iterator<Item = (u64, Value)> + '_> {
+        let size = self.ty.size();
+        match &self.candidates {
+            Candidates::Blocks(blocks) => Box::new(blocks.iter().flat_map(move |block| {
+                block.indices().map(move |index| {
+                    let offset = index * size;
+                    (block.start + offset as u64, self.ty.decode(&block.bytes[offset..offset + size]))
+                })
+            })),
+            Candidates::List { addrs, values } => Box::new(addrs.iter()
+                .zip(values.chunks_exact(size))
+                .map(move |(addr, bytes)| (*addr, self.ty.decode(bytes)))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // File standing in for '/proc/{PID}/mem', the memory at 0 holds @bytes
+    struct Memory {
+        path: std::path::PathBuf,
+        file: fs::File,
+        region: MemoryRegion,
+    }
+
+    impl Memory {
+        fn new(name: &str, bytes: &[u8]) -> Self {
+            let path = std::env::temp_dir().join(format!("rsdb-scan-{}-{}", std::process::id(), name));
+            fs::write(&path, bytes).unwrap();
+            let file = fs::File::open(&path).unwrap();
+            let region = MemoryRegion {
+                start: 0, end: bytes.len() as u64, read: true, write: true, exec: false, shared: false,
+                offset: 0, dev: "00:00".to_string(), inode: 0, path: String::new(),
+            };
+            Memory { path, file, region }
+        }
+
+        fn write(&self, addr: usize, bytes: &[u8]) {
+            use std::os::unix::fs::FileExt;
+            fs::OpenOptions::new().write(true).open(&self.path).unwrap().write_at(bytes, addr as u64).unwrap();
+        }
+
+        fn first(&self, ty: ValueType, value: i128) -> Scan {
+            let expected = Expected { value: Value::Int(value), tolerance: 0.0 };
+            Scan::first(&self.file, &BreakpointTable::new(), &[&self.region], ty, &expected)
+        }
+
+        fn next(&self, scan: &mut Scan, filter: Filter) {
+            scan.next(&self.file, &BreakpointTable::new(), &filter);
+        }
+    }
+
+    impl Drop for Memory {
+        fn drop(&mut self) {
+            fs::remove_file(&self.path).unwrap_or_default();
+        }
+    }
+
+    fn addrs(scan: &Scan) -> Vec<u64> {
+        scan.iter().map(|(addr, _)| addr).collect()
+    }
+
+    fn eq(value: i128) -> Filter {
+        Filter::Equal(Expected { value: Value::Int(value), tolerance: 0.0 })
+    }
+
+    #[test]
+    fn values_out_of_range() {
+        assert!(matches!(ValueType::U8.value_of(255), Some(Value::Int(255))));
+        assert!(ValueType::U8.value_of(300).is_none());
+        assert!(ValueType::U32.value_of(u64::MAX).is_none());
+        assert!(matches!(ValueType::I8.value_of(-128i64 as u64), Some(Value::Int(-128))));
+        assert!(ValueType::I8.value_of(128).is_none());
+        assert!(ValueType::I16.value_of(-32769i64 as u64).is_none());
+        assert!(matches!(ValueType::U64.value_of(u64::MAX), Some(Value::Int(v)) if v == u64::MAX as i128));
+        assert!(matches!(ValueType::I64.value_of(u64::MAX), Some(Value::Int(-1))));
+        assert!(ValueType::F32.value_of(1).is_none());
+    }
+
+    #[test]
+    fn filters_compare_with_the_last_value() {
+        let (one, two) = (Value::Int(1), Value::Int(2));
+        assert!(eq(2).matches(&one, &two) && !eq(2).matches(&two, &one));
+        assert!(Filter::Changed.matches(&one, &two) && !Filter::Changed.matches(&one, &one));
+        assert!(Filter::Unchanged.matches(&one, &one) && !Filter::Unchanged.matches(&one, &two));
+        assert!(Filter::Increased.matches(&one, &two) && !Filter::Increased.matches(&two, &one));
+        assert!(Filter::Decreased.matches(&two, &one) && !Filter::Decreased.matches(&one, &one));
+    }
+
+    #[test]
+    fn floats_match_within_the_tolerance() {
+        let expected = Expected { value: Value::Float(1.5), tolerance: 0.05 };
+        assert!(expected.matches(&Value::Float(1.54)));
+        assert!(!expected.matches(&Value::Float(1.56)));
+        assert!(Filter::Decreased.matches(&Value::Float(1.0), &Value::Float(0.5)));
+    }
+
+    #[test]
+    fn few_candidates_are_listed() {
+        let mut bytes = vec![0u8; 0x100];
+        bytes[0x10..0x14].copy_from_slice(&100i32.to_le_bytes());
+        bytes[0x40..0x44].copy_from_slice(&100i32.to_le_bytes());
+        bytes[0x81..0x85].copy_from_slice(&100i32.to_le_bytes());
+        let memory = Memory::new("listed", &bytes);
+
+        // unaligned values aren't candidates
+        let mut scan = memory.first(ValueType::I32, 100);
+        assert!(matches!(scan.candidates, Candidates::List { .. }));
+        assert_eq!(addrs(&scan), vec![0x10, 0x40]);
+
+        memory.write(0x10, &101i32.to_le_bytes());
+        memory.next(&mut scan, Filter::Changed);
+        assert_eq!(addrs(&scan), vec![0x10]);
+        assert!(matches!(scan.iter().next(), Some((0x10, Value::Int(101)))));
+        assert_eq!(scan.rounds, 2);
+
+        memory.write(0x10, &(-5i32).to_le_bytes());
+        memory.next(&mut scan, Filter::Decreased);
+        assert_eq!(addrs(&scan), vec![0x10]);
+        memory.next(&mut scan, eq(-6));
+        assert_eq!(scan.len(), 0);
+    }
+
+    #[test]
+    fn many_candidates_are_kept_in_chunks() {
+        let size = CHUNK_SIZE + 0x1000;
+        let memory = Memory::new("chunks", &vec![0u8; size]);
+        let mut scan = memory.first(ValueType::U16, 0);
+        assert!(matches!(scan.candidates, Candidates::Blocks(_)));
+        assert_eq!(scan.len(), size / 2);
+
+        memory.write(0x20, &[1]);
+        memory.write(CHUNK_SIZE + 0x10, &[2]);
+        memory.write(CHUNK_SIZE + 0x30, &[3]);
+        memory.next(&mut scan, Filter::Unchanged);
+        assert!(matches!(scan.candidates, Candidates::Blocks(_)));
+        assert_eq!(scan.len(), size / 2 - 3);
+
+        // listed once few enough
+        memory.write(0x22, &[4]);
+        memory.write(CHUNK_SIZE + 0x34, &[5]);
+        memory.next(&mut scan, Filter::Increased);
+        assert!(matches!(scan.candidates, Candidates::List { .. }));
+        assert_eq!(addrs(&scan), vec![0x22, CHUNK_SIZE as u64 + 0x34]);
+        let values: Vec<_> = scan.iter().map(|(_, value)| value).collect();
+        assert!(matches!(values[..], [Value::Int(4), Value::Int(5)]));
+    }
+}
@@ -1,7 +1,16 @@
 use std::path::{self, PathBuf};
+use std::collections::BTreeMap;
 
-use elf;
-use crate::process::Proc;
+use crate::process::{Proc, launch::LaunchConfig};
+use crate::breakpoint::BreakpointTable;
+use crate::watchpoint::WatchpointTable;
+use crate::track::TrackTable;
+use crate::scan::Scan;
+use crate::coredump::reader::CoreFile;
+use crate::symbol::SymbolTable;
+use crate::signal::SignalTable;
+use crate::syscall::SyscallCatcher;
+use crate::expr::Expr;
 use crate::traits::*;
 
 #[derive(PartialEq)]
@@ -16,56 +25,290 @@ pub enum Type {
     NotAttached,
 }
 
+#[derive(PartialEq, Clone, Copy)]
+pub enum FollowForkMode {
+    Parent,
+    Child,
+}
+
+// Process kept stopped under ptrace while another one is being debugged
+pub struct Inferior {
+    pub num: usize,
+    pub proc: Proc,
+}
+
 pub struct Session {
     pub proc: Proc,
 
+    // Core file loaded by 'core', inspected while no process is attached
+    pub core: Option<CoreFile>,
+
     // Elf object
     pub elf: Option<elf::File>,
 
+    // Symbols of the ELF, relocated to the load address of the target
+    pub symbols: SymbolTable,
+
     // Path to ELF file
     pub path: Option<path::PathBuf>,
 
     pub attach_type: Type,
+
+    // Arguments, environment and redirections of the program to 'run'
+    pub launch: LaunchConfig,
+
+    // Software breakpoints, kept across processes
+    pub breakpoints: BreakpointTable,
+
+    // Hardware watchpoints, numbered together with the breakpoints
+    pub watchpoints: WatchpointTable,
+
+    // Memory re-read and compared at every stop, 'track'
+    pub tracked: TrackTable,
+
+    // Candidates of the value scan narrowed down by 'scan next'
+    pub scan: Option<Scan>,
+
+    // Signal dispositions set by 'handle'
+    pub signals: SignalTable,
+
+    // System calls to stop at or print, 'catch syscall' and 'trace syscalls'
+    pub syscalls: SyscallCatcher,
+
+    // Process to debug after fork, 'set follow-fork-mode'
+    pub follow_fork_mode: FollowForkMode,
+
+    // Detaches from the other process after fork, 'set detach-on-fork'
+    pub detach_on_fork: bool,
+
+    // Number of the current inferior, the one in 'proc'
+    pub inferior: usize,
+    next_inferior: usize,
+
+    // Other inferiors kept stopped with 'set detach-on-fork off'
+    pub inferiors: Vec<Inferior>,
+
+    // Convenience variables set by 'set $name = EXPR', and by rsdb such as '$_exitcode'
+    pub variables: BTreeMap<String, u64>,
 }
 
 impl Session {
     pub fn new() -> Self {
         Session {
             proc: Proc::new(),
+            core: None,
             path: None,
             elf:  None,
+            symbols: SymbolTable::new(),
             attach_type: Type::NotAttached,
+            launch: LaunchConfig::new(),
+            breakpoints: BreakpointTable::new(),
+            watchpoints: WatchpointTable::new(),
+            tracked: TrackTable::new(),
+            scan: None,
+            signals: SignalTable::new(),
+            syscalls: SyscallCatcher::new(),
+            follow_fork_mode: FollowForkMode::Parent,
+            detach_on_fork: true,
+            inferior: 1,
+            next_inferior: 2,
+            inferiors: Vec::new(),
+            variables: BTreeMap::new(),
         }
     }
 
-    pub fn mut_proc(&mut self) -> &mut Proc {
-        &mut self.proc
-    }
-
     pub fn set_elf(
         &mut self, 
         path: path::PathBuf
     ) -> Result<(), elf::ParseError> {
-        self.elf = Some(elf::File::open_path(&path)?);
+        let file = elf::File::open_path(&path)?;
+        self.symbols = SymbolTable::from_elf(&file);
+        self.elf = Some(file);
         self.path = Some(path);
+        self.rebase();
         Ok(())
     }
 
-    pub fn get_target(&self) -> i32 { self.proc.target }
+    // Relocates symbols of PIE against the load address of the target
+    fn rebase(&mut self) {
+        let base = match &self.elf {
+            Some(file) if file.ehdr.elftype == elf::types::ET_DYN && self.has_target() => {
+                self.target().maps().load_base(self.target().get_exe()).unwrap_or(0)
+            },
+            _ => 0,
+        };
+        self.symbols.set_base(base);
+
+        // breakpoints set by symbol follow the new load address
+        let symbols = &self.symbols;
+        for bp in self.breakpoints.iter_mut() {
+            if let Some(addr) = bp.location.as_ref().and_then(|loc| symbols.resolve(loc)) {
+                bp.addr = addr;
+            }
+        }
+        for tracked in self.tracked.iter_mut() {
+            if let Some(addr) = tracked.location.as_ref().and_then(|loc| symbols.resolve(loc)) {
+                tracked.addr = addr;
+            }
+        }
+        for wp in self.watchpoints.iter_mut() {
+            if let Some(addr) = wp.location.as_ref().and_then(|loc| symbols.resolve(loc)) {
+                if wp.addr != addr {
+                    wp.addr = addr;
+                    wp.value = None;
+                }
+            }
+        }
+    }
+
+    // Value of the expression @expr with the registers of the current thread,
+    // e.g) 'main+0x24' or '*(u64*)($rsp+8)'. Prints what's wrong on failure.
+    pub fn eval(&self, expr: &str) -> Result<u64, ()> {
+        Expr::parse(expr)?.eval(self, self.target().tid())
+    }
+
+    // Process being debugged, or the core file if no process is attached
+    pub fn target(&self) -> &dyn Target {
+        match &self.core {
+            Some(core) => core,
+            None => &self.proc,
+        }
+    }
+
+    // Registers and memory are there to inspect, of a process or of a core file
+    pub fn has_target(&self) -> bool {
+        self.proc.valid() || self.core.is_some()
+    }
+
+    // Inspects @core from now on, with the executable it names unless one is given
+    pub fn load_core(&mut self, core: CoreFile) {
+        let exe = core.get_exe().clone();
+        self.core = Some(core);
+        if self.elf.is_none() && exe.is_file() {
+            if let Err(e) = self.set_elf(exe.clone()) {
+                println!("[ELF] Failed to parse an ELF");
+                println!("  path: '{}'", exe.display());
+                println!("  err : {:?}", e);
+            }
+        }
+        self.rebase();
+    }
 
+    // Convenience variable @name without '$', the load addresses are looked up every time
+    // since they change with the process: '$base' of the executable and '$libc'.
+    pub fn variable(&self, name: &str) -> Option<u64> {
+        match name {
+            "base" if self.has_target() => self.target().maps().load_base(self.target().get_exe()),
+            "libc" if self.has_target() => self.target().maps().library_base("libc"),
+            name => self.variables.get(name).copied(),
+        }
+    }
+
+    // Entry point in the ELF header, relocated to the load address
+    pub fn entry_point(&self) -> Option<u64> {
+        self.elf.as_ref().map(|file| file.ehdr.entry + self.symbols.base())
+    }
+
+    // '0x401126 <main+0x4>' if @addr belongs to any symbol, '0x7ffff7e29d90 <libc.so.6+0x29d90>'
+    // if it's in a mapped file, '0x401126' otherwise
+    pub fn format_addr(&self, addr: u64) -> String {
+        if let Some(sym) = self.symbols.symbolize(addr) {
+            return format!("{:#x} <{}>", addr, sym);
+        }
+        let maps = self.target().maps();
+        match maps.find(addr).filter(|region| region.path.starts_with('/')) {
+            Some(region) => format!("{:#x} <{}+{:#x}>", addr, region.name(), addr - maps.base_of(region)),
+            None => format!("{:#x}", addr),
+        }
+    }
+
+    // Current thread of the process, ptrace requests are made to it
+    pub fn get_target(&self) -> i32 { self.proc.tid() }
+
+    // Debugs the process @target, the core file is put away if any
     pub fn set_target(&mut self, target: i32) -> Result<i32, ()> {
-        self.proc.set(target)
+        self.core = None;
+        let ret = self.proc.set(target);
+        if ret.is_ok() && self.proc.attach_threads().is_err() {
+            println!("Failed to attach to threads of the process: {}", target);
+        }
+        self.rebase();
+        ret
     }
 
     pub fn set_type(&mut self, t: Type) {
         self.attach_type = t;
     }
 
-    pub fn get_exe(&self) -> &PathBuf { &self.proc.get_exe() }
+    pub fn get_exe(&self) -> &PathBuf { self.proc.get_exe() }
 
     pub fn release(&mut self) {
         self.proc.release();
-        self.set_type(Type::NotAttached);
+        self.breakpoints.reset();
+        self.watchpoints.reset();
+        self.scan = None;
+
+        // carry on with an inferior kept by 'set detach-on-fork off'
+        if self.inferiors.is_empty() {
+            self.set_type(Type::NotAttached);
+        } else {
+            let next = self.inferiors.remove(0);
+            self.switch_inferior(next.num, next.proc);
+        }
+    }
+
+    pub fn new_inferior_num(&mut self) -> usize {
+        self.next_inferior += 1;
+        self.next_inferior - 1
+    }
+
+    // Keeps @proc stopped as the inferior @num
+    pub fn hold(&mut self, num: usize, proc: Proc) {
+        self.inferiors.push(Inferior { num, proc });
+    }
+
+    // Makes the held inferior @num current, the current one is held instead
+    pub fn select_inferior(&mut self, num: usize) -> Result<(), ()> {
+        let index = self.inferiors.iter().position(|inferior| inferior.num == num).ok_or(())?;
+
+        // breakpoints are inserted only in the current inferior
+        if self.proc.valid() {
+            self.breakpoints.remove_all(self.get_target())?;
+        }
+        let next = self.inferiors.remove(index);
+        self.switch_inferior(next.num, next.proc);
+        Ok(())
+    }
+
+    fn switch_inferior(&mut self, num: usize, proc: Proc) {
+        let prev = std::mem::replace(&mut self.proc, proc);
+        if prev.valid() {
+            self.hold(self.inferior, prev);
+        }
+        self.inferior = num;
+        println!("[Switching to inferior {} (process {})]", num, self.proc.target);
+        self.reload_elf();
+    }
+
+    // The process has executed a new program, its symbols are loaded
+    // and the breakpoints are inserted again.
+    pub fn exec_reload(&mut self) {
+        self.proc.update();
+        self.proc.threads.reset(self.proc.target);
+        self.breakpoints.reset();
+        self.watchpoints.reset();
+        self.scan = None;
+        self.reload_elf();
+    }
+
+    // Loads the ELF from the executable of the process
+    pub fn reload_elf(&mut self) {
+        let exe = self.get_exe().to_path_buf();
+        if let Err(e) = self.set_elf(exe.clone()) {
+            println!("[ELF] Failed to parse an ELF");
+            println!("  path: '{}'", exe.display());
+            println!("  err : {:?}", e);
+        }
     }
 }
 
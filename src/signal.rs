@@ -0,0 +1,86 @@
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::str::FromStr;
+
+use nix::sys::signal::Signal;
+
+// What to do when the process receives a signal
+#[derive(Clone, Copy)]
+pub struct Disposition {
+    // give control back to the user
+    pub stop: bool,
+
+    // tell the user that the signal has been received
+    pub print: bool,
+
+    // deliver the signal to the process on resume
+    pub pass: bool,
+}
+
+pub struct SignalTable {
+    table: HashMap<Signal, Disposition>,
+}
+
+impl SignalTable {
+    pub fn new() -> Self {
+        let table = Signal::iterator()
+            .map(|sig| {
+                let disposition = match sig {
+                    // used in normal operation of many programs
+                    Signal::SIGALRM | Signal::SIGURG | Signal::SIGCHLD | Signal::SIGWINCH |
+                    Signal::SIGIO | Signal::SIGVTALRM | Signal::SIGPROF => {
+                        Disposition { stop: false, print: false, pass: true }
+                    },
+                    // used by debugger itself
+                    Signal::SIGINT | Signal::SIGTRAP => {
+                        Disposition { stop: true, print: true, pass: false }
+                    },
+                    _ => Disposition { stop: true, print: true, pass: true },
+                };
+                (sig, disposition)
+            })
+            .collect();
+        SignalTable { table }
+    }
+
+    pub fn get(&self, sig: Signal) -> Disposition {
+        self.table.get(&sig)
+            .copied()
+            .unwrap_or(Disposition { stop: true, print: true, pass: true })
+    }
+
+    // Applies 'stop', 'nostop', 'print', 'noprint', 'pass' and 'nopass' keyword,
+    // 'stop' implies 'print' and 'noprint' implies 'nostop'.
+    pub fn handle(&mut self, sig: Signal, keyword: &str) -> Result<(), ()> {
+        let mut disposition = self.get(sig);
+        match keyword {
+            "stop" => {
+                disposition.stop = true;
+                disposition.print = true;
+            },
+            "nostop" => disposition.stop = false,
+            "print" => disposition.print = true,
+            "noprint" => {
+                disposition.print = false;
+                disposition.stop = false;
+            },
+            "pass" | "noignore" => disposition.pass = true,
+            "nopass" | "ignore" => disposition.pass = false,
+            _ => return Err(()),
+        }
+        self.table.insert(sig, disposition);
+        Ok(())
+    }
+}
+
+// Parses 'SIGALRM', 'ALRM' or '14'
+pub fn parse(s: &str) -> Option<Signal> {
+    if let Ok(signum) = s.parse::<i32>() {
+        return Signal::try_from(signum).ok();
+    }
+    let name = s.to_uppercase();
+    match name.starts_with("SIG") {
+        true => Signal::from_str(&name).ok(),
+        false => Signal::from_str(&format!("SIG{}", name)).ok(),
+    }
+}
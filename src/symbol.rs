@@ -0,0 +1,101 @@
+use elf::types::*;
+
+// Symbols of the main executable, sorted by address for symbolization
+pub struct Symbol {
+    pub name: String,
+    pub addr: u64,
+    pub size: u64,
+}
+
+pub struct SymbolTable {
+    symbols: Vec<Symbol>,
+
+    // Load address of PIE, 0 for the executables that are not relocated
+    base: u64,
+}
+
+impl SymbolTable {
+    pub fn new() -> Self {
+        SymbolTable { symbols: Vec::new(), base: 0 }
+    }
+
+    // Loads '.symtab' and '.dynsym', the stripped binaries have the latter only
+    pub fn from_elf(file: &elf::File) -> Self {
+        let mut symbols = Vec::new();
+        for section in file.sections.iter() {
+            if section.shdr.shtype != SHT_SYMTAB && section.shdr.shtype != SHT_DYNSYM {
+                continue;
+            }
+            let entries = match file.get_symbols(section) {
+                Ok(entries) => entries,
+                Err(_) => continue,
+            };
+            for sym in entries {
+                // undefined(imported), section and file symbols don't have any address
+                if sym.name.is_empty() || sym.shndx == 0 || sym.value == 0 ||
+                   sym.symtype == STT_SECTION || sym.symtype == STT_FILE {
+                    continue;
+                }
+                symbols.push(Symbol { name: sym.name, addr: sym.value, size: sym.size });
+            }
+        }
+        symbols.sort_by(|a, b| a.addr.cmp(&b.addr).then_with(|| a.name.cmp(&b.name)));
+        symbols.dedup_by(|a, b| a.addr == b.addr && a.name == b.name);
+        SymbolTable { symbols, base: 0 }
+    }
+
+    pub fn set_base(&mut self, base: u64) { self.base = base; }
+
+    pub fn base(&self) -> u64 { self.base }
+
+    pub fn lookup(&self, name: &str) -> Option<u64> {
+        self.symbols.iter()
+            .find(|sym| sym.name == name)
+            .map(|sym| sym.addr + self.base)
+    }
+
+    // Resolves 'symbol', 'symbol+offset' or 'symbol-offset'
+    pub fn resolve(&self, expr: &str) -> Option<u64> {
+        if let Some(addr) = self.lookup(expr) {
+            return Some(addr);
+        }
+        let split = expr.rfind(['+', '-']).filter(|i| *i > 0)?;
+        let (name, rest) = expr.split_at(split);
+        let (sign, offset) = rest.split_at(1);
+        let offset = match offset.strip_prefix("0x") {
+            Some(hex) => u64::from_str_radix(hex, 16).ok()?,
+            None => offset.parse::<u64>().ok()?,
+        };
+        let addr = self.lookup(name)?;
+        match sign {
+            "+" => Some(addr.wrapping_add(offset)),
+            _ => Some(addr.wrapping_sub(offset)),
+        }
+    }
+
+    // Symbol containing unrelocated @addr
+    fn containing(&self, addr: u64) -> Option<&Symbol> {
+        let end = self.symbols.partition_point(|sym| sym.addr <= addr);
+        self.symbols[..end].iter()
+            .rev()
+            .take_while(|sym| addr - sym.addr < 0x10000)
+            .find(|sym| addr == sym.addr || addr - sym.addr < sym.size)
+    }
+
+    // Returns 'symbol+offset' of the symbol containing @addr
+    pub fn symbolize(&self, addr: u64) -> Option<String> {
+        let addr = addr.checked_sub(self.base)?;
+        let sym = self.containing(addr)?;
+        match addr - sym.addr {
+            0 => Some(sym.name.clone()),
+            offset => Some(format!("{}+{:#x}", sym.name, offset)),
+        }
+    }
+
+    // (start, size) of the sized symbol containing @addr, e.g) the whole function
+    pub fn bounds(&self, addr: u64) -> Option<(u64, u64)> {
+        let sym = self.containing(addr.checked_sub(self.base)?)
+            .filter(|sym| sym.size > 0)?;
+        Some((sym.addr + self.base, sym.size))
+    }
+}
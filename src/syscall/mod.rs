@@ -0,0 +1,374 @@
+// System call names and decoding of the arguments for 'catch syscall' and 'trace syscalls'
+
+#[cfg(target_arch = "x86_64")]
+mod x86_64;
+#[cfg(target_arch = "x86_64")]
+use self::x86_64::SYSCALLS;
+
+#[cfg(target_arch = "aarch64")]
+mod aarch64;
+#[cfg(target_arch = "aarch64")]
+use self::aarch64::SYSCALLS;
+
+use nix::errno::Errno;
+use nix::sys::signal::Signal;
+use std::convert::TryFrom;
+
+use crate::process::Proc;
+use crate::traits::Target;
+
+// Longest string shown in the arguments
+const MAX_STR_LEN: usize = 64;
+
+// Longest data shown for the buffer of write(2) family
+const MAX_BUF_LEN: usize = 32;
+
+#[derive(Clone, Copy)]
+enum Arg {
+    // C int, upper half of the register is ignored
+    Int,
+    // long and off_t
+    Long,
+    Uint,
+    Hex,
+    Fd,
+    // file descriptor or AT_FDCWD
+    DirFd,
+    // NUL terminated string, usually a path
+    Str,
+    // data of the length in the next argument
+    Buf,
+    OpenFlags,
+    // permission bits in octal
+    Mode,
+    Prot,
+    MapFlags,
+    Signal,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Ret {
+    Int,
+    Hex,
+}
+
+use Arg::*;
+
+// Arguments and return value of well known system calls, shared by every architecture
+const SIGNATURES: &[(&str, &[Arg], Ret)] = &[
+    ("read", &[Fd, Hex, Uint], Ret::Int),
+    ("write", &[Fd, Buf, Uint], Ret::Int),
+    ("pread64", &[Fd, Hex, Uint, Long], Ret::Int),
+    ("pwrite64", &[Fd, Buf, Uint, Long], Ret::Int),
+    ("open", &[Str, OpenFlags, Mode], Ret::Int),
+    ("openat", &[DirFd, Str, OpenFlags, Mode], Ret::Int),
+    ("close", &[Fd], Ret::Int),
+    ("stat", &[Str, Hex], Ret::Int),
+    ("lstat", &[Str, Hex], Ret::Int),
+    ("fstat", &[Fd, Hex], Ret::Int),
+    ("newfstatat", &[DirFd, Str, Hex, Int], Ret::Int),
+    ("statx", &[DirFd, Str, Int, Hex, Hex], Ret::Int),
+    ("lseek", &[Fd, Long, Int], Ret::Int),
+    ("mmap", &[Hex, Uint, Prot, MapFlags, Fd, Hex], Ret::Hex),
+    ("mprotect", &[Hex, Uint, Prot], Ret::Int),
+    ("munmap", &[Hex, Uint], Ret::Int),
+    ("madvise", &[Hex, Uint, Int], Ret::Int),
+    ("brk", &[Hex], Ret::Hex),
+    ("ioctl", &[Fd, Hex, Hex], Ret::Int),
+    ("fcntl", &[Fd, Int, Hex], Ret::Int),
+    ("access", &[Str, Int], Ret::Int),
+    ("faccessat", &[DirFd, Str, Int], Ret::Int),
+    ("faccessat2", &[DirFd, Str, Int, Int], Ret::Int),
+    ("pipe", &[Hex], Ret::Int),
+    ("pipe2", &[Hex, OpenFlags], Ret::Int),
+    ("dup", &[Fd], Ret::Int),
+    ("dup2", &[Fd, Fd], Ret::Int),
+    ("dup3", &[Fd, Fd, OpenFlags], Ret::Int),
+    ("getdents64", &[Fd, Hex, Uint], Ret::Int),
+    ("getcwd", &[Hex, Uint], Ret::Int),
+    ("chdir", &[Str], Ret::Int),
+    ("fchdir", &[Fd], Ret::Int),
+    ("rename", &[Str, Str], Ret::Int),
+    ("renameat", &[DirFd, Str, DirFd, Str], Ret::Int),
+    ("renameat2", &[DirFd, Str, DirFd, Str, Hex], Ret::Int),
+    ("mkdir", &[Str, Mode], Ret::Int),
+    ("mkdirat", &[DirFd, Str, Mode], Ret::Int),
+    ("rmdir", &[Str], Ret::Int),
+    ("unlink", &[Str], Ret::Int),
+    ("unlinkat", &[DirFd, Str, Hex], Ret::Int),
+    ("readlink", &[Str, Hex, Uint], Ret::Int),
+    ("readlinkat", &[DirFd, Str, Hex, Uint], Ret::Int),
+    ("chmod", &[Str, Mode], Ret::Int),
+    ("fchmod", &[Fd, Mode], Ret::Int),
+    ("fchmodat", &[DirFd, Str, Mode], Ret::Int),
+    ("socket", &[Int, Int, Int], Ret::Int),
+    ("connect", &[Fd, Hex, Uint], Ret::Int),
+    ("bind", &[Fd, Hex, Uint], Ret::Int),
+    ("listen", &[Fd, Int], Ret::Int),
+    ("accept", &[Fd, Hex, Hex], Ret::Int),
+    ("accept4", &[Fd, Hex, Hex, Hex], Ret::Int),
+    ("sendto", &[Fd, Buf, Uint, Hex, Hex, Uint], Ret::Int),
+    ("recvfrom", &[Fd, Hex, Uint, Hex, Hex, Hex], Ret::Int),
+    ("execve", &[Str, Hex, Hex], Ret::Int),
+    ("execveat", &[DirFd, Str, Hex, Hex, Hex], Ret::Int),
+    ("clone", &[Hex, Hex, Hex, Hex, Hex], Ret::Int),
+    ("clone3", &[Hex, Uint], Ret::Int),
+    ("fork", &[], Ret::Int),
+    ("vfork", &[], Ret::Int),
+    ("exit", &[Int], Ret::Int),
+    ("exit_group", &[Int], Ret::Int),
+    ("wait4", &[Int, Hex, Hex, Hex], Ret::Int),
+    ("kill", &[Int, Signal], Ret::Int),
+    ("tkill", &[Int, Signal], Ret::Int),
+    ("tgkill", &[Int, Int, Signal], Ret::Int),
+    ("rt_sigaction", &[Signal, Hex, Hex, Uint], Ret::Int),
+    ("rt_sigprocmask", &[Int, Hex, Hex, Uint], Ret::Int),
+    ("rt_sigreturn", &[], Ret::Int),
+    ("nanosleep", &[Hex, Hex], Ret::Int),
+    ("clock_nanosleep", &[Int, Int, Hex, Hex], Ret::Int),
+    ("clock_gettime", &[Int, Hex], Ret::Int),
+    ("getpid", &[], Ret::Int),
+    ("getppid", &[], Ret::Int),
+    ("gettid", &[], Ret::Int),
+    ("getuid", &[], Ret::Int),
+    ("geteuid", &[], Ret::Int),
+    ("getgid", &[], Ret::Int),
+    ("getegid", &[], Ret::Int),
+    ("uname", &[Hex], Ret::Int),
+    ("sched_yield", &[], Ret::Int),
+    ("arch_prctl", &[Hex, Hex], Ret::Int),
+    ("set_tid_address", &[Hex], Ret::Int),
+    ("set_robust_list", &[Hex, Uint], Ret::Int),
+    ("futex", &[Hex, Int, Int, Hex, Hex, Int], Ret::Int),
+    ("prlimit64", &[Int, Int, Hex, Hex], Ret::Int),
+    ("getrandom", &[Hex, Uint, Hex], Ret::Int),
+    ("rseq", &[Hex, Uint, Hex, Hex], Ret::Int),
+];
+
+const OPEN_FLAGS: &[(u64, &str)] = &[
+    (libc::O_CREAT as u64, "O_CREAT"),
+    (libc::O_EXCL as u64, "O_EXCL"),
+    (libc::O_NOCTTY as u64, "O_NOCTTY"),
+    (libc::O_TRUNC as u64, "O_TRUNC"),
+    (libc::O_APPEND as u64, "O_APPEND"),
+    (libc::O_NONBLOCK as u64, "O_NONBLOCK"),
+    (libc::O_DIRECTORY as u64, "O_DIRECTORY"),
+    (libc::O_NOFOLLOW as u64, "O_NOFOLLOW"),
+    (libc::O_CLOEXEC as u64, "O_CLOEXEC"),
+    (libc::O_PATH as u64, "O_PATH"),
+];
+
+const PROT_FLAGS: &[(u64, &str)] = &[
+    (libc::PROT_READ as u64, "PROT_READ"),
+    (libc::PROT_WRITE as u64, "PROT_WRITE"),
+    (libc::PROT_EXEC as u64, "PROT_EXEC"),
+];
+
+const MAP_FLAGS: &[(u64, &str)] = &[
+    (libc::MAP_SHARED as u64, "MAP_SHARED"),
+    (libc::MAP_PRIVATE as u64, "MAP_PRIVATE"),
+    (libc::MAP_FIXED as u64, "MAP_FIXED"),
+    (libc::MAP_ANONYMOUS as u64, "MAP_ANONYMOUS"),
+    (libc::MAP_NORESERVE as u64, "MAP_NORESERVE"),
+    (libc::MAP_DENYWRITE as u64, "MAP_DENYWRITE"),
+    (libc::MAP_POPULATE as u64, "MAP_POPULATE"),
+    (libc::MAP_STACK as u64, "MAP_STACK"),
+    (libc::MAP_FIXED_NOREPLACE as u64, "MAP_FIXED_NOREPLACE"),
+];
+
+// System call being executed by a thread
+pub struct Call {
+    pub nr: u64,
+
+    // 'openat(AT_FDCWD, "/etc/passwd", O_RDONLY)', formatted at the entry
+    pub text: String,
+
+    // return value, set at the exit of the call
+    pub ret: Option<u64>,
+}
+
+// Which system calls stop the process and whether they are printed
+pub struct SyscallCatcher {
+    // numbers to stop at, every system call if empty
+    catch: Option<Vec<u64>>,
+
+    // prints every system call with 'trace syscalls on'
+    pub trace: bool,
+}
+
+impl SyscallCatcher {
+    pub fn new() -> Self {
+        SyscallCatcher { catch: None, trace: false }
+    }
+
+    // The process has to be resumed with PTRACE_SYSCALL
+    pub fn active(&self) -> bool {
+        self.catch.is_some() || self.trace
+    }
+
+    // Catches @numbers in addition, or every system call if empty
+    pub fn add(&mut self, numbers: &[u64]) {
+        match (&mut self.catch, numbers.is_empty()) {
+            (_, true) | (None, false) => self.catch = Some(numbers.to_vec()),
+            // catching everything already
+            (Some(list), false) if list.is_empty() => (),
+            (Some(list), false) => list.extend_from_slice(numbers),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.catch = None;
+    }
+
+    pub fn catches(&self, nr: u64) -> bool {
+        match &self.catch {
+            Some(list) => list.is_empty() || list.contains(&nr),
+            None => false,
+        }
+    }
+
+    // None if not catching, empty if catching every system call
+    pub fn caught(&self) -> Option<&[u64]> {
+        self.catch.as_deref()
+    }
+}
+
+pub fn name(nr: u64) -> Option<&'static str> {
+    SYSCALLS.iter().find(|(n, _)| *n == nr).map(|(_, name)| *name)
+}
+
+// Parses 'openat' or '257'
+pub fn parse(s: &str) -> Option<u64> {
+    match s.parse::<u64>() {
+        Ok(nr) => Some(nr),
+        Err(_) => SYSCALLS.iter().find(|(_, name)| *name == s).map(|(nr, _)| *nr),
+    }
+}
+
+// 'NAME' or 'syscall_NR' for numbers missing in the table
+pub fn display_name(nr: u64) -> String {
+    name(nr).map_or(format!("syscall_{}", nr), String::from)
+}
+
+// 'O_RDONLY|O_CLOEXEC', bits not in @table are shown in hex
+fn format_flags(value: u64, table: &[(u64, &str)]) -> Vec<String> {
+    let mut rest = value;
+    let mut list: Vec<String> = table.iter()
+        .filter(|(bit, _)| value & bit == *bit && *bit != 0)
+        .map(|(bit, name)| {
+            rest &= !bit;
+            name.to_string()
+        })
+        .collect();
+    if rest != 0 {
+        list.push(format!("{:#x}", rest));
+    }
+    list
+}
+
+// String at @addr, quoted and escaped
+fn format_str(proc: &Proc, addr: u64, max: usize, nul_terminated: bool) -> String {
+    if addr == 0 {
+        return "NULL".to_string();
+    }
+    let bytes = proc.read_memory(addr, max)
+        .or_else(|_| {
+            let page_end = (addr | 0xfff) + 1;
+            proc.read_memory(addr, std::cmp::min(max as u64, page_end - addr) as usize)
+        });
+    let bytes = match bytes {
+        Ok(bytes) => bytes,
+        Err(_) => return format!("{:#x}", addr),
+    };
+    let (bytes, truncated) = match nul_terminated {
+        true => match bytes.iter().position(|b| *b == 0) {
+            Some(len) => (&bytes[..len], false),
+            None => (&bytes[..], true),
+        },
+        false => (&bytes[..], false),
+    };
+    let text = format!("{:?}", String::from_utf8_lossy(bytes));
+    match truncated {
+        true => format!("{}...", text),
+        false => text,
+    }
+}
+
+fn format_arg(proc: &Proc, kind: Arg, value: u64, next: u64) -> String {
+    match kind {
+        Int | Fd => (value as i32).to_string(),
+        Long => (value as i64).to_string(),
+        Uint => value.to_string(),
+        Hex => format!("{:#x}", value),
+        DirFd => match value as i32 {
+            libc::AT_FDCWD => "AT_FDCWD".to_string(),
+            fd => fd.to_string(),
+        },
+        Str => format_str(proc, value, MAX_STR_LEN, true),
+        Buf => {
+            let len = std::cmp::min(next as usize, MAX_BUF_LEN);
+            let text = format_str(proc, value, len, false);
+            match next as usize > MAX_BUF_LEN {
+                true => format!("{}...", text),
+                false => text,
+            }
+        },
+        OpenFlags => {
+            let mode = match value as i32 & libc::O_ACCMODE {
+                libc::O_WRONLY => "O_WRONLY",
+                libc::O_RDWR => "O_RDWR",
+                _ => "O_RDONLY",
+            };
+            let mut list = vec![mode.to_string()];
+            list.extend(format_flags(value & !(libc::O_ACCMODE as u64), OPEN_FLAGS));
+            list.join("|")
+        },
+        Mode => match value {
+            0 => "0".to_string(),
+            _ => format!("0{:o}", value),
+        },
+        Prot => match value {
+            0 => "PROT_NONE".to_string(),
+            _ => format_flags(value, PROT_FLAGS).join("|"),
+        },
+        MapFlags => format_flags(value, MAP_FLAGS).join("|"),
+        Signal => match Signal::try_from(value as i32) {
+            Ok(sig) => sig.as_str().to_string(),
+            Err(_) => (value as i32).to_string(),
+        },
+    }
+}
+
+fn signature(nr: u64) -> Option<(&'static [Arg], Ret)> {
+    let name = name(nr)?;
+    SIGNATURES.iter()
+        .find(|(n, _, _)| *n == name)
+        .map(|(_, args, ret)| (*args, *ret))
+}
+
+// 'openat(AT_FDCWD, "/etc/passwd", O_RDONLY)', arguments of unknown calls are in hex
+pub fn format_call(proc: &Proc, nr: u64, args: &[u64]) -> String {
+    let kinds: Vec<Arg> = match signature(nr) {
+        Some((kinds, _)) => kinds.to_vec(),
+        None => vec![Hex; args.len()],
+    };
+    let list: Vec<String> = kinds.iter()
+        .zip(args.iter())
+        .enumerate()
+        .map(|(i, (kind, value))| format_arg(proc, *kind, *value, args.get(i + 1).copied().unwrap_or(0)))
+        .collect();
+    format!("{}({})", display_name(nr), list.join(", "))
+}
+
+// '3', '0x7ffff7fc1000' or '-1 ENOENT (No such file or directory)'
+pub fn format_return(nr: u64, value: u64) -> String {
+    let ret = value as i64;
+    if (-4095..0).contains(&ret) {
+        let errno = Errno::from_i32(-ret as i32);
+        return format!("-1 {:?} ({})", errno, errno.desc());
+    }
+    match signature(nr) {
+        Some((_, Ret::Hex)) => format!("{:#x}", value),
+        _ => ret.to_string(),
+    }
+}
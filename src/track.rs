@@ -0,0 +1,98 @@
+use std::collections::VecDeque;
+
+use crate::process::Proc;
+use crate::traits::*;
+
+// Stops kept in the history of each tracked memory
+const MAX_HISTORY: usize = 1000;
+
+// Memory re-read at every stop, 'track'
+pub struct Tracked {
+    pub id: usize,
+    pub addr: u64,
+    pub size: usize,
+
+    // display format, one of x, d, u, c and f(float)
+    pub fmt: char,
+
+    // symbol expression the address came from, resolved again on relocation
+    pub location: Option<String>,
+
+    // (stop number, value) of the latest stops, None if unreadable
+    pub history: VecDeque<(usize, Option<Vec<u8>>)>,
+}
+
+impl Tracked {
+    // Value at the last stop, None if never read
+    pub fn last(&self) -> Option<&Option<Vec<u8>>> {
+        self.history.back().map(|(_, value)| value)
+    }
+
+    fn record(&mut self, stop: usize, value: Option<Vec<u8>>) {
+        if self.history.len() == MAX_HISTORY {
+            self.history.pop_front();
+        }
+        self.history.push_back((stop, value));
+    }
+}
+
+pub struct TrackTable {
+    next_id: usize,
+
+    // number of the stops so far, the history refers to them
+    stops: usize,
+    list: Vec<Tracked>,
+}
+
+impl TrackTable {
+    pub fn new() -> Self {
+        TrackTable { next_id: 1, stops: 0, list: Vec::new() }
+    }
+
+    // The value is read right away if the process is alive
+    pub fn add(&mut self, proc: &Proc, addr: u64, size: usize, fmt: char, location: Option<String>) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+        let mut tracked = Tracked { id, addr, size, fmt, location, history: VecDeque::new() };
+        if proc.valid() {
+            tracked.record(self.stops, proc.read_memory(addr, size).ok());
+        }
+        self.list.push(tracked);
+        id
+    }
+
+    pub fn delete(&mut self, id: usize) -> Result<Tracked, ()> {
+        let index = self.list.iter().position(|tracked| tracked.id == id).ok_or(())?;
+        Ok(self.list.remove(index))
+    }
+
+    pub fn get(&self, id: usize) -> Option<&Tracked> {
+        self.list.iter().find(|tracked| tracked.id == id)
+    }
+
+    pub fn ids(&self) -> Vec<usize> {
+        self.list.iter().map(|tracked| tracked.id).collect()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Tracked> {
+        self.list.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> std::slice::IterMut<'_, Tracked> {
+        self.list.iter_mut()
+    }
+
+    // Reads every tracked memory at a new stop, returns the ids whose value has changed
+    pub fn update(&mut self, proc: &Proc) -> Vec<usize> {
+        self.stops += 1;
+        let mut changed = Vec::new();
+        for tracked in self.list.iter_mut() {
+            let value = proc.read_memory(tracked.addr, tracked.size).ok();
+            if tracked.last().is_some_and(|last| *last != value) {
+                changed.push(tracked.id);
+            }
+            tracked.record(self.stops, value);
+        }
+        changed
+    }
+}
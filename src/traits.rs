@@ -1,5 +1,8 @@
 use std::path::PathBuf;
 
+use crate::arch::{Regs, RegisterFile};
+use crate::process::maps::MemoryMap;
+
 pub trait Valid {
     fn valid(&self) -> bool;
     fn invalid(&self) -> bool { !self.valid() }
@@ -11,3 +14,40 @@ impl Valid for Option<PathBuf> {
         self.as_ref().unwrap().exists() && self.as_ref().unwrap().is_file()
     }
 }
+
+// What the commands inspect, the live process or a core file of one
+pub trait Target {
+    // Process id, the one dumped for core files
+    fn pid(&self) -> i32;
+
+    // Current thread, registers are read from it
+    fn tid(&self) -> i32;
+
+    // Every thread id, the current one included
+    fn tids(&self) -> Vec<i32>;
+
+    fn get_exe(&self) -> &PathBuf;
+
+    // Memory regions as of the last stop
+    fn maps(&self) -> &MemoryMap;
+
+    fn read_memory(&self, addr: u64, len: usize) -> Result<Vec<u8>, ()>;
+    fn write_memory(&self, addr: u64, data: &[u8]) -> Result<(), ()>;
+
+    fn thread_regs(&self, tid: i32) -> Result<Regs, ()>;
+    fn set_thread_regs(&self, tid: i32, regs: &Regs) -> Result<(), ()>;
+
+    fn getregs(&self) -> Result<Regs, ()> {
+        self.thread_regs(self.tid())
+    }
+
+    fn getreg(&self, regname: &str) -> Result<u64, ()> {
+        self.getregs()?.get(regname).ok_or(())
+    }
+
+    fn setreg(&self, regname: &str, value: u64) -> Result<(), ()> {
+        let mut regs = self.getregs()?;
+        regs.set(regname, value)?;
+        self.set_thread_regs(self.tid(), &regs)
+    }
+}
@@ -0,0 +1,244 @@
+use crate::arch::{Regs, RegisterFile, WatchKind, WatchSlot};
+use crate::process::Proc;
+use crate::traits::Target;
+
+pub struct Watchpoint {
+    // shares the numbers with breakpoints
+    pub id: usize,
+    pub addr: u64,
+    pub len: usize,
+    pub kind: WatchKind,
+    pub enabled: bool,
+    pub hits: usize,
+
+    // symbol expression the address came from, resolved again on relocation
+    pub location: Option<String>,
+
+    // contents at the last stop, None if unknown or unreadable
+    pub value: Option<Vec<u8>>,
+
+    // checked by single-stepping the process, when the debug registers are not enough
+    pub software: bool,
+}
+
+impl Watchpoint {
+    // Splits the watched range into the pieces each debug register can take
+    fn slots(&self) -> Vec<WatchSlot> {
+        let kind = match self.kind {
+            WatchKind::Read if !Regs::WATCH_READ_ONLY => WatchKind::Access,
+            kind => kind,
+        };
+        let mut slots = Vec::new();
+        let (mut addr, mut left) = (self.addr, self.len);
+        while left > 0 {
+            let len = Regs::watch_chunk(addr, left);
+            slots.push(WatchSlot { addr, len, kind });
+            addr += len as u64;
+            left -= len;
+        }
+        slots
+    }
+
+    // 'hw watchpoint', 'read watchpoint' or 'acc watchpoint' as 'info breakpoints' shows
+    pub fn type_name(&self) -> &'static str {
+        match self.kind {
+            WatchKind::Write if self.software => "watchpoint",
+            WatchKind::Write => "hw watchpoint",
+            WatchKind::Read => "read watchpoint",
+            WatchKind::Access => "acc watchpoint",
+        }
+    }
+}
+
+// Value change reported when the watchpoint is hit
+pub struct Hit {
+    pub id: usize,
+    pub old: Option<Vec<u8>>,
+    pub new: Option<Vec<u8>>,
+}
+
+pub struct WatchpointTable {
+    list: Vec<Watchpoint>,
+}
+
+impl WatchpointTable {
+    pub fn new() -> Self {
+        WatchpointTable { list: Vec::new() }
+    }
+
+    // @id is taken from the breakpoint table so that numbers don't collide
+    pub fn add(&mut self, id: usize, addr: u64, len: usize, kind: WatchKind, location: Option<String>) {
+        self.list.push(Watchpoint {
+            id, addr, len, kind, enabled: true, hits: 0, location, value: None, software: false
+        });
+    }
+
+    pub fn delete(&mut self, id: usize) -> Result<Watchpoint, ()> {
+        let index = self.list.iter().position(|wp| wp.id == id).ok_or(())?;
+        Ok(self.list.remove(index))
+    }
+
+    pub fn get_mut(&mut self, id: usize) -> Option<&mut Watchpoint> {
+        self.list.iter_mut().find(|wp| wp.id == id)
+    }
+
+    pub fn ids(&self) -> Vec<usize> {
+        self.list.iter().map(|wp| wp.id).collect()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Watchpoint> {
+        self.list.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> std::slice::IterMut<'_, Watchpoint> {
+        self.list.iter_mut()
+    }
+
+    // Debug registers taken by the enabled watchpoints, in order of the registers
+    fn slots(&self) -> Vec<(usize, WatchSlot)> {
+        self.list.iter()
+            .filter(|wp| wp.enabled && !wp.software)
+            .flat_map(|wp| wp.slots().into_iter().map(move |slot| (wp.id, slot)))
+            .collect()
+    }
+
+    // Whether the process has to be single-stepped for the software watchpoints
+    pub fn software(&self) -> bool {
+        self.list.iter().any(|wp| wp.enabled && wp.software)
+    }
+
+    // Moves the latest write watchpoints to software until the others fit in @available
+    // debug registers, returns the ids moved. Reads and accesses can't be watched in software.
+    fn fit(&mut self, available: usize) -> Result<Vec<usize>, ()> {
+        let mut moved = Vec::new();
+        while self.slots().len() > available {
+            let wp = self.list.iter_mut()
+                .rev()
+                .find(|wp| wp.enabled && !wp.software && wp.kind == WatchKind::Write)
+                .ok_or(())?;
+            wp.software = true;
+            moved.push(wp.id);
+        }
+        Ok(moved)
+    }
+
+    // Watchpoint of the debug register @index
+    pub fn find_by_slot(&mut self, index: usize) -> Option<&mut Watchpoint> {
+        let id = self.slots().get(index)?.0;
+        self.get_mut(id)
+    }
+
+    // Programs the debug registers of the threads @tids, reading the initial values
+    // of the watchpoints set before the process started. Returns the ids of the
+    // watchpoints moved to software for lack of the debug registers.
+    pub fn sync(&mut self, proc: &Proc, tids: &[i32]) -> Result<Vec<usize>, ()> {
+        for wp in self.list.iter_mut().filter(|wp| wp.value.is_none()) {
+            wp.value = proc.read_memory(wp.addr, wp.len).ok();
+        }
+        let moved = match tids.first() {
+            Some(tid) => self.fit(Regs::watch_slots(*tid)?)?,
+            None => Vec::new(),
+        };
+        let slots: Vec<WatchSlot> = self.slots().into_iter().map(|(_, slot)| slot).collect();
+        for tid in tids {
+            Regs::set_watch_slots(*tid, &slots)?;
+        }
+        Ok(moved)
+    }
+
+    // Compares the software watchpoints with their last values, the first one
+    // changed is returned as a hit.
+    pub fn check_software(&mut self, proc: &Proc) -> Option<Hit> {
+        for wp in self.list.iter_mut().filter(|wp| wp.enabled && wp.software) {
+            let new = proc.read_memory(wp.addr, wp.len).ok();
+            if new != wp.value {
+                let old = std::mem::replace(&mut wp.value, new.clone());
+                wp.hits += 1;
+                return Some(Hit { id: wp.id, old, new });
+            }
+        }
+        None
+    }
+
+    // The process is gone, the values are read again from the next one
+    pub fn reset(&mut self) {
+        for wp in self.list.iter_mut() {
+            wp.value = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn watchpoint(addr: u64, len: usize, kind: WatchKind) -> Watchpoint {
+        Watchpoint { id: 1, addr, len, kind, enabled: true, hits: 0, location: None, value: None, software: false }
+    }
+
+    #[test]
+    fn slots_cover_the_range() {
+        for addr in 0x1000..0x1010 {
+            for len in 1..=20 {
+                let slots = watchpoint(addr, len, WatchKind::Write).slots();
+                let mut next = addr;
+                for slot in slots.iter() {
+                    assert_eq!(slot.addr, next);
+                    assert!(slot.len >= 1 && slot.len <= 8);
+
+                    // no debug register watches across a doubleword
+                    assert_eq!(slot.addr / 8, (slot.addr + slot.len as u64 - 1) / 8);
+                    next += slot.len as u64;
+                }
+                assert_eq!(next, addr + len as u64);
+            }
+        }
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    #[test]
+    fn slots_are_aligned_to_length() {
+        let slots = watchpoint(0x1003, 7, WatchKind::Write).slots();
+        let split: Vec<(u64, usize)> = slots.iter().map(|slot| (slot.addr, slot.len)).collect();
+        assert_eq!(split, vec![(0x1003, 1), (0x1004, 4), (0x1008, 2)]);
+    }
+
+    #[cfg(target_arch = "aarch64")]
+    #[test]
+    fn slots_are_split_by_doubleword() {
+        let slots = watchpoint(0x1003, 7, WatchKind::Write).slots();
+        let split: Vec<(u64, usize)> = slots.iter().map(|slot| (slot.addr, slot.len)).collect();
+        assert_eq!(split, vec![(0x1003, 5), (0x1008, 2)]);
+    }
+
+    #[test]
+    fn read_is_watched_as_access_without_read_only() {
+        let slots = watchpoint(0x1000, 4, WatchKind::Read).slots();
+        let expected = if Regs::WATCH_READ_ONLY { WatchKind::Read } else { WatchKind::Access };
+        assert!(slots.iter().all(|slot| slot.kind == expected));
+    }
+
+    #[test]
+    fn latest_writes_go_to_software() {
+        let mut table = WatchpointTable::new();
+        table.add(1, 0x1000, 8, WatchKind::Write, None);
+        table.add(2, 0x2000, 8, WatchKind::Read, None);
+        table.add(3, 0x3000, 16, WatchKind::Write, None);
+        assert_eq!(table.fit(2), Ok(vec![3]));
+        assert!(table.software());
+        assert_eq!(table.slots().iter().map(|(id, _)| *id).collect::<Vec<_>>(), vec![1, 2]);
+
+        // reads can't be watched in software
+        assert_eq!(table.fit(0), Err(()));
+    }
+
+    #[test]
+    fn slot_index_finds_watchpoint() {
+        let mut table = WatchpointTable::new();
+        table.add(4, 0x1000, 16, WatchKind::Write, None);
+        table.add(7, 0x2000, 1, WatchKind::Write, None);
+        assert_eq!(table.find_by_slot(1).map(|wp| wp.id), Some(4));
+        assert_eq!(table.find_by_slot(2).map(|wp| wp.id), Some(7));
+        assert!(table.find_by_slot(3).is_none());
+    }
+}